@@ -10,9 +10,13 @@ use crate::ast::visitor::Walkable;
 use crate::escape_codes::Color;
 use crate::Ast;
 
+/// The tree with coloured headers, or as plain text with the alternate flag (`{:#}`).
 impl Display for Program {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        let mut display = AstDisplay::new();
+        let mut display = match f.alternate() {
+            true => AstDisplay::plain(),
+            false => AstDisplay::new(),
+        };
         write!(f, "{}", display.build(self))
     }
 }
@@ -20,6 +24,7 @@ impl Display for Program {
 pub struct AstDisplay {
     indent: usize,
     result: String,
+    color: bool,
 }
 
 impl AstDisplay {
@@ -27,6 +32,22 @@ impl AstDisplay {
         Self {
             indent: 0,
             result: String::new(),
+            color: true,
+        }
+    }
+
+    /// Without escape codes, for writing to files.
+    pub fn plain() -> Self {
+        Self {
+            color: false,
+            ..Self::new()
+        }
+    }
+
+    fn paint(&self, color: Color) -> String {
+        match self.color {
+            true => color.to_string(),
+            false => String::new(),
         }
     }
 
@@ -48,23 +69,34 @@ impl AstDisplay {
     fn add_statement_header(&mut self, text: &str) {
         self.add_newline();
         self.add_padding();
-        self.result
-            .push_str(&format!("{}{}:{} ", Color::Magenta, text, Color::Reset));
+        self.result.push_str(&format!(
+            "{}{}:{} ",
+            self.paint(Color::Magenta),
+            text,
+            self.paint(Color::Reset)
+        ));
     }
 
     fn add_expression_header(&mut self, text: &str) {
         self.add_newline();
         self.add_padding();
-        self.result
-            .push_str(&format!("{}{}:{} ", Color::Cyan, text, Color::Reset));
+        self.result.push_str(&format!(
+            "{}{}:{} ",
+            self.paint(Color::Cyan),
+            text,
+            self.paint(Color::Reset)
+        ));
     }
 
     fn add_block_end(&mut self) {
         self.indent -= 2;
         self.add_newline();
         self.add_padding();
-        self.result
-            .push_str(&format!("{}End{} ", Color::Magenta, Color::Reset));
+        self.result.push_str(&format!(
+            "{}End{} ",
+            self.paint(Color::Magenta),
+            self.paint(Color::Reset)
+        ));
         self.add_newline();
         self.add_padding();
     }
@@ -72,7 +104,7 @@ impl AstDisplay {
 
 impl Visitor for AstDisplay {
     fn visit_function(&mut self, func: &Function) {
-        self.add_statement_header("Function");
+        self.add_statement_header(if func.external { "Extern" } else { "Function" });
         self.result.push_str(&func.pattern.name);
 
         self.indent += 1;
@@ -0,0 +1,114 @@
+use alloc::string::String;
+
+use super::{Ast, Function, LiteralKind, Program};
+use crate::json;
+
+impl Program {
+    /// The tree as JSON, one object per node with its `kind` and byte `span`.
+    pub fn to_json(&self) -> String {
+        let functions: Vec<String> = self.functions.iter().map(function_json).collect();
+        format!("{{\"functions\": [{}]}}", functions.join(", "))
+    }
+}
+
+fn function_json(func: &Function) -> String {
+    let params: Vec<String> = func
+        .parameters
+        .iter()
+        .map(|p| json::string(&p.name))
+        .collect();
+    format!(
+        "{{\"kind\": \"Function\", \"name\": {}, \"return_type\": {}, \"parameters\": [{}], \"external\": {}, \"span\": {}, \"body\": {}}}",
+        json::string(&func.pattern.name),
+        json::string(&func.return_type.to_string()),
+        params.join(", "),
+        func.external,
+        json::span(&func.span),
+        ast_json(&func.body)
+    )
+}
+
+fn ast_json(ast: &Ast) -> String {
+    let span = match ast.span() {
+        Some(span) => json::span(&span),
+        None => "null".to_string(),
+    };
+    let fields = match ast {
+        Ast::Sequence(seq) => {
+            let body: Vec<String> = seq.expressions.iter().map(ast_json).collect();
+            format!("\"body\": [{}]", body.join(", "))
+        }
+        Ast::Definition(def) => format!(
+            "\"name\": {}, \"value\": {}",
+            json::string(&def.pattern.name),
+            ast_json(&def.value)
+        ),
+        Ast::Assignment(def) => format!(
+            "\"name\": {}, \"value\": {}",
+            json::string(&def.pattern.name),
+            ast_json(&def.value)
+        ),
+        Ast::Return(value) => format!("\"value\": {}", ast_json(value)),
+        Ast::If(if_expr) => format!(
+            "\"condition\": {}, \"then\": {}",
+            ast_json(&if_expr.condition),
+            ast_json(&if_expr.then)
+        ),
+        Ast::While(while_expr) => format!(
+            "\"condition\": {}, \"then\": {}",
+            ast_json(&while_expr.condition),
+            ast_json(&while_expr.then)
+        ),
+        Ast::Binary(bin) => format!(
+            "\"op\": \"{:?}\", \"left\": {}, \"right\": {}",
+            bin.op,
+            ast_json(&bin.left),
+            ast_json(&bin.right)
+        ),
+        Ast::Unary(un) => format!(
+            "\"op\": \"{:?}\", \"operand\": {}",
+            un.op,
+            ast_json(&un.operand)
+        ),
+        Ast::Literal(lit) => match &lit.kind {
+            LiteralKind::Int(i) => format!("\"type\": \"int\", \"value\": {}", i),
+            LiteralKind::Char(ch) => {
+                format!(
+                    "\"type\": \"char\", \"value\": {}",
+                    json::string(&ch.to_string())
+                )
+            }
+            LiteralKind::String(s) => {
+                format!("\"type\": \"string\", \"value\": {}", json::string(s))
+            }
+        },
+        Ast::Variable(var) => format!("\"name\": {}", json::string(&var.name)),
+        Ast::Call(call) => {
+            let args: Vec<String> = call.arguments.iter().map(ast_json).collect();
+            format!(
+                "\"name\": {}, \"arguments\": [{}]",
+                json::string(&call.pattern.name),
+                args.join(", ")
+            )
+        }
+        Ast::Error => String::new(),
+    };
+    let kind = match ast {
+        Ast::Sequence(_) => "Sequence",
+        Ast::Definition(_) => "Definition",
+        Ast::Assignment(_) => "Assignment",
+        Ast::Return(_) => "Return",
+        Ast::If(_) => "If",
+        Ast::While(_) => "While",
+        Ast::Binary(_) => "Binary",
+        Ast::Unary(_) => "Unary",
+        Ast::Literal(_) => "Literal",
+        Ast::Variable(_) => "Variable",
+        Ast::Call(_) => "Call",
+        Ast::Error => "Error",
+    };
+    match fields.is_empty() {
+        true => format!("{{\"kind\": \"{}\", \"span\": {}}}", kind, span),
+        false => format!("{{\"kind\": \"{}\", \"span\": {}, {}}}", kind, span, fields),
+    }
+}
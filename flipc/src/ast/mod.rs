@@ -5,6 +5,7 @@ use crate::lexer::Token;
 use crate::span::Span;
 
 mod display;
+mod json;
 pub mod ptr;
 mod types;
 pub mod visitor;
@@ -22,6 +23,8 @@ pub struct Function {
     pub pattern: Pattern,
     pub parameters: Vec<Pattern>,
     pub body: Ast,
+    // declared with `extern`, defined in another object
+    pub external: bool,
     pub span: Span,
 }
 
@@ -131,6 +134,23 @@ impl Ast {
     pub fn return_expr(value: Ast) -> Ast {
         Ast::Return(P(value))
     }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Ast::Sequence(seq) => Some(seq.span),
+            Ast::Definition(def) => Some(def.span),
+            Ast::Assignment(def) => Some(def.span),
+            Ast::Return(value) => value.span(),
+            Ast::If(if_expr) => Some(if_expr.span),
+            Ast::While(while_expr) => Some(while_expr.span),
+            Ast::Binary(bin) => Some(bin.span),
+            Ast::Unary(un) => Some(un.span),
+            Ast::Literal(lit) => Some(lit.span),
+            Ast::Variable(var) => Some(var.span),
+            Ast::Call(call) => Some(call.span),
+            Ast::Error => None,
+        }
+    }
 }
 #[derive(Debug, Clone)]
 pub struct Sequence {
@@ -0,0 +1,255 @@
+use std::fmt;
+use std::path::Path;
+
+use flipc::frontend::{Code, ErrorFormat, Level, Levels};
+use flipc::OptLevel;
+
+const EMIT_KINDS: [&str; 6] = ["tokens", "ast", "ast-json", "symbols", "asm", "bin"];
+
+pub enum ArgsError {
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+    MultipleInputs,
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::UnknownFlag(s) => write!(f, "unknown flag: {}", s),
+            ArgsError::MissingValue(s) => write!(f, "missing value for flag: {}", s),
+            ArgsError::InvalidValue(s, v) => write!(f, "invalid value for flag {}: {}", s, v),
+            ArgsError::MultipleInputs => write!(f, "multiple input files provided"),
+            ArgsError::Conflict(a, b) => write!(f, "{} can't be used with {}", a, b),
+        }
+    }
+}
+
+pub struct Args {
+    pub bin_name: String,
+    pub input_file: String,
+    pub output_file: Option<String>,
+    pub object: bool,
+    // requested outputs, with an optional path
+    pub emit: Vec<(String, Option<String>)>,
+    pub entry: String,
+    pub load_offset: u32,
+    pub opt_level: OptLevel,
+    pub warnings: Levels,
+    pub error_format: ErrorFormat,
+    pub run: bool,
+    show_help: bool,
+}
+
+impl Args {
+    pub fn validate(&self) -> bool {
+        if self.show_help {
+            return false;
+        }
+        !self.input_file.is_empty()
+    }
+
+    /// True if `kind` is written. Without `--emit` that is the executable, unless it's only run.
+    pub fn emits(&self, kind: &str) -> bool {
+        match self.emit.is_empty() {
+            true => kind == "bin" && (!self.run || self.output_file.is_some()),
+            false => self.emit.iter().any(|(k, _)| k == kind),
+        }
+    }
+
+    /// Where to write `kind`: the `--emit` path, the `-o` path when it's the executable or the
+    /// only output, otherwise `<input name>.<kind>` or `<input name>.o` for the executable.
+    pub fn emit_path(&self, kind: &str) -> Option<String> {
+        if !self.emits(kind) {
+            return None;
+        }
+        let path = self
+            .emit
+            .iter()
+            .find(|(k, _)| k == kind)
+            .and_then(|(_, path)| path.clone());
+        let output = match kind == "bin" || self.emit.len() == 1 {
+            true => self.output_file.clone(),
+            false => None,
+        };
+        Some(path.or(output).unwrap_or_else(|| {
+            let extension = match kind {
+                "bin" => "o",
+                "ast-json" => "ast.json",
+                "symbols" => "symbols.json",
+                x => x,
+            };
+            format!("{}.{}", self.stem(), extension)
+        }))
+    }
+
+    fn stem(&self) -> &str {
+        Path::new(&self.input_file)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("out")
+    }
+
+    pub fn usage(&self) -> String {
+        format!(
+            "usage: {} [OPTIONS] <input file>
+
+options, values can also be given as --flag=value:
+    -h, --help\t\t\tShow this message.
+    -o, --output <file>\t\tWrite the executable, or the only --emit output, to <file>.
+    -c, --object\t\tWrite a relocatable object for `link`.
+    --emit <kind>[=<file>],...\tOutputs to write, from tokens, ast, ast-json, symbols, asm and bin.
+\t\t\t\tThe default file is <input>.<kind>, `-` writes to stdout.
+    -e, --entry <function>\tFunction the program starts at, default main.
+    -x, --load-offset <addr>\tAddress the program is loaded and started at.
+    -O0, -O1, -O2\t\tOptimisation level: none, peephole, and constant folding.
+    -W, --warnings <level>[=<code>,...]
+\t\t\t\tReport warnings, or only the named ones: allow, warn or deny.
+\t\t\t\tSource comments such as `# allow(unused_variable)` take precedence.
+    --error-format <format>\tPrint diagnostics as human or json, one object per line.
+    --run\t\t\tRun the program after compiling it, exiting with its exit code.
+
+",
+            self.bin_name
+        )
+    }
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            bin_name: ".".to_string(),
+            input_file: String::new(),
+            output_file: None,
+            object: false,
+            emit: Vec::new(),
+            entry: "main".to_string(),
+            load_offset: 0x0,
+            opt_level: OptLevel::None,
+            warnings: Levels::default(),
+            error_format: ErrorFormat::Human,
+            run: false,
+            show_help: false,
+        }
+    }
+}
+
+fn parse_address(flag: &str, value: &str) -> Result<u32, ArgsError> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| ArgsError::InvalidValue(flag.to_string(), value.to_string()))
+}
+
+/// Function addresses are loaded with a 12 bit immediate, so the code has to start below 0x1000,
+/// on an instruction boundary.
+fn parse_load_offset(flag: &str, value: &str) -> Result<u32, ArgsError> {
+    match parse_address(flag, value)? {
+        offset if offset % 2 == 0 && offset <= 0xfff => Ok(offset),
+        _ => Err(ArgsError::InvalidValue(flag.to_string(), value.to_string())),
+    }
+}
+
+fn parse_warnings(out: &mut Args, flag: &str, value: &str) -> Result<(), ArgsError> {
+    let invalid = |value: &str| ArgsError::InvalidValue(flag.to_string(), value.to_string());
+    let (level, codes) = match value.split_once('=') {
+        Some((level, codes)) => (level, Some(codes)),
+        None => (value, None),
+    };
+    let level = match level {
+        "allow" => Level::Allow,
+        "warn" => Level::Warn,
+        "deny" => Level::Deny,
+        _ => return Err(invalid(value)),
+    };
+    match codes {
+        None => out.warnings.set_all(level),
+        Some(codes) => {
+            for code in codes.split(',') {
+                let code: Code = code.parse().map_err(|_| invalid(code))?;
+                out.warnings.set(code, level).map_err(|e| invalid(&e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_emit(out: &mut Args, value: &str) -> Result<(), ArgsError> {
+    for kind in value.split(',') {
+        let (kind, path) = match kind.split_once('=') {
+            Some((kind, path)) => (kind, Some(path.to_string())),
+            None => (kind, None),
+        };
+        if !EMIT_KINDS.contains(&kind) {
+            return Err(ArgsError::InvalidValue(
+                "emit".to_string(),
+                kind.to_string(),
+            ));
+        }
+        out.emit.push((kind.to_string(), path));
+    }
+    Ok(())
+}
+
+pub fn process(args: &[String]) -> Result<Args, ArgsError> {
+    let mut out = Args {
+        bin_name: args[0].to_string(),
+        ..Default::default()
+    };
+
+    let mut iter = args[1..].iter();
+    while let Some(a) = iter.next() {
+        let (flag, mut inline) = match a.strip_prefix("--").or_else(|| a.strip_prefix('-')) {
+            Some(flag) if !flag.is_empty() => match flag.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (flag, None),
+            },
+            // `-` reads stdin
+            _ => {
+                if !out.input_file.is_empty() {
+                    return Err(ArgsError::MultipleInputs);
+                }
+                out.input_file = a.to_string();
+                continue;
+            }
+        };
+        let mut value = || {
+            inline
+                .take()
+                .or_else(|| iter.next().map(|s| s.as_str()))
+                .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))
+        };
+        match flag {
+            "o" | "output" => out.output_file = Some(value()?.to_string()),
+            "c" | "object" => out.object = true,
+            "emit" => parse_emit(&mut out, value()?)?,
+            "e" | "entry" => out.entry = value()?.to_string(),
+            "x" | "load-offset" => out.load_offset = parse_load_offset(flag, value()?)?,
+            "O0" => out.opt_level = OptLevel::None,
+            "O1" => out.opt_level = OptLevel::Peephole,
+            "O2" => out.opt_level = OptLevel::Full,
+            "W" | "warnings" => parse_warnings(&mut out, flag, value()?)?,
+            "error-format" => {
+                out.error_format = match value()? {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    x => return Err(ArgsError::InvalidValue(flag.to_string(), x.to_string())),
+                }
+            }
+            "run" => out.run = true,
+            "h" | "help" => out.show_help = true,
+            x => return Err(ArgsError::UnknownFlag(x.to_string())),
+        }
+    }
+
+    // objects are placed by `link`
+    if out.object && out.load_offset != 0 {
+        return Err(ArgsError::Conflict("--object", "--load-offset"));
+    }
+    if out.object && out.run {
+        return Err(ArgsError::Conflict("--object", "--run"));
+    }
+    Ok(out)
+}
@@ -0,0 +1,164 @@
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+
+use flipc::frontend::{self, ErrorFormat, Options};
+use flipc::{to_asm, to_object, CodeGenerator, CodegenOptions, Pass};
+use flipvm::exe::Executable;
+use flipvm::{layout, DebugInfo, Machine, Register};
+
+mod args;
+
+fn main() -> Result<(), String> {
+    let args_raw: Vec<_> = env::args().collect();
+    let args = args::process(&args_raw).map_err(|e| format!("{}", e))?;
+
+    if !args.validate() {
+        println!("{}", args.usage());
+        return Ok(());
+    }
+
+    let mut reader: Box<dyn Read> = match args.input_file.as_str() {
+        "-" => Box::new(std::io::stdin()),
+        _ => Box::new(File::open(&args.input_file).map_err(|e| format!("failed to open: {}", e))?),
+    };
+
+    let mut content = Vec::new();
+    reader
+        .read_to_end(&mut content)
+        .map_err(|e| format!("failed to read: {}", e))?;
+    let code = String::from_utf8(content).map_err(|e| format!("failed to parse: {}", e))?;
+
+    if let Some(path) = args.emit_path("tokens") {
+        write_output(&path, frontend::tokens(&code).as_bytes())?;
+    }
+
+    let options = Options {
+        entry: args.entry.clone(),
+        warnings: args.warnings.clone(),
+        error_format: args.error_format,
+    };
+    let (root, st) = match frontend::check_with(&code, &options) {
+        Ok(checked) => checked,
+        // keep stderr to the diagnostics
+        Err(_) if args.error_format == ErrorFormat::Json => process::exit(1),
+        Err(e) => return Err(format!("{}", e)),
+    };
+    if let Some(path) = args.emit_path("ast") {
+        write_output(&path, format!("{:#}\n", root).as_bytes())?;
+    }
+    if let Some(path) = args.emit_path("ast-json") {
+        write_output(&path, format!("{}\n", root.to_json()).as_bytes())?;
+    }
+    if let Some(path) = args.emit_path("symbols") {
+        write_output(&path, format!("{}\n", st.to_json()).as_bytes())?;
+    }
+    if !args.emits("asm") && !args.emits("bin") && !args.run {
+        return Ok(());
+    }
+
+    let codegen = CodegenOptions {
+        offset: args.load_offset,
+        entry: args.entry.clone(),
+        opt_level: args.opt_level,
+    };
+    let (instructions, mut debug_info, line_table, relocations) =
+        CodeGenerator::run((&root, &st, &codegen))?;
+    line_table.resolve_into(&code, &mut debug_info);
+    debug_info.file = Some(args.input_file.clone());
+
+    if let Some(path) = args.emit_path("asm") {
+        let asm = to_asm(&instructions, &debug_info, &relocations, args.load_offset);
+        write_output(&path, asm.as_bytes())?;
+    }
+    if !args.emits("bin") && !args.run {
+        return Ok(());
+    }
+
+    let object = to_object(&instructions, &debug_info, relocations);
+    if args.object {
+        if let Some(path) = args.emit_path("bin") {
+            write_output(&path, &object.to_bytes()?)?;
+        }
+        return Ok(());
+    }
+    if let Some(name) = object.undefined().first() {
+        return Err(format!(
+            "undefined reference to {}, compile with -c and link it",
+            name
+        ));
+    }
+
+    let mut bytecode: Vec<u8> = Vec::new();
+    for i in instructions {
+        let raw = i.encode_u16();
+        bytecode.push((raw & 0xff) as u8);
+        bytecode.push((raw >> 8) as u8);
+    }
+
+    // function addresses are loaded with a 12 bit immediate
+    let end = args.load_offset as usize + bytecode.len();
+    if end > 0x1000 {
+        return Err(format!(
+            "program loaded at 0x{:X} ends at 0x{:X}, past 0xfff",
+            args.load_offset, end
+        ));
+    }
+
+    let mut exe = Executable::from_raw(bytecode, args.load_offset);
+    exe.symbols = Some(debug_info.symbols());
+
+    if let Some(path) = args.emit_path("bin") {
+        write_executable(&path, &exe, &debug_info)?;
+    }
+    if args.run {
+        process::exit(run(&exe, &debug_info)?);
+    }
+    Ok(())
+}
+
+/// Writes `exe` to `path` with its `.sym` and `.dbg` files next to it.
+fn write_executable(path: &str, exe: &Executable, debug_info: &DebugInfo) -> Result<(), String> {
+    write_output(path, &exe.to_bytes()?)?;
+    if path == "-" {
+        return Ok(());
+    }
+
+    // function addresses for `vm --symbols`
+    let symbols_file = Path::new(path).with_extension("sym");
+    std::fs::write(&symbols_file, debug_info.symbols().to_string())
+        .map_err(|e| format!("failed to write: {}", e))?;
+
+    // source locations, picked up by `vm` from next to the program
+    let debug_file = Path::new(path).with_extension("dbg");
+    std::fs::write(&debug_file, debug_info.to_string())
+        .map_err(|e| format!("failed to write: {}", e))
+}
+
+/// Runs `exe` on the machine `vm` would run it on until it halts, returning its exit code.
+fn run(exe: &Executable, debug_info: &DebugInfo) -> Result<i32, String> {
+    let mut vm = Machine::with_std_syscalls();
+    layout::map_std(&mut vm)?;
+    exe.load(&mut vm)?;
+    while !vm.is_halted() {
+        if let Err(fault) = vm.step() {
+            let pc = vm.get_register(Register::PC);
+            return Err(match debug_info.describe(pc) {
+                Some(location) => format!("fault @ 0x{:X} ({}): {}", pc, location, fault),
+                None => format!("fault @ 0x{:X}: {}", pc, fault),
+            });
+        }
+    }
+    Ok((vm.exit_code().unwrap_or(0) & 0xff) as i32)
+}
+
+/// Writes to `path`, or to stdout for `-`.
+fn write_output(path: &str, bytes: &[u8]) -> Result<(), String> {
+    match path {
+        "-" => std::io::stdout().lock().write_all(bytes),
+        _ => File::create(path).and_then(|mut f| f.write_all(bytes)),
+    }
+    .map_err(|e| format!("failed to write {}: {}", path, e))
+}
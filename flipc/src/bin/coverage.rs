@@ -0,0 +1,151 @@
+use std::env;
+use std::fs::File;
+use std::io::{stdin, Read};
+use std::process;
+
+use flipc::coverage::CoverageReport;
+use flipc::{frontend, CodeGenerator, CodegenOptions, Pass};
+use flipvm::{exe, layout, Machine, Register};
+
+const DEFAULT_OUTPUT: &str = "lcov.info";
+
+const EXIT_USAGE: i32 = 64;
+const EXIT_NO_INPUT: i32 = 66;
+const EXIT_HOST_FAULT: i32 = 70;
+
+struct Args {
+    target_file: String,
+    output_file: String,
+}
+
+fn usage(bin_name: &str) -> String {
+    format!(
+        "usage: {} [OPTIONS] <input file>
+
+Compiles and runs a Flip program, then writes its line and branch coverage.
+
+options:
+    -h, --help\t\tShow this message.
+    -o, --output <file>\tWrite the lcov tracefile to <file>, default `{}`.
+",
+        bin_name, DEFAULT_OUTPUT
+    )
+}
+
+fn process_args(args: &[String]) -> Result<Option<Args>, String> {
+    let mut target_file = None;
+    let mut output_file = DEFAULT_OUTPUT.to_string();
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => {
+                output_file = iter
+                    .next()
+                    .ok_or_else(|| format!("missing value for flag: {}", arg))?
+                    .to_string();
+            }
+            x if x.starts_with('-') && x != "-" => return Err(format!("unknown flag: {}", x)),
+            x => {
+                if target_file.is_some() {
+                    return Err("extra input file(s)".to_string());
+                }
+                target_file = Some(x.to_string());
+            }
+        }
+    }
+
+    Ok(target_file.map(|target_file| Args {
+        target_file,
+        output_file,
+    }))
+}
+
+fn read_source(path: &str) -> Result<String, String> {
+    let mut reader: Box<dyn Read> = match path {
+        "-" => Box::new(stdin()),
+        _ => Box::new(File::open(path).map_err(|e| format!("failed to open: {}", e))?),
+    };
+    let mut code = String::new();
+    reader
+        .read_to_string(&mut code)
+        .map_err(|e| format!("failed to read: {}", e))?;
+    Ok(code)
+}
+
+fn main() {
+    let args_raw: Vec<_> = env::args().collect();
+    let args = match process_args(&args_raw) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            eprintln!("{}", usage(&args_raw[0]));
+            process::exit(EXIT_USAGE);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    let code = read_source(&args.target_file).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_NO_INPUT);
+    });
+    let (root, st) = frontend::check(&code).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_NO_INPUT);
+    });
+    let (instructions, _, line_table, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(EXIT_NO_INPUT);
+        });
+
+    let bytecode: Vec<u8> = instructions
+        .iter()
+        .flat_map(|i| i.encode_u16().to_le_bytes())
+        .collect();
+    let mut vm = init_machine(bytecode).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_HOST_FAULT);
+    });
+    vm.enable_coverage();
+
+    let mut status = 0;
+    while !vm.is_halted() {
+        if let Err(fault) = vm.step() {
+            eprintln!("fault @ 0x{:X}: {}", vm.get_register(Register::PC), fault);
+            status = EXIT_HOST_FAULT;
+            break;
+        }
+    }
+    if vm.is_halted() {
+        status = (vm.exit_code().unwrap_or(0) & 0xff) as i32;
+    }
+
+    let coverage = vm.take_coverage().unwrap_or_default();
+    let report = CoverageReport::new(
+        &args.target_file,
+        &code,
+        &instructions,
+        0x0,
+        &line_table,
+        &coverage,
+    );
+    if let Err(e) = std::fs::write(&args.output_file, report.lcov()) {
+        eprintln!("failed to write: {}", e);
+        process::exit(EXIT_HOST_FAULT);
+    }
+    // keep the summary apart from the program's own output
+    eprint!("{}", report.summary());
+    process::exit(status);
+}
+
+// the same machine `vm` runs the program on
+fn init_machine(program: Vec<u8>) -> Result<Machine, String> {
+    let mut vm = Machine::with_std_syscalls();
+    layout::map_std(&mut vm)?;
+    exe::load(&mut vm, program)?;
+    Ok(vm)
+}
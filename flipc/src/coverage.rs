@@ -0,0 +1,173 @@
+//! Line and branch coverage of a Flip source file, from the addresses a `flipvm::Coverage`
+//! recorded and the `LineTable` of the generated code.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use flipvm::op::Instruction;
+use flipvm::Coverage;
+
+use crate::passes::codegen::LineTable;
+use crate::source::Source;
+
+/// How often an `if` or `while` condition was true and false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchCoverage {
+    pub line: usize,
+    pub when_true: u64,
+    pub when_false: u64,
+}
+
+impl BranchCoverage {
+    pub fn executed(&self) -> bool {
+        self.when_true + self.when_false > 0
+    }
+}
+
+#[derive(Debug)]
+pub struct CoverageReport {
+    file: String,
+    // execution count per 1-based line
+    lines: BTreeMap<usize, u64>,
+    branches: Vec<BranchCoverage>,
+}
+
+fn percent(hit: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        100.0 * hit as f64 / total as f64
+    }
+}
+
+impl CoverageReport {
+    /// Builds the report for `source`, compiled to `program` and loaded at `base`.
+    pub fn new(
+        file: &str,
+        source: &str,
+        program: &[Instruction],
+        base: u16,
+        line_table: &LineTable,
+        coverage: &Coverage,
+    ) -> Self {
+        let source = Source::new(source.to_string());
+        let mut lines = BTreeMap::new();
+        let mut branches = Vec::new();
+
+        for &(addr, span) in line_table.iter() {
+            let line = source.line_index(span.start) + 1;
+            let count = lines.entry(line).or_insert(0);
+            *count = coverage.hits(addr).max(*count);
+
+            let (taken, fell_through) = coverage.branch(addr);
+            let index = (addr.wrapping_sub(base) / 2) as usize;
+            // conditions are tested for zero, so the body runs when the branch falls through
+            let (when_true, when_false) = match program.get(index) {
+                Some(Instruction::BranchIf(_)) => (fell_through, taken),
+                Some(Instruction::BranchIfNot(_)) => (taken, fell_through),
+                _ => continue,
+            };
+            branches.push(BranchCoverage {
+                line,
+                when_true,
+                when_false,
+            });
+        }
+
+        Self {
+            file: file.to_string(),
+            lines,
+            branches,
+        }
+    }
+
+    /// The execution count of `line`, `None` when no code was generated for it.
+    pub fn line_count(&self, line: usize) -> Option<u64> {
+        self.lines.get(&line).copied()
+    }
+
+    pub fn branches(&self) -> &[BranchCoverage] {
+        &self.branches
+    }
+
+    /// Executed and instrumented lines.
+    pub fn lines_hit(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|&&count| count > 0).count();
+        (hit, self.lines.len())
+    }
+
+    /// Taken and total branch outcomes, each condition has a true and a false outcome.
+    pub fn branches_hit(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .iter()
+            .map(|b| (b.when_true > 0) as usize + (b.when_false > 0) as usize)
+            .sum();
+        (hit, self.branches.len() * 2)
+    }
+
+    /// The report as an lcov tracefile.
+    pub fn lcov(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{}", self.file);
+        for (block, branch) in self.branches.iter().enumerate() {
+            for (index, count) in [branch.when_true, branch.when_false].iter().enumerate() {
+                if branch.executed() {
+                    let _ = writeln!(out, "BRDA:{},{},{},{}", branch.line, block, index, count);
+                } else {
+                    let _ = writeln!(out, "BRDA:{},{},{},-", branch.line, block, index);
+                }
+            }
+        }
+        let (branches_hit, branches_found) = self.branches_hit();
+        let _ = writeln!(out, "BRF:{}", branches_found);
+        let _ = writeln!(out, "BRH:{}", branches_hit);
+        for (line, count) in &self.lines {
+            let _ = writeln!(out, "DA:{},{}", line, count);
+        }
+        let (lines_hit, lines_found) = self.lines_hit();
+        let _ = writeln!(out, "LF:{}", lines_found);
+        let _ = writeln!(out, "LH:{}", lines_hit);
+        let _ = writeln!(out, "end_of_record");
+        out
+    }
+
+    /// A short summary listing the lines never executed and the conditions never true or false.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        let (lines_hit, lines_found) = self.lines_hit();
+        let (branches_hit, branches_found) = self.branches_hit();
+        let _ = writeln!(
+            out,
+            "{}: lines {}/{} ({:.1}%), branches {}/{} ({:.1}%)",
+            self.file,
+            lines_hit,
+            lines_found,
+            percent(lines_hit, lines_found),
+            branches_hit,
+            branches_found,
+            percent(branches_hit, branches_found)
+        );
+
+        let missed: Vec<_> = self
+            .lines
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(line, _)| line.to_string())
+            .collect();
+        if !missed.is_empty() {
+            let _ = writeln!(out, "  lines never executed: {}", missed.join(", "));
+        }
+        for branch in &self.branches {
+            let never = match (branch.when_true, branch.when_false) {
+                (0, 0) => "never evaluated",
+                (0, _) => "never true",
+                (_, 0) => "never false",
+                _ => continue,
+            };
+            let _ = writeln!(out, "  line {}: condition {}", branch.line, never);
+        }
+        out
+    }
+}
@@ -0,0 +1,110 @@
+use core::fmt::{self, Display};
+use core::str::FromStr;
+
+use alloc::string::String;
+
+/// Stable name of a diagnostic, used to change how warnings are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    ExpectedToken,
+    UnexpectedToken,
+    ExpectedExpression,
+    IllegalToken,
+    UnterminatedString,
+    InvalidCharLiteral,
+    IntegerOverflow,
+    UnknownStatement,
+    InvalidOperator,
+    UnknownExpression,
+    VariableAlreadyDeclared,
+    FunctionAlreadyDeclared,
+    UndeclaredAssignment,
+    UndefinedReference,
+    ReferenceBeforeAssignment,
+    EntryNotFound,
+    MismatchedType,
+    ExpectedType,
+    UnusedVariable,
+    UnusedFunction,
+    EmptyBlock,
+    InvalidDirective,
+}
+
+impl Code {
+    pub const ALL: [Code; 22] = [
+        Code::ExpectedToken,
+        Code::UnexpectedToken,
+        Code::ExpectedExpression,
+        Code::IllegalToken,
+        Code::UnterminatedString,
+        Code::InvalidCharLiteral,
+        Code::IntegerOverflow,
+        Code::UnknownStatement,
+        Code::InvalidOperator,
+        Code::UnknownExpression,
+        Code::VariableAlreadyDeclared,
+        Code::FunctionAlreadyDeclared,
+        Code::UndeclaredAssignment,
+        Code::UndefinedReference,
+        Code::ReferenceBeforeAssignment,
+        Code::EntryNotFound,
+        Code::MismatchedType,
+        Code::ExpectedType,
+        Code::UnusedVariable,
+        Code::UnusedFunction,
+        Code::EmptyBlock,
+        Code::InvalidDirective,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::ExpectedToken => "expected_token",
+            Code::UnexpectedToken => "unexpected_token",
+            Code::ExpectedExpression => "expected_expression",
+            Code::IllegalToken => "illegal_token",
+            Code::UnterminatedString => "unterminated_string",
+            Code::InvalidCharLiteral => "invalid_char_literal",
+            Code::IntegerOverflow => "integer_overflow",
+            Code::UnknownStatement => "unknown_statement",
+            Code::InvalidOperator => "invalid_operator",
+            Code::UnknownExpression => "unknown_expression",
+            Code::VariableAlreadyDeclared => "variable_already_declared",
+            Code::FunctionAlreadyDeclared => "function_already_declared",
+            Code::UndeclaredAssignment => "undeclared_assignment",
+            Code::UndefinedReference => "undefined_reference",
+            Code::ReferenceBeforeAssignment => "reference_before_assignment",
+            Code::EntryNotFound => "entry_not_found",
+            Code::MismatchedType => "mismatched_type",
+            Code::ExpectedType => "expected_type",
+            Code::UnusedVariable => "unused_variable",
+            Code::UnusedFunction => "unused_function",
+            Code::EmptyBlock => "empty_block",
+            Code::InvalidDirective => "invalid_directive",
+        }
+    }
+
+    /// True if the diagnostic is a warning, whose level can be changed. Errors always fail.
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            Code::UnusedVariable | Code::UnusedFunction | Code::EmptyBlock | Code::InvalidDirective
+        )
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Code {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Code::ALL
+            .into_iter()
+            .find(|code| code.as_str() == s)
+            .ok_or_else(|| format!("unknown diagnostic: {}", s))
+    }
+}
@@ -3,7 +3,7 @@
 use alloc::string::String;
 use core::cmp;
 
-use super::Diagnostic;
+use super::{Diagnostic, Edit, Suggestion};
 use crate::diagnostics::DiagnosticKind;
 use crate::error::Result;
 use crate::escape_codes::Color;
@@ -12,18 +12,23 @@ use crate::span::Span;
 
 pub struct DiagnosticsDisplay<'a> {
     text: &'a Source, // May need more info => SourceCode struct
-    diagnostics: &'a [Diagnostic],
+    diagnostics: &'a [&'a Diagnostic],
 }
 
 const MESSAGE_PADDING: usize = 16;
 
 impl<'a> DiagnosticsDisplay<'a> {
-    pub fn new(text: &'a Source, diagnostics: &'a [Diagnostic]) -> Self {
+    pub fn new(text: &'a Source, diagnostics: &'a [&'a Diagnostic]) -> Self {
         Self { text, diagnostics }
     }
 
     /// Formats diagnostic in desired format for user presentation
     pub fn stringify(&self, message: &str, span: &Span) -> Result<String> {
+        self.stringify_with(message, span, Color::Red)
+    }
+
+    /// Formats `message` under the source of `span`, highlighted in `highlight`.
+    fn stringify_with(&self, message: &str, span: &Span, highlight: Color) -> Result<String> {
         let line_index = self.text.line_index(span.start);
         let line = self.text.line(line_index)?;
         let line_start = self.text.line_start(line_index);
@@ -90,7 +95,7 @@ impl<'a> DiagnosticsDisplay<'a> {
         Ok(format!(
             "{}{}{}{}{}\n{}\n{}\n{}\n",
             prefix,
-            Color::Red,
+            highlight,
             line_span,
             Color::Reset,
             suffix,
@@ -109,24 +114,82 @@ impl<'a> DiagnosticsDisplay<'a> {
                 continue;
             }
 
+            let message = format!("{}[{}]: {}", kind, diagnostic.code, diagnostic.message);
             eprintln!(
                 "{}",
-                self.stringify(&diagnostic.message, &diagnostic.span.expect("unreachable"))?
+                self.stringify(&message, &diagnostic.span.expect("unreachable"))?
             );
+            self.print_details(diagnostic)?;
         }
 
         eprintln!();
         for diagnostic in program_diagnostics {
             eprintln!(
-                "{}[{}]{}: {}",
+                "{}{}[{}]{}: {}",
                 Color::Red,
                 kind,
+                diagnostic.code,
                 Color::Reset,
                 diagnostic.message
             );
+            self.print_details(diagnostic)?;
         }
         eprintln!();
 
         Ok(())
     }
+
+    /// Prints the labels, notes and suggestions of `diagnostic`.
+    fn print_details(&self, diagnostic: &Diagnostic) -> Result<()> {
+        for label in &diagnostic.labels {
+            eprintln!(
+                "{}",
+                self.stringify_with(&label.message, &label.span, Color::Cyan)?
+            );
+        }
+        for note in &diagnostic.notes {
+            eprintln!("= {}note{}: {}", Color::Cyan, Color::Reset, note);
+        }
+        for suggestion in &diagnostic.suggestions {
+            eprintln!(
+                "= {}help{}: {}",
+                Color::Cyan,
+                Color::Reset,
+                suggestion.message
+            );
+            if let Some(preview) = self.preview(suggestion) {
+                eprintln!("    {}", preview);
+            }
+        }
+        if !diagnostic.notes.is_empty() || !diagnostic.suggestions.is_empty() {
+            eprintln!();
+        }
+        Ok(())
+    }
+
+    /// The line `suggestion` changes with the suggestion applied, if it only changes one line.
+    fn preview(&self, suggestion: &Suggestion) -> Option<String> {
+        let (start, end) = match suggestion.edit {
+            Edit::Insert(at) => (at, at),
+            Edit::Replace(span) => (span.start, span.end + 1),
+        };
+        let line_index = self.text.line_index(start);
+        if self.text.line_index(end) != line_index {
+            return None;
+        }
+        let line = self.text.line(line_index).ok()?;
+        let line_start = self.text.line_start(line_index);
+        let (from, to) = (start - line_start, end - line_start);
+        if to > line.len() {
+            return None;
+        }
+        Some(format!(
+            "{}{}{}{}{}",
+            line[..from].trim_start(),
+            Color::Cyan,
+            suggestion.text,
+            Color::Reset,
+            &line[to..]
+        ))
+    }
 }
@@ -0,0 +1,128 @@
+use alloc::string::{String, ToString};
+
+use alloc::vec::Vec;
+
+use super::{Diagnostic, DiagnosticKind, Edit};
+use crate::json;
+use crate::source::Source;
+use crate::span::Span;
+
+impl Diagnostic {
+    /// The diagnostic as a JSON object on one line. Lines and columns start at 1 and are `null`
+    /// with the span for diagnostics about the whole program.
+    pub fn to_json(&self, severity: &DiagnosticKind, src: &Source) -> String {
+        let labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\": {}, {}}}",
+                    json::string(&label.message),
+                    location(Some(label.span), src)
+                )
+            })
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json::string(note)).collect();
+        let suggestions: Vec<String> = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let edit = match suggestion.edit {
+                    Edit::Insert(at) => format!("\"span\": null, \"insert_at\": {}", at),
+                    Edit::Replace(span) => {
+                        format!("\"span\": {}, \"insert_at\": null", json::span(&span))
+                    }
+                };
+                format!(
+                    "{{\"message\": {}, \"replacement\": {}, {}}}",
+                    json::string(&suggestion.message),
+                    json::string(&suggestion.text),
+                    edit
+                )
+            })
+            .collect();
+        format!(
+            "{{\"severity\": \"{}\", \"code\": \"{}\", \"message\": {}, {}, \"labels\": [{}], \"notes\": [{}], \"suggestions\": [{}]}}",
+            severity,
+            self.code,
+            json::string(&self.message),
+            location(self.span, src),
+            labels.join(", "),
+            notes.join(", "),
+            suggestions.join(", ")
+        )
+    }
+}
+
+/// The `span`, `line`, `column`, `end_line` and `end_column` fields of `span`.
+fn location(span: Option<Span>, src: &Source) -> String {
+    let position = |index: usize| {
+        let line = src.line_index(index);
+        (line + 1, index - src.line_start(line) + 1)
+    };
+    match span {
+        Some(span) => {
+            let (line, column) = position(span.start);
+            let (end_line, end_column) = position(span.end);
+            format!(
+                "\"span\": {}, \"line\": {}, \"column\": {}, \"end_line\": {}, \"end_column\": {}",
+                json::span(&span),
+                line,
+                column,
+                end_line,
+                end_column
+            )
+        }
+        None => "\"span\": null, \"line\": null, \"column\": null, \"end_line\": null, \"end_column\": null"
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Code;
+
+    #[test]
+    fn to_json() {
+        let src = Source::new("int main() {\n    let x = \"a\";\n}".to_string());
+        let diagnostic = Diagnostic::warning(
+            Code::UnusedVariable,
+            "unused variable: `x`".to_string(),
+            Span::new(21, 21),
+        );
+        assert_eq!(
+            diagnostic.to_json(&DiagnosticKind::Warning, &src),
+            "{\"severity\": \"warning\", \"code\": \"unused_variable\", \"message\": \"unused variable: `x`\", \"span\": [21, 21], \"line\": 2, \"column\": 9, \"end_line\": 2, \"end_column\": 9, \"labels\": [], \"notes\": [], \"suggestions\": []}"
+        );
+
+        let program = Diagnostic::new(
+            Code::EntryNotFound,
+            "`main` function \"not\" found".to_string(),
+            None,
+        );
+        assert_eq!(
+            program.to_json(&DiagnosticKind::Error, &src),
+            "{\"severity\": \"error\", \"code\": \"entry_not_found\", \"message\": \"`main` function \\\"not\\\" found\", \"span\": null, \"line\": null, \"column\": null, \"end_line\": null, \"end_column\": null, \"labels\": [], \"notes\": [], \"suggestions\": []}"
+        );
+    }
+
+    #[test]
+    fn details() {
+        let src = Source::new("let x = 1;\nlet x = 2".to_string());
+        let mut diagnostic = Diagnostic::new(
+            Code::VariableAlreadyDeclared,
+            "again".to_string(),
+            Some(Span::new(15, 15)),
+        );
+        diagnostic
+            .label(Span::new(4, 4), "first".to_string())
+            .note("a note".to_string())
+            .suggest("insert `;`".to_string(), Edit::Insert(20), ";")
+            .suggest("rename".to_string(), Edit::Replace(Span::new(15, 15)), "y");
+        let json = diagnostic.to_json(&DiagnosticKind::Error, &src);
+        assert!(json.contains("\"labels\": [{\"message\": \"first\", \"span\": [4, 4], \"line\": 1, \"column\": 5, \"end_line\": 1, \"end_column\": 5}]"));
+        assert!(json.contains("\"notes\": [\"a note\"]"));
+        assert!(json.ends_with("\"suggestions\": [{\"message\": \"insert `;`\", \"replacement\": \";\", \"span\": null, \"insert_at\": 20}, {\"message\": \"rename\", \"replacement\": \"y\", \"span\": [15, 15], \"insert_at\": null}]}"));
+    }
+}
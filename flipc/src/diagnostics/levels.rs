@@ -0,0 +1,267 @@
+//! diagnostics/levels.rs - How each warning is reported, set for the whole compilation or in the
+//! source with comments:
+//!
+//! ```text
+//! #! allow(unused_function)     the whole file
+//! # deny(unused_variable)       the next line with code
+//! let x = 1; # allow(unused_variable, empty_block)   this line
+//! ```
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Code, Diagnostic};
+use crate::span::Span;
+
+/// How a warning is reported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Not reported.
+    Allow,
+    #[default]
+    Warn,
+    /// Reported as an error, failing the compilation.
+    Deny,
+}
+
+impl Level {
+    fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The level of every warning, with per code overrides.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Levels {
+    default: Level,
+    codes: Vec<(Code, Level)>,
+}
+
+impl Levels {
+    pub fn new(default: Level) -> Self {
+        Self {
+            default,
+            codes: Vec::new(),
+        }
+    }
+
+    /// Sets the level of every warning, replacing any set for a single code.
+    pub fn set_all(&mut self, level: Level) {
+        self.default = level;
+        self.codes.clear();
+    }
+
+    /// Sets the level of one warning. Errors can't be changed.
+    pub fn set(&mut self, code: Code, level: Level) -> Result<(), String> {
+        if !code.is_warning() {
+            return Err(format!("`{}` is an error, not a warning", code));
+        }
+        self.codes.retain(|&(c, _)| c != code);
+        self.codes.push((code, level));
+        Ok(())
+    }
+
+    pub fn level(&self, code: Code) -> Level {
+        self.codes
+            .iter()
+            .find(|&&(c, _)| c == code)
+            .map_or(self.default, |&(_, level)| level)
+    }
+}
+
+struct Directive {
+    // None for the whole file
+    line: Option<usize>,
+    code: Code,
+    level: Level,
+}
+
+/// The levels set by comments in a source file.
+#[derive(Default)]
+pub struct Directives {
+    directives: Vec<Directive>,
+    /// Warnings for directives naming an unknown code or an error.
+    pub invalid: Vec<Diagnostic>,
+}
+
+impl Directives {
+    pub fn parse(text: &str) -> Self {
+        let mut out = Self::default();
+        // directives on their own line, waiting for the next line with code
+        let mut pending: Vec<Directive> = Vec::new();
+        let mut line_start = 0;
+        for (index, line) in text.split('\n').enumerate() {
+            let (code, comment) = match comment_start(line) {
+                Some(hash) => (&line[..hash], Some(hash)),
+                None => (line, None),
+            };
+            let has_code = !code.trim().is_empty();
+            if has_code {
+                for mut directive in pending.drain(..) {
+                    directive.line = Some(index);
+                    out.directives.push(directive);
+                }
+            }
+            if let Some(hash) = comment {
+                let body = &line[hash + 1..];
+                let (file_wide, body, body_start) = match body.strip_prefix('!') {
+                    Some(rest) => (true, rest, line_start + hash + 2),
+                    None => (false, body, line_start + hash + 1),
+                };
+                for (code, level) in out.parse_comment(body, body_start) {
+                    let directive = Directive {
+                        line: Some(index),
+                        code,
+                        level,
+                    };
+                    match (file_wide, has_code) {
+                        (true, _) => out.directives.push(Directive {
+                            line: None,
+                            ..directive
+                        }),
+                        (false, true) => out.directives.push(directive),
+                        (false, false) => pending.push(directive),
+                    }
+                }
+            }
+            line_start += line.len() + 1;
+        }
+        out
+    }
+
+    /// Reads `level(code, ...)` from a comment starting at byte `start`. Other comments are
+    /// ignored.
+    fn parse_comment(&mut self, body: &str, start: usize) -> Vec<(Code, Level)> {
+        let trimmed = body.trim_start();
+        let offset = start + body.len() - trimmed.len();
+        let paren = match trimmed.find('(') {
+            Some(paren) => paren,
+            None => return Vec::new(),
+        };
+        let name = trimmed[..paren].trim_end();
+        let (level, args) = match (Level::from_name(name), trimmed[paren + 1..].split_once(')')) {
+            (Some(level), Some((args, _))) => (level, args),
+            _ => return Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        let mut arg_start = offset + paren + 1;
+        for arg in args.split(',') {
+            let name = arg.trim();
+            let name_start = arg_start + arg.len() - arg.trim_start().len();
+            arg_start += arg.len() + 1;
+            if name.is_empty() {
+                continue;
+            }
+            let span = Span::new(name_start, name_start + name.len() - 1);
+            match name.parse::<Code>() {
+                Ok(code) if code.is_warning() => out.push((code, level)),
+                Ok(code) => self.invalid.push(Diagnostic::warning(
+                    Code::InvalidDirective,
+                    format!("`{}` is an error, its level can't be changed", code),
+                    span,
+                )),
+                Err(_) => self.invalid.push(Diagnostic::warning(
+                    Code::InvalidDirective,
+                    format!("unknown diagnostic: `{}`", name),
+                    span,
+                )),
+            }
+        }
+        out
+    }
+
+    /// The level of `code` reported on `line`: set on the line, then for the file, then `levels`.
+    pub fn level(&self, code: Code, line: Option<usize>, levels: &Levels) -> Level {
+        let find = |line: Option<usize>| {
+            self.directives
+                .iter()
+                .rev()
+                .find(|d| d.code == code && d.line == line)
+                .map(|d| d.level)
+        };
+        line.and_then(|line| find(Some(line)))
+            .or_else(|| find(None))
+            .unwrap_or_else(|| levels.level(code))
+    }
+}
+
+/// Byte offset of the `#` starting a comment in `line`, skipping `#` in string and char literals.
+fn comment_start(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut in_string = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => in_string = !in_string,
+            b'\'' if !in_string => i += 2,
+            b'#' if !in_string => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "#! allow(empty_block)
+# deny(unused_variable)
+
+let x = 1; # allow(unused_function, mismatched_type, nope)
+let y = 2;";
+
+    #[test]
+    fn directives() {
+        let directives = Directives::parse(SOURCE);
+        let levels = Levels::default();
+        let level = |code, line| directives.level(code, Some(line), &levels);
+
+        assert_eq!(level(Code::EmptyBlock, 4), Level::Allow);
+        assert_eq!(level(Code::UnusedVariable, 3), Level::Deny);
+        assert_eq!(level(Code::UnusedFunction, 3), Level::Allow);
+        assert_eq!(level(Code::UnusedVariable, 4), Level::Warn);
+        assert_eq!(level(Code::UnusedFunction, 4), Level::Warn);
+        assert_eq!(
+            directives.level(Code::EmptyBlock, None, &levels),
+            Level::Allow
+        );
+
+        let invalid: Vec<_> = directives
+            .invalid
+            .iter()
+            .map(|d| {
+                let span = d.span.unwrap();
+                &SOURCE[span.start..=span.end]
+            })
+            .collect();
+        assert_eq!(invalid, vec!["mismatched_type", "nope"]);
+    }
+
+    #[test]
+    fn levels() {
+        let mut levels = Levels::new(Level::Deny);
+        levels.set(Code::UnusedVariable, Level::Allow).unwrap();
+        assert!(levels.set(Code::MismatchedType, Level::Allow).is_err());
+        assert_eq!(levels.level(Code::UnusedVariable), Level::Allow);
+        assert_eq!(levels.level(Code::EmptyBlock), Level::Deny);
+
+        levels.set_all(Level::Warn);
+        assert_eq!(levels.level(Code::UnusedVariable), Level::Warn);
+    }
+
+    #[test]
+    fn comments_in_literals() {
+        assert_eq!(comment_start("let s = \"# deny(x)\"; # c"), Some(21));
+        assert_eq!(comment_start("let c = '#';"), None);
+        assert!(Directives::parse("# not a directive (really)")
+            .directives
+            .is_empty());
+    }
+}
@@ -7,17 +7,92 @@ use core::cell::RefCell;
 use core::fmt::{self, Display};
 
 use self::display::DiagnosticsDisplay;
+use self::levels::Directives;
 use crate::error::{CompilerError, Result};
 use crate::lexer::Token;
 use crate::source::Source;
 use crate::span::Span;
 
+pub use self::code::Code;
+pub use self::levels::{Level, Levels};
+pub use self::suggest::closest;
+
+mod code;
 mod display;
+mod json;
+mod levels;
+mod suggest;
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug)]
 pub struct Diagnostic {
+    pub code: Code,
     pub message: String,
     pub span: Option<Span>,
+    /// Other places the diagnostic refers to, such as an earlier declaration.
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// A secondary span with its own message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Where the text of a [`Suggestion`] goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    /// Before the byte at this offset.
+    Insert(usize),
+    Replace(Span),
+}
+
+/// A fix-it, applying `edit` with `text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub edit: Edit,
+    pub text: String,
+}
+
+impl Diagnostic {
+    fn new(code: Code, message: String, span: Option<Span>) -> Self {
+        Self {
+            code,
+            message,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    fn warning(code: Code, message: String, span: Span) -> Self {
+        Self::new(code, message, Some(span))
+    }
+
+    fn label(&mut self, span: Span, message: String) -> &mut Self {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    fn note(&mut self, message: String) -> &mut Self {
+        self.notes.push(message);
+        self
+    }
+
+    fn suggest(&mut self, message: String, edit: Edit, text: &str) -> &mut Self {
+        self.suggestions.push(Suggestion {
+            message,
+            edit,
+            text: text.to_owned(),
+        });
+        self
+    }
 }
 
 #[repr(u8)]
@@ -36,6 +111,16 @@ impl Display for DiagnosticKind {
     }
 }
 
+/// How diagnostics are printed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Source excerpts with the problem highlighted.
+    #[default]
+    Human,
+    /// One JSON object per line, for editors and CI.
+    Json,
+}
+
 #[derive(Default, Debug)]
 pub struct DiagnosticBag {
     pub warnings: Vec<Diagnostic>,
@@ -59,19 +144,31 @@ impl DiagnosticBag {
         self.warnings.is_empty() && self.errors.is_empty()
     }
 
-    pub fn check(&self, src: &Source) -> Result<()> {
+    /// Prints every diagnostic in `format`, reporting each warning at the level set in `src` or
+    /// `levels`.
+    pub fn check(&self, src: &Source, levels: &Levels, format: ErrorFormat) -> Result<()> {
+        let directives = Directives::parse(src.text());
+        let mut errors: Vec<&Diagnostic> = self.errors.iter().collect();
+        let mut warnings = Vec::new();
+        for diagnostic in self.warnings.iter().chain(&directives.invalid) {
+            let line = diagnostic.span.map(|span| src.line_index(span.start));
+            match directives.level(diagnostic.code, line, levels) {
+                Level::Allow => {}
+                Level::Warn => warnings.push(diagnostic),
+                Level::Deny => errors.push(diagnostic),
+            }
+        }
+
         let mut error: Option<CompilerError> = None;
 
-        if !self.warnings.is_empty() {
-            let diagnostics_display = DiagnosticsDisplay::new(src, &self.warnings);
-            diagnostics_display.print(DiagnosticKind::Warning)?;
+        if !warnings.is_empty() {
+            Self::print(src, &warnings, DiagnosticKind::Warning, format)?;
 
             error = Some(CompilerError::DiagnosticWarning);
         }
 
-        if !self.errors.is_empty() {
-            let diagnostics_display = DiagnosticsDisplay::new(src, &self.errors);
-            diagnostics_display.print(DiagnosticKind::Error)?;
+        if !errors.is_empty() {
+            Self::print(src, &errors, DiagnosticKind::Error, format)?;
 
             error = Some(CompilerError::DiagnosticError);
         }
@@ -79,112 +176,241 @@ impl DiagnosticBag {
         error.map_or(Ok(()), Err)
     }
 
-    fn error(&mut self, message: String, span: Span) {
-        self.errors.push(Diagnostic {
-            message,
-            span: Some(span),
-        });
+    fn print(
+        src: &Source,
+        diagnostics: &[&Diagnostic],
+        kind: DiagnosticKind,
+        format: ErrorFormat,
+    ) -> Result<()> {
+        match format {
+            ErrorFormat::Human => DiagnosticsDisplay::new(src, diagnostics).print(kind),
+            ErrorFormat::Json => {
+                for diagnostic in diagnostics {
+                    eprintln!("{}", diagnostic.to_json(&kind, src));
+                }
+                Ok(())
+            }
+        }
     }
 
-    fn program_error(&mut self, message: String) {
-        self.errors.push(Diagnostic {
-            message,
-            span: None,
-        });
+    fn error(&mut self, code: Code, message: String, span: Span) -> &mut Diagnostic {
+        self.errors.push(Diagnostic::new(code, message, Some(span)));
+        self.errors.last_mut().expect("just pushed")
     }
 
-    fn warning(&mut self, message: String, span: Span) {
-        self.warnings.push(Diagnostic {
-            message,
-            span: Some(span),
-        });
+    fn program_error(&mut self, code: Code, message: String) -> &mut Diagnostic {
+        self.errors.push(Diagnostic::new(code, message, None));
+        self.errors.last_mut().expect("just pushed")
+    }
+
+    fn warning(&mut self, code: Code, message: String, span: Span) -> &mut Diagnostic {
+        self.warnings.push(Diagnostic::warning(code, message, span));
+        self.warnings.last_mut().expect("just pushed")
     }
 
     pub fn expected_token(&mut self, expected: &Token, actual: &Token, span: Span) {
         self.error(
+            Code::ExpectedToken,
             format!("expected: '{}', found: `{}`", expected, actual),
             span,
         );
     }
 
+    /// An expected closing token that is missing, with a fix inserting it at `insert_at`.
+    pub fn missing_token(
+        &mut self,
+        expected: &Token,
+        actual: &Token,
+        span: Span,
+        insert_at: usize,
+    ) {
+        let text = expected.to_string();
+        self.error(
+            Code::ExpectedToken,
+            format!("expected: '{}', found: `{}`", expected, actual),
+            span,
+        )
+        .suggest(format!("insert `{}`", text), Edit::Insert(insert_at), &text);
+    }
+
     pub fn unexpected_token(&mut self, token: &Token, span: Span) {
-        self.error(format!("unexpected token: `{}`", token), span);
+        self.error(
+            Code::UnexpectedToken,
+            format!("unexpected token: `{}`", token),
+            span,
+        );
     }
 
     pub fn expected_expression(&mut self, expected: &Token, span: Span) {
-        self.error(format!("expected expression, found `{}`", expected), span);
+        self.error(
+            Code::ExpectedExpression,
+            format!("expected expression, found `{}`", expected),
+            span,
+        );
     }
 
     pub fn illegal_token(&mut self, span: Span) {
-        self.error("illegal token".to_owned(), span);
+        self.error(Code::IllegalToken, "illegal token".to_owned(), span);
+    }
+
+    pub fn unterminated_string(&mut self, span: Span) {
+        self.error(
+            Code::UnterminatedString,
+            "unterminated string".to_owned(),
+            span,
+        )
+        .suggest("insert `\"`".to_owned(), Edit::Insert(span.end + 1), "\"");
+    }
+
+    pub fn invalid_char_literal(&mut self, span: Span) {
+        self.error(
+            Code::InvalidCharLiteral,
+            "invalid char literal".to_owned(),
+            span,
+        )
+        .note("char literals hold a single character, such as `'a'`".to_owned());
+    }
+
+    pub fn integer_overflow(&mut self, span: Span) {
+        self.error(
+            Code::IntegerOverflow,
+            "integer literal is too large".to_owned(),
+            span,
+        )
+        .note(format!("the largest integer is {}", u64::MAX));
     }
 
     pub fn unknown_statement(&mut self, token: &Token, span: Span) {
-        self.error(format!("unknown statement `{}`", token), span);
+        self.error(
+            Code::UnknownStatement,
+            format!("unknown statement `{}`", token),
+            span,
+        );
     }
 
     pub fn invalid_operator(&mut self, token: &Token, span: Span) {
-        self.error(format!("invalid operator `{}`", token), span);
+        self.error(
+            Code::InvalidOperator,
+            format!("invalid operator `{}`", token),
+            span,
+        );
     }
 
     pub fn unknown_expression(&mut self, token: &Token, span: Span) {
-        self.error(format!("unknown expression `{}`", token), span);
+        self.error(
+            Code::UnknownExpression,
+            format!("unknown expression `{}`", token),
+            span,
+        );
     }
 
-    pub fn variable_already_declared(&mut self, pattern: &String, span: Span) {
+    pub fn variable_already_declared(&mut self, pattern: &String, span: Span, previous: Span) {
         self.error(
+            Code::VariableAlreadyDeclared,
             format!("variable: `{}` already exists in scope", pattern),
             span,
-        );
+        )
+        .label(previous, format!("`{}` first declared here", pattern))
+        .note("variables can't be shadowed, use a different name".to_owned());
     }
 
-    pub fn function_already_declared(&mut self, pattern: &String, span: Span) {
-        // TODO: better message maybe?
-        self.error(format!("function: `{}` already exists", pattern), span);
+    pub fn function_already_declared(&mut self, pattern: &String, span: Span, previous: Span) {
+        self.error(
+            Code::FunctionAlreadyDeclared,
+            format!("function: `{}` already exists", pattern),
+            span,
+        )
+        .label(previous, format!("`{}` first defined here", pattern));
     }
 
-    pub fn undeclared_assignment(&mut self, ident: &String, span: Span) {
-        self.error(format!("undeclared symbol: `{}`", ident), span);
+    /// An assignment to an unknown name, suggesting `similar` if it is close.
+    pub fn undeclared_assignment(&mut self, ident: &String, span: Span, similar: Option<&str>) {
+        let diagnostic = self.error(
+            Code::UndeclaredAssignment,
+            format!("undeclared symbol: `{}`", ident),
+            span,
+        );
+        match similar {
+            Some(name) => did_you_mean(diagnostic, name, span),
+            None => {
+                diagnostic.note(format!("declare it with `let {} = ...;`", ident));
+            }
+        }
     }
 
-    pub fn undefined_reference(&mut self, ident: &String, span: Span) {
-        self.error(format!("symbol: `{}` is undefined", ident), span);
+    /// A reference to an unknown name, suggesting `similar` if it is close.
+    pub fn undefined_reference(&mut self, ident: &String, span: Span, similar: Option<&str>) {
+        let diagnostic = self.error(
+            Code::UndefinedReference,
+            format!("symbol: `{}` is undefined", ident),
+            span,
+        );
+        if let Some(name) = similar {
+            did_you_mean(diagnostic, name, span);
+        }
     }
 
     pub fn reference_before_assignment(&mut self, ident: &String, span: Span) {
         self.error(
+            Code::ReferenceBeforeAssignment,
             format!("symbol: `{}` referenced before assignment", ident),
             span,
         );
     }
 
     pub fn unused_variable(&mut self, ident: &String, span: Span) {
-        self.warning(format!("unused variable: `{}`", ident), span);
+        self.warning(
+            Code::UnusedVariable,
+            format!("unused variable: `{}`", ident),
+            span,
+        );
     }
 
     pub fn unused_function(&mut self, ident: &String, span: Span) {
-        self.warning(format!("unused function: `{}`", ident), span);
+        self.warning(
+            Code::UnusedFunction,
+            format!("unused function: `{}`", ident),
+            span,
+        );
     }
 
     pub fn empty_block(&mut self, span: Span) {
-        self.warning("empty block found".to_owned(), span);
+        self.warning(Code::EmptyBlock, "empty block found".to_owned(), span);
     }
 
-    pub fn main_not_found(&mut self) {
-        self.program_error("`main` function not found".to_owned());
+    pub fn entry_not_found(&mut self, entry: &str) {
+        self.program_error(
+            Code::EntryNotFound,
+            format!("`{}` function not found", entry),
+        );
     }
 
-    pub fn mismatched_type(&mut self, expected: &Type, found: &Type, span: Span) {
+    /// A value of type `found` assigned to a name declared at `declared` as `expected`.
+    pub fn mismatched_type(&mut self, expected: &Type, found: &Type, span: Span, declared: Span) {
         self.error(
+            Code::MismatchedType,
             format!(
                 "mismatched types: expected `{}`, found `{}`",
                 expected, found
             ),
             span,
-        );
+        )
+        .label(declared, format!("declared as `{}` here", expected));
     }
 
     pub fn expected_type(&mut self, expected: &Token, span: Span) {
-        self.error(format!("expected type, found `{}`", expected), span);
+        self.error(
+            Code::ExpectedType,
+            format!("expected type, found `{}`", expected),
+            span,
+        );
     }
 }
+
+fn did_you_mean(diagnostic: &mut Diagnostic, name: &str, span: Span) {
+    diagnostic.suggest(
+        format!("did you mean `{}`?", name),
+        Edit::Replace(span),
+        name,
+    );
+}
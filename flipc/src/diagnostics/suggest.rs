@@ -0,0 +1,65 @@
+//! diagnostics/suggest.rs - "Did you mean" suggestions, picking the candidate closest to a
+//! misspelled name by edit distance.
+use alloc::vec::Vec;
+
+/// The edit distance between `a` and `b`: the insertions, deletions, substitutions and swaps of
+/// adjacent characters needed to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // d[i][j] is the distance between the first i characters of a and the first j of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// The candidate closest to `name`, if it is within a third of its length. Ties go to the first.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= limit)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("foo", "fo"), 1);
+        assert_eq!(edit_distance("count", "conut"), 1);
+    }
+
+    #[test]
+    fn closest_candidate() {
+        let names = ["count", "total", "x"];
+        assert_eq!(closest("totl", names), Some("total"));
+        assert_eq!(closest("cuont", names), Some("count"));
+        assert_eq!(closest("cnuot", names), None);
+        assert_eq!(closest("y", names), Some("x"));
+        assert_eq!(closest("unrelated", names), None);
+        assert_eq!(closest("count", names), None);
+    }
+}
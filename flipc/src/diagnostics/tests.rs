@@ -0,0 +1,152 @@
+use super::{Code, DiagnosticBag, Edit, Label, Suggestion};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::passes::nameresolver::NameResolver;
+use crate::passes::symbol_table::SymbolTableBuilder;
+use crate::passes::typechecker::TypeChecker;
+use crate::passes::Pass;
+
+fn diagnose(input: &str) -> DiagnosticBag {
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let mut root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&mut root, &mut st, &mut ft, diagnostics.clone()));
+    TypeChecker::run((&mut root, &mut st, &mut ft, diagnostics.clone()));
+    diagnostics.take()
+}
+
+fn text(input: &str, span: crate::span::Span) -> &str {
+    &input[span.start..=span.end]
+}
+
+#[test]
+fn already_declared() {
+    let input = "int main() {\n    let count = 1;\n    let count = 2;\n    return count;\n}";
+    let bag = diagnose(input);
+    let error = &bag.errors[0];
+    assert_eq!(text(input, error.span.unwrap()), "count");
+    assert_eq!(error.span.unwrap().start, 40);
+    let label = &error.labels[0];
+    assert_eq!(label.message, "`count` first declared here");
+    assert!(text(input, label.span).starts_with("count = 1"));
+    assert_eq!(error.notes.len(), 1);
+
+    let input = "int main() {\n    return 0;\n}\n\nint main() {\n    return 1;\n}";
+    let bag = diagnose(input);
+    assert_eq!(
+        bag.errors[0].labels,
+        vec![Label {
+            span: crate::span::Span::new(4, 7),
+            message: "`main` first defined here".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn extern_parameters() {
+    let input = "extern int add(a, b);\n\nint main() {\n    return add(40, 2);\n}";
+    let bag = diagnose(input);
+    assert!(bag.is_empty(), "{:?}", bag.warnings);
+}
+
+#[test]
+fn did_you_mean() {
+    let input = "int main() {\n    let total = 1;\n    return totl + fob(total);\n}\n\nint foo(a) {\n    return a;\n}";
+    let bag = diagnose(input);
+    let suggestions: Vec<_> = bag
+        .errors
+        .iter()
+        .map(|e| e.suggestions[0].clone())
+        .collect();
+    assert_eq!(
+        suggestions,
+        vec![
+            Suggestion {
+                message: "did you mean `total`?".to_string(),
+                edit: Edit::Replace(crate::span::Span::new(43, 46)),
+                text: "total".to_string(),
+            },
+            Suggestion {
+                message: "did you mean `foo`?".to_string(),
+                edit: Edit::Replace(crate::span::Span::new(50, 52)),
+                text: "foo".to_string(),
+            },
+        ]
+    );
+
+    // only names declared before the reference
+    let bag = diagnose("int main() {\n    let a = totl;\n    let total = a;\n    return total;\n}");
+    assert!(bag.errors[0].suggestions.is_empty());
+}
+
+#[test]
+fn insert_token() {
+    let input = "int main() {\n    let x = 1\n    return x;\n}";
+    let bag = diagnose(input);
+    let suggestion = &bag.errors[0].suggestions[0];
+    assert_eq!(suggestion.message, "insert `;`");
+    // straight after the `1`
+    assert_eq!(suggestion.edit, Edit::Insert(26));
+    assert_eq!(&input[25..26], "1");
+}
+
+#[test]
+fn missing_token_not_consumed() {
+    // the `}` closes the block, so only the `;` is missing
+    let input = "int main() { return 1 }";
+    let bag = diagnose(input);
+    assert_eq!(bag.errors.len(), 1, "{:?}", bag.errors);
+    assert_eq!(bag.errors[0].suggestions[0].message, "insert `;`");
+    assert_eq!(bag.errors[0].suggestions[0].edit, Edit::Insert(21));
+}
+
+#[test]
+fn lexer_errors() {
+    let input =
+        "int main() {\n    let c = 'ab';\n    let n = 99999999999999999999;\n    return \"0;\n}";
+    let bag = diagnose(input);
+    let codes: Vec<_> = bag.errors.iter().map(|e| e.code).collect();
+    // the literals are skipped, leaving `let c = ;` without a second error
+    assert_eq!(
+        &codes[..3],
+        &[
+            Code::InvalidCharLiteral,
+            Code::IntegerOverflow,
+            Code::UnterminatedString,
+        ]
+    );
+    assert!(!codes.contains(&Code::ExpectedExpression), "{:?}", codes);
+    assert_eq!(bag.errors[2].suggestions[0].edit, Edit::Insert(input.len()));
+
+    for input in [
+        "int main() {\n    let x = 99999999999999999999999;\n    return x;\n}",
+        "int main() {\n    let c = 'ab';\n    return c;\n}",
+        "int main() {\n    let s = \"abc",
+    ] {
+        let bag = diagnose(input);
+        assert_eq!(bag.errors.len(), 1, "{:?}", bag.errors);
+    }
+}
+
+#[test]
+fn lexer_error_first() {
+    let input = "$int main() {\n    return 0;\n}";
+    let bag = diagnose(input);
+    assert_eq!(bag.errors.len(), 1, "{:?}", bag.errors);
+    assert_eq!(bag.errors[0].code, Code::IllegalToken);
+    assert_eq!(text(input, bag.errors[0].span.unwrap()), "$");
+}
+
+#[test]
+fn no_fix_after_lexer_error() {
+    let input = "int main() {\n    let x = 1 'ab'\n    return x;\n}";
+    let bag = diagnose(input);
+    assert_eq!(bag.errors[0].code, Code::InvalidCharLiteral);
+    // the `;` is missing after the bad literal, not the `1`
+    assert!(bag
+        .errors
+        .iter()
+        .all(|e| e.suggestions.iter().all(|s| s.message != "insert `;`")));
+}
@@ -1,5 +1,5 @@
 use crate::ast::visitor::{Visitor, Walkable};
-use crate::ast::{BinOp, Binary, Literal, LiteralKind, UnOp, Unary, Variable};
+use crate::ast::{BinOp, Binary, Call, Literal, LiteralKind, UnOp, Unary, Variable};
 
 // Currently any operation that yields a float is floored.
 #[derive(Default)]
@@ -25,13 +25,13 @@ impl Visitor for Evaluator {
             None => return,
         };
 
-        self.last_value = Some(match bin.op {
-            BinOp::Add => left + right,
-            BinOp::Sub => left - right,
-            BinOp::Mul => left * right,
-            BinOp::Div => left / right,
-            _ => todo!("{:?}", bin.op),
-        });
+        self.last_value = match bin.op {
+            BinOp::Add => Some(left + right),
+            BinOp::Sub => Some(left - right),
+            BinOp::Mul => Some(left * right),
+            BinOp::Div => Some(left / right),
+            _ => None,
+        };
     }
 
     fn visit_unary(&mut self, un: &Unary) {
@@ -46,10 +46,15 @@ impl Visitor for Evaluator {
         });
     }
 
+    fn visit_call(&mut self, _call: &Call) {
+        self.last_value = None;
+    }
+
     fn visit_literal(&mut self, lit: &Literal) {
-        if let LiteralKind::Int(i) = lit.kind {
-            self.last_value = Some(i as f64);
-        }
+        self.last_value = match lit.kind {
+            LiteralKind::Int(i) => Some(i as f64),
+            _ => None,
+        };
     }
 }
 
@@ -4,7 +4,7 @@
 use crate::ast::Program;
 use crate::diagnostics::DiagnosticBag;
 use crate::error::{CompilerError, Result};
-use crate::lexer::Lexer;
+use crate::lexer::{Lexer, Token};
 use crate::parser::Parser;
 use crate::passes::nameresolver::NameResolver;
 use crate::passes::symbol_table::SymbolTableBuilder;
@@ -12,7 +12,32 @@ use crate::passes::typechecker::TypeChecker;
 use crate::passes::{Pass, SymbolTable};
 use crate::source::Source;
 
+pub use crate::diagnostics::{Code, ErrorFormat, Level, Levels};
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Function the program starts at.
+    pub entry: String,
+    /// How warnings are reported, unless changed in the source.
+    pub warnings: Levels,
+    pub error_format: ErrorFormat,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            entry: "main".to_string(),
+            warnings: Levels::default(),
+            error_format: ErrorFormat::Human,
+        }
+    }
+}
+
 pub fn check(input: &str) -> Result<(Program, SymbolTable)> {
+    check_with(input, &Options::default())
+}
+
+pub fn check_with(input: &str, options: &Options) -> Result<(Program, SymbolTable)> {
     let diagnostics = DiagnosticBag::new();
 
     // Fix to make lexer take src
@@ -24,19 +49,17 @@ pub fn check(input: &str) -> Result<(Program, SymbolTable)> {
 
     //let nameres = NameResolver::new(diagnostics.clone());
     // let st = nameres.resolve(&mut root);
-    let (mut st, mut ft) = SymbolTableBuilder::run((&root, diagnostics.clone()));
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, &options.entry, diagnostics.clone()));
     NameResolver::run((&mut root, &mut st, &mut ft, diagnostics.clone()));
     TypeChecker::run((&mut root, &mut st, &mut ft, diagnostics.clone()));
-    eprintln!("{:#?}", st);
-
-    eprintln!();
-    eprintln!("{}", root);
-    eprintln!();
 
     #[cfg(test)]
     assert!(diagnostics.borrow().is_empty());
 
-    match diagnostics.borrow().check(&source) {
+    match diagnostics
+        .borrow()
+        .check(&source, &options.warnings, options.error_format)
+    {
         Ok(_) => Ok(()),
         Err(CompilerError::DiagnosticWarning) => Ok(()), // TODO: Change maybe in future
         Err(e) => Err(e),
@@ -44,3 +67,16 @@ pub fn check(input: &str) -> Result<(Program, SymbolTable)> {
 
     Ok((root, st))
 }
+
+/// The tokens of `input`, one `<start>..<end> <token>` per line with inclusive byte offsets.
+pub fn tokens(input: &str) -> String {
+    let mut lexer = Lexer::new(input.to_string());
+    let mut out = String::new();
+    loop {
+        let (token, span) = lexer.next_token();
+        out.push_str(&format!("{}..{} {}\n", span.start, span.end, token));
+        if token == Token::Eof {
+            return out;
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! json.rs - Helpers for the hand written JSON output of `--emit` and diagnostics.
+use crate::span::Span;
+
+/// `s` as a quoted JSON string.
+pub fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A span as `[start, end]` byte offsets, both inclusive.
+pub fn span(span: &Span) -> String {
+    format!("[{}, {}]", span.start, span.end)
+}
@@ -7,7 +7,7 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 
-pub use self::token::Token;
+pub use self::token::{LexError, Token};
 use crate::span::Span;
 
 #[cfg(test)]
@@ -52,23 +52,21 @@ impl Lexer {
         let start_position = self.position - 1;
         // Tmp Solution to ensure counting stops on Eof. TODO: Change this
         if self.ch == EOF {
+            // reading past an unterminated string moves the position beyond the end
+            let start_position = start_position.min(self.input.len());
             return (Token::Eof, Span::new(start_position, start_position));
         }
 
         let token = match self.ch {
-            b'\"' => Token::String(self.read_string()),
+            b'\"' => self
+                .read_string()
+                .map_or_else(Token::Illegal, Token::String),
 
-            b'0'..=b'9' => Token::Int(self.read_integer()),
+            b'0'..=b'9' => self.read_integer().map_or_else(Token::Illegal, Token::Int),
 
-            b'\'' => {
-                self.read_char();
-                let ch = self.ch;
-                self.read_char();
-                if self.ch != b'\'' {
-                    panic!("Unexpected {}: missing trailing \'", self.ch as char);
-                }
-                Token::Char(ch as char)
-            }
+            b'\'' => self
+                .read_char_literal()
+                .map_or_else(Token::Illegal, Token::Char),
 
             b'a'..=b'z' | b'A'..=b'Z' => Token::from(self.read_identifier()),
 
@@ -87,7 +85,8 @@ impl Lexer {
             _ => Token::from(self.ch),
         };
 
-        let span = Span::new(start_position, self.position - 1);
+        // an unterminated string ends past the input
+        let span = Span::new(start_position, self.position.min(self.input.len()) - 1);
         self.read_char();
         (token, span)
     }
@@ -112,26 +111,52 @@ impl Lexer {
         self.position += 1;
     }
 
-    /// Reads a string input and returns it.
-    fn read_string(&mut self) -> String {
+    /// Reads a string input and returns it, or an error if the input ends first.
+    fn read_string(&mut self) -> Result<String, LexError> {
         let mut string = String::new();
         self.read_char();
 
         while self.ch != b'\"' {
             if self.ch == EOF {
-                // TODO: something more robust
-                panic!("Unexpected EOF: missing trailing \"");
+                return Err(LexError::UnterminatedString);
             }
 
             string.push(self.ch as char);
             self.read_char();
         }
 
-        string
+        Ok(string)
+    }
+
+    /// Reads a char literal, leaving the current character on its closing quote. An invalid
+    /// literal is skipped up to a closing quote on the same line.
+    fn read_char_literal(&mut self) -> Result<char, LexError> {
+        if matches!(self.peek(), b'\n' | EOF) {
+            return Err(LexError::InvalidChar);
+        }
+        self.read_char();
+        let ch = self.ch;
+        // `''` is empty
+        if ch == b'\'' {
+            return Err(LexError::InvalidChar);
+        }
+        if self.peek() == b'\'' {
+            self.read_char();
+            return Ok(ch as char);
+        }
+
+        let rest = &self.input[self.position..];
+        let line = rest.split(|&c| c == b'\n').next().unwrap_or_default();
+        if let Some(end) = line.iter().position(|&c| c == b'\'') {
+            for _ in 0..=end {
+                self.read_char();
+            }
+        }
+        Err(LexError::InvalidChar)
     }
 
-    /// Reads an integer input and returns it.
-    fn read_integer(&mut self) -> u64 {
+    /// Reads an integer input and returns it, or an error if it doesn't fit in a `u64`.
+    fn read_integer(&mut self) -> Result<u64, LexError> {
         let mut integer = String::new();
 
         loop {
@@ -145,7 +170,7 @@ impl Lexer {
             self.read_char();
         }
 
-        integer.parse().unwrap() // TODO HANDLE ERRR
+        integer.parse().map_err(|_| LexError::IntegerOverflow)
     }
 
     /// Reads an ident input and returns it.
@@ -228,3 +228,59 @@ fn tokenize_complete() {
 
     check_tokens(input, expected)
 }
+
+fn check_spans(input: &str, expected: Vec<(Token, &str)>) {
+    let mut lex = Lexer::new(input.to_string());
+
+    for (token, text) in expected {
+        let (next_token, span) = lex.next_token();
+        assert_eq!(next_token, token);
+        assert_eq!(&input[span.start..=span.end], text);
+    }
+    assert_eq!(lex.next_token().0, Token::Eof);
+}
+
+#[test]
+fn invalid_char_literals() {
+    check_spans(
+        "'ab' '' 'c' 'd\n'",
+        vec![
+            (Token::Illegal(LexError::InvalidChar), "'ab'"),
+            (Token::Illegal(LexError::InvalidChar), "''"),
+            (Token::Char('c'), "'c'"),
+            (Token::Illegal(LexError::InvalidChar), "'d"),
+            (Token::Newline, "\n"),
+            (Token::Illegal(LexError::InvalidChar), "'"),
+        ],
+    );
+}
+
+#[test]
+fn unterminated_string() {
+    check_spans(
+        "foo \"bar;\n",
+        vec![
+            (Token::Ident(String::from("foo")), "foo"),
+            (Token::Illegal(LexError::UnterminatedString), "\"bar;\n"),
+        ],
+    );
+
+    let mut lex = Lexer::new("\"a".to_string());
+    lex.next_token();
+    assert_eq!(lex.next_token(), (Token::Eof, Span::new(2, 2)));
+}
+
+#[test]
+fn integer_overflow() {
+    check_spans(
+        "18446744073709551615 18446744073709551616;",
+        vec![
+            (Token::Int(u64::MAX), "18446744073709551615"),
+            (
+                Token::Illegal(LexError::IntegerOverflow),
+                "18446744073709551616",
+            ),
+            (Token::SemiColon, ";"),
+        ],
+    );
+}
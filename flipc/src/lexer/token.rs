@@ -1,13 +1,25 @@
 use alloc::string::String;
 use core::fmt::{Display, Formatter, Result};
 
+/// Why the lexer couldn't read a token, reported by the parser.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LexError {
+    UnknownCharacter,
+    /// A string with no closing `"` before the end of the input.
+    UnterminatedString,
+    /// A char literal that is empty, holds more than one character or isn't closed.
+    InvalidChar,
+    /// An integer too large for a `u64`.
+    IntegerOverflow,
+}
+
 /// Token enum representing a lexical token in the input source.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     // Special
     Assign,
     Ident(String),
-    Illegal,
+    Illegal(LexError),
     Eof,
     Newline,
     // Whitespace sequence of length n
@@ -36,6 +48,7 @@ pub enum Token {
     Else,
     While,
     Return,
+    Extern,
 
     // Separators
     LParen,
@@ -51,7 +64,7 @@ impl Display for Token {
         let display = match self {
             Token::Assign => "=",
             Token::Ident(val) => return write!(f, "Ident({})", val),
-            Token::Illegal => "<Illegal>",
+            Token::Illegal(_) => "<Illegal>",
             Token::Eof => "EoF",
             Token::Newline => r#"\n"#,
             Token::Int(val) => return write!(f, "Integer({})", val),
@@ -72,6 +85,7 @@ impl Display for Token {
             Token::Else => "else",
             Token::While => "while",
             Token::Return => "return",
+            Token::Extern => "extern",
             Token::LParen => "(",
             Token::RParen => ")",
             Token::LBrace => "{",
@@ -104,7 +118,7 @@ impl From<u8> for Token {
             b',' => Self::Comma,
             b';' => Self::SemiColon,
 
-            _ => Self::Illegal,
+            _ => Self::Illegal(LexError::UnknownCharacter),
         }
     }
 }
@@ -117,7 +131,7 @@ impl From<(u8, u8)> for Token {
             (b'<', b'=') => Self::LessThanEqual,
             (b'>', b'=') => Self::GreaterThanEqual,
 
-            _ => Self::Illegal,
+            _ => Self::Illegal(LexError::UnknownCharacter),
         }
     }
 }
@@ -136,6 +150,7 @@ impl From<String> for Token {
             "else" => Self::Else,
             "while" => Self::While,
             "return" => Self::Return,
+            "extern" => Self::Extern,
 
             _ => Self::Ident(value),
         }
@@ -148,7 +163,10 @@ mod tests {
 
     #[test]
     fn illegal() {
-        assert_eq!(Token::from(b' '), Token::Illegal);
+        assert_eq!(
+            Token::from(b' '),
+            Token::Illegal(LexError::UnknownCharacter)
+        );
     }
 
     #[test]
@@ -248,6 +266,7 @@ mod tests {
         assert_eq!(Token::from(String::from("else")), Token::Else);
         assert_eq!(Token::from(String::from("while")), Token::While);
         assert_eq!(Token::from(String::from("return")), Token::Return);
+        assert_eq!(Token::from(String::from("extern")), Token::Extern);
         assert_eq!(
             Token::from(String::from("foobar")),
             Token::Ident(String::from("foobar"))
@@ -7,15 +7,18 @@ extern crate alloc;
 mod ast;
 pub use ast::Ast;
 
+pub mod coverage;
+
 mod diagnostics;
 mod error;
 mod escape_codes;
 mod evaluator;
 pub mod frontend;
+mod json;
 mod lexer;
 mod parser;
 mod passes;
-pub use passes::CodeGenerator;
 pub use passes::Pass;
+pub use passes::{to_asm, to_object, CodeGenerator, CodegenOptions, LineTable, OptLevel};
 mod source;
 mod span;
@@ -27,6 +27,10 @@ pub fn parse_program(parser: &mut Parser) -> Program {
 }
 
 pub fn parse_function(parser: &mut Parser) -> Option<Function> {
+    let external = parser.current_token_is(&Token::Extern);
+    if external {
+        parser.step();
+    }
     let (mut token, mut span) = parser.consume();
 
     let return_type: Type = match token {
@@ -53,10 +57,16 @@ pub fn parse_function(parser: &mut Parser) -> Option<Function> {
             let parameters = parse_parameters(parser);
             parser.expect(Token::RParen);
 
-            // Body Block
-            parser.expect(Token::LBrace);
-            let body = parse_sequence(parser, Token::RBrace);
-            parser.expect(Token::RBrace);
+            // Body Block, declarations end at the parameters
+            let body = if external {
+                parser.expect(Token::SemiColon);
+                Ast::sequence(Vec::new(), parser.current_span())
+            } else {
+                parser.expect(Token::LBrace);
+                let body = parse_sequence(parser, Token::RBrace);
+                parser.expect(Token::RBrace);
+                body
+            };
 
             let pattern = Pattern {
                 name: name.to_owned(),
@@ -71,6 +81,7 @@ pub fn parse_function(parser: &mut Parser) -> Option<Function> {
                 pattern,
                 parameters,
                 body,
+                external,
                 span: Span::combine(vec![&span, &parser.current_span()]),
             })
         }
@@ -239,7 +250,7 @@ fn parse_call(parser: &mut Parser, pattern: Pattern, start_span: Span) -> Ast {
 // TODO: Test
 fn parse_arguments(parser: &mut Parser) -> Vec<Ast> {
     let mut args: Vec<Ast> = Vec::new();
-    while !parser.current_token_is(&Token::RParen) {
+    while !parser.current_token_is(&Token::RParen) && !parser.current_token_is(&Token::Eof) {
         args.push(parse_expression(parser));
 
         if !parser.current_token_is(&Token::RParen) {
@@ -344,6 +355,23 @@ pub fn parse_unary(parser: &mut Parser) -> Ast {
 }
 
 pub fn parse_primary(parser: &mut Parser) -> Ast {
+    let primary = matches!(
+        parser.current_token(),
+        Token::Int(_) | Token::Char(_) | Token::String(_) | Token::LParen | Token::Ident(_)
+    );
+    if !primary {
+        // Left for the caller to recover from, usually the `;` ending the statement. A lexer
+        // error in place of the expression was already reported
+        if parser.after_illegal {
+            return Ast::Error;
+        }
+        let span = parser.current_span();
+        parser
+            .diagnostics
+            .borrow_mut()
+            .expected_expression(parser.current_token(), span);
+        return Ast::Error;
+    }
     let (token, span) = parser.consume();
 
     match &token {
@@ -368,7 +396,7 @@ pub fn parse_primary(parser: &mut Parser) -> Ast {
             // Var
             Ast::variable(symbol.to_owned(), span)
         }
-        _ => panic!("Really shouldn't reach here, implement fatal error instead"),
+        _ => unreachable!(),
     }
 }
 
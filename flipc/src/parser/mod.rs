@@ -3,7 +3,7 @@ use core::mem;
 use self::combinators::parse_program;
 use crate::ast::Program;
 use crate::diagnostics::DiagnosticsCell;
-use crate::lexer::{Lexer, Token};
+use crate::lexer::{LexError, Lexer, Token};
 use crate::span::Span;
 
 pub mod combinators;
@@ -15,22 +15,29 @@ pub struct Parser<'a> {
     lexer: &'a mut Lexer,
     current_token: (Token, Span),
     next_token: (Token, Span),
+    // last token stepped past, other than whitespace and lexer errors
+    previous_span: Span,
+    // a lexer error was stepped past since `previous_span`
+    after_illegal: bool,
 
     pub diagnostics: DiagnosticsCell,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(lexer: &'a mut Lexer, diagnostics: DiagnosticsCell) -> Self {
-        // Need to handle case where these tokens may be illegal
         let current_token = lexer.next_token();
         let next_token = lexer.next_token();
 
-        Self {
+        let mut parser = Self {
             lexer,
             current_token,
             next_token,
+            previous_span: Span::default(),
+            after_illegal: false,
             diagnostics,
-        }
+        };
+        parser.skip_ignored();
+        parser
     }
 
     pub fn parse(&mut self) -> Program {
@@ -42,19 +49,38 @@ impl<'a> Parser<'a> {
             return;
         }
 
+        match self.current_token.0 {
+            Token::Whitespace => {}
+            Token::Illegal(_) => self.after_illegal = true,
+            _ => {
+                self.previous_span = self.current_token.1;
+                self.after_illegal = false;
+            }
+        }
         // Cheaper than cloning
         self.current_token = self.lexer.next_token();
         mem::swap(&mut self.current_token, &mut self.next_token);
-        if self.current_token.0 == Token::Whitespace {
-            self.step();
-        }
-
-        if self.current_token.0 == Token::Illegal {
-            self.diagnostics
-                .borrow_mut()
-                .illegal_token(self.current_token.1);
+        self.skip_ignored();
+    }
 
-            self.step();
+    /// Steps past whitespace and tokens the lexer couldn't read, reporting each lexer error.
+    fn skip_ignored(&mut self) {
+        match self.current_token.0 {
+            Token::Whitespace => self.step(),
+            Token::Illegal(error) => {
+                let span = self.current_token.1;
+                let mut diagnostics = self.diagnostics.borrow_mut();
+                match error {
+                    LexError::UnknownCharacter => diagnostics.illegal_token(span),
+                    LexError::UnterminatedString => diagnostics.unterminated_string(span),
+                    LexError::InvalidChar => diagnostics.invalid_char_literal(span),
+                    LexError::IntegerOverflow => diagnostics.integer_overflow(span),
+                }
+                drop(diagnostics);
+
+                self.step();
+            }
+            _ => {}
         }
     }
 
@@ -72,16 +98,30 @@ impl<'a> Parser<'a> {
     }
 
     pub fn expect_with_outcome(&mut self, expected: Token) -> bool {
-        let (token, span) = self.consume();
-        if token != expected {
-            self.diagnostics
-                .borrow_mut()
-                .expected_token(&expected, &token, span);
+        if self.current_token_is(&expected) {
+            self.step();
+            return true;
+        }
 
-            return false;
+        let (token, span) = self.current_token.clone();
+        match expected {
+            // a lexer error ran to the end of the input, such as an unterminated string
+            _ if self.after_illegal && token == Token::Eof => {}
+            // usually forgotten at the end of the previous token, unless that token is the real
+            // mistake. The token found is left for the enclosing rule, which likely expects it
+            Token::SemiColon | Token::RParen | Token::RBrace if !self.after_illegal => self
+                .diagnostics
+                .borrow_mut()
+                .missing_token(&expected, &token, span, self.previous_span.end + 1),
+            _ => {
+                self.diagnostics
+                    .borrow_mut()
+                    .expected_token(&expected, &token, span);
+                self.step();
+            }
         }
 
-        true
+        false
     }
 
     pub fn optional(&mut self, optional: Token) {
@@ -374,3 +374,24 @@ fn function_call_multiple_mixed_args() {
 
     assert_program(input, expected);
 }
+
+#[test]
+fn function_extern() {
+    let input = r#"extern int add(x, y);
+    void main() { x = 4; }"#;
+    let expected = HashMap::from([
+        (
+            "add".to_string(),
+            vec![
+                ASTNode::Variable("x".to_string()),
+                ASTNode::Variable("y".to_string()),
+            ],
+        ),
+        (
+            "main".to_string(),
+            vec![ASTNode::Variable("x".to_string()), ASTNode::Integer(4)],
+        ),
+    ]);
+
+    assert_program(input, expected);
+}
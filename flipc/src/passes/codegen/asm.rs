@@ -0,0 +1,92 @@
+//! Assembler source for generated code, in the syntax of the `flipvm` `asm` tool.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use flipvm::obj::Relocation;
+use flipvm::op::Instruction;
+use flipvm::DebugInfo;
+
+/// The address a branch at `address` jumps to.
+fn branch_target(ins: &Instruction, address: u32) -> Option<u32> {
+    match ins {
+        Instruction::Branch(lit) | Instruction::BranchIf(lit) | Instruction::BranchIfNot(lit) => {
+            Some((address as i64 + lit.as_signed() as i64 * 2) as u32)
+        }
+        _ => None,
+    }
+}
+
+/// Writes `instructions`, generated at `offset`, as assembler source.
+///
+/// Functions start with a `:name` label and branch targets get a `:lbl_<address>` label. Branches
+/// and function addresses refer to their labels, so the output can be edited and re-assembled.
+/// Functions defined elsewhere are declared with `.extern`.
+pub fn to_asm(
+    instructions: &[Instruction],
+    debug_info: &DebugInfo,
+    relocations: &[Relocation],
+    offset: u32,
+) -> String {
+    let address = |i: usize| offset + i as u32 * 2;
+    let mut labels: BTreeMap<u32, String> = BTreeMap::new();
+    for func in debug_info.functions() {
+        labels.insert(func.start as u32, func.name.clone());
+    }
+    for (i, ins) in instructions.iter().enumerate() {
+        if let Some(target) = branch_target(ins, address(i)) {
+            labels
+                .entry(target)
+                .or_insert_with(|| format!("lbl_{:04x}", target));
+        }
+    }
+    let functions: Vec<u32> = debug_info
+        .functions()
+        .iter()
+        .map(|f| f.start as u32)
+        .collect();
+    // relocation offsets are relative to the start of the code
+    let references: HashMap<u32, &str> = relocations
+        .iter()
+        .map(|r| (offset + r.offset, r.symbol.as_str()))
+        .collect();
+
+    let mut externs: Vec<&str> = references
+        .values()
+        .copied()
+        .filter(|symbol| debug_info.function(symbol).is_none())
+        .collect();
+    externs.sort();
+    externs.dedup();
+
+    let mut out = String::new();
+    if offset != 0 {
+        writeln!(out, ".offsetPC {}", offset / 2).unwrap();
+    }
+    if !externs.is_empty() {
+        writeln!(out, ".extern {}", externs.join(" ")).unwrap();
+    }
+    for (i, ins) in instructions.iter().enumerate() {
+        let addr = address(i);
+        if let Some(label) = labels.get(&addr) {
+            if functions.contains(&addr) && i != 0 {
+                out.push('\n');
+            }
+            writeln!(out, ":{}", label).unwrap();
+        }
+
+        let text = ins.to_string();
+        let op = text.split(' ').next().unwrap_or_default();
+        match (ins, references.get(&addr), branch_target(ins, addr)) {
+            (Instruction::Imm(r, _), Some(symbol), _) => writeln!(out, "Imm {} !{}", r, symbol),
+            (_, _, Some(target)) => writeln!(out, "{} @{}", op, labels[&target]),
+            _ => writeln!(out, "{}", text),
+        }
+        .unwrap();
+    }
+    // branches past the last instruction
+    for (_, label) in labels.range(address(instructions.len())..) {
+        writeln!(out, ":{}", label).unwrap();
+    }
+    out
+}
@@ -2,6 +2,7 @@ use crate::ast::visitor::Walkable;
 use crate::passes::symbol_table::DefinitionType;
 use crate::Ast;
 use flipvm::op::{Instruction, Literal12Bit, Literal7Bit, Nibble, StackOp, TestOp};
+use flipvm::FunctionInfo;
 use flipvm::Register::*;
 
 use crate::ast::visitor::Visitor;
@@ -10,179 +11,307 @@ use crate::ast::{
     Variable, While,
 };
 
-use super::CodeGenerator;
+use super::{CodeGenerator, FutureType, OptLevel};
+
+/// The value of `bin` if it only operates on integer literals, computed as the emitted code would
+/// with wrapping word arithmetic and unsigned comparisons. Operators codegen can't emit are not
+/// folded.
+fn fold(bin: &Binary) -> Option<u16> {
+    let (left, right) = (constant(&bin.left)?, constant(&bin.right)?);
+    let value = match bin.op {
+        BinOp::Add => left.wrapping_add(right),
+        BinOp::Sub => left.wrapping_sub(right),
+        BinOp::Mul | BinOp::Div => return None,
+        BinOp::Eq => (left == right) as u16,
+        BinOp::NotEq => (left != right) as u16,
+        BinOp::LessThan => (left < right) as u16,
+        BinOp::LessThanEq => (left <= right) as u16,
+        BinOp::GreaterThan => (left > right) as u16,
+        BinOp::GreaterThanEq => (left >= right) as u16,
+    };
+    Some(value)
+}
 
-impl Visitor for CodeGenerator<'_> {
-    fn visit_function(&mut self, func: &Function) {
-        self.define_label(func.pattern.name.clone());
+fn constant(ast: &Ast) -> Option<u16> {
+    match ast {
+        Ast::Literal(Literal {
+            kind: LiteralKind::Int(i),
+            ..
+        }) => u16::try_from(*i).ok(),
+        Ast::Binary(bin) => fold(bin),
+        _ => None,
+    }
+}
 
-        let local_off = format!("__internal_{}_local_offset", func.pattern.name);
-        self.addimm_future(SP, local_off.clone());
+impl CodeGenerator<'_> {
+    /// Pushes the integer `i`.
+    fn emit_int(&mut self, i: u64) {
+        if i <= 0xfff {
+            self.emit(Instruction::Imm(
+                C,
+                Literal12Bit::new_checked(i as u16).unwrap(),
+            ));
+            self.emit(Instruction::Stack(C, SP, StackOp::Push));
+        } else if i <= 0xffff && (i & 0xf) == 0 {
+            self.emit(Instruction::Imm(
+                C,
+                Literal12Bit::new_checked((i >> 4) as u16).unwrap(),
+            ));
+            self.emit(Instruction::ShiftLeft(
+                C,
+                C,
+                Nibble::new_checked(4).unwrap(),
+            ));
+            self.emit(Instruction::Stack(C, SP, StackOp::Push));
+        } else if i <= 0xffff {
+            self.emit(Instruction::Imm(
+                C,
+                Literal12Bit::new_checked((i >> 4) as u16).unwrap(),
+            ));
+            self.emit(Instruction::ShiftLeft(
+                C,
+                C,
+                Nibble::new_checked(4).unwrap(),
+            ));
+            self.emit(Instruction::AddImm(
+                C,
+                Literal7Bit::new_checked((i & 0xf) as u8).unwrap(),
+            ));
+            self.emit(Instruction::Stack(C, SP, StackOp::Push));
+        } else {
+            unimplemented!("int too large");
+        }
+    }
 
-        self.enter_scope();
+    /// Pops `bytes` off the stack, in steps of the largest signed immediate.
+    fn drop_stack(&mut self, bytes: usize) {
+        let mut remaining = bytes;
+        while remaining > 0 {
+            let step = remaining.min(64);
+            self.emit(Instruction::AddImmSigned(
+                SP,
+                Literal7Bit::from_signed(-(step as i8)).unwrap(),
+            ));
+            remaining -= step;
+        }
+    }
+}
+
+impl Visitor for CodeGenerator<'_> {
+    fn visit_function(&mut self, func: &Function) {
+        if func.external {
+            // calls are left to the linker
+            self.enter_scope();
+            self.exit_scope();
+            return;
+        }
+        self.with_span(func.span, |cg| {
+            cg.define_label(func.pattern.name.clone());
+            cg.function = Some(FunctionInfo {
+                name: func.pattern.name.clone(),
+                start: cg.current_offset as u16,
+                end: cg.current_offset as u16,
+                locals: Vec::new(),
+            });
+
+            let local_off = format!("__internal_{}_local_offset", func.pattern.name);
+            cg.addimm_future(SP, local_off.clone());
+
+            cg.enter_scope();
+            for param in &func.parameters {
+                let arg_idx = cg
+                    .symbol_table
+                    .lookup_symbol(param, cg.current_scope)
+                    .unwrap()
+                    .symbol_idx;
+                // matches the LoadStackOffset in visit_variable
+                cg.add_local(&param.name, -((arg_idx as i16 + 3) * 2));
+            }
 
-        func.body.walk(self);
-        let local_count = self.symbol_table.local_count();
-        self.exit_scope();
+            func.body.walk(cg);
+            let local_count = cg.symbol_table.local_count();
+            cg.exit_scope();
 
-        self.emit_function_exit();
-        self.define_label_offset(local_off, local_count as u32 * 2);
+            cg.emit_function_exit();
+            cg.define_label_offset(local_off, local_count as u32 * 2);
+            if let Some(mut info) = cg.function.take() {
+                info.end = cg.current_offset as u16;
+                cg.debug_info.add_function(info);
+            }
+        })
     }
 
     fn visit_return(&mut self, ret: &Ast) {
-        ret.walk(self);
-        self.emit(Instruction::Stack(A, SP, StackOp::Pop));
+        self.with_span(ret.span(), |cg| {
+            ret.walk(cg);
+            cg.emit(Instruction::Stack(A, SP, StackOp::Pop));
 
-        // FIXME: when return exists emit function exit done twice
-        self.emit_function_exit();
+            // FIXME: when return exists emit function exit done twice
+            cg.emit_function_exit();
+        })
     }
 
     fn visit_call(&mut self, call: &Call) {
-        for arg in call.arguments.iter().rev() {
-            arg.walk(self);
-        }
+        self.with_span(call.span, |cg| {
+            for arg in call.arguments.iter().rev() {
+                arg.walk(cg);
+            }
+
+            cg.imm_future(C, call.pattern.name.clone());
+            cg.emit(Instruction::Call(C));
 
-        self.emit(Instruction::Stack(BP, SP, StackOp::Push));
-        self.emit(Instruction::Stack(PC, SP, StackOp::Push));
-        self.emit(Instruction::Add(SP, Zero, BP));
-        self.imm_future(PC, call.pattern.name.clone());
+            // Drop arguments
+            cg.drop_stack(call.arguments.len() * 2);
 
-        // Push return
-        self.emit(Instruction::Stack(A, SP, StackOp::Push));
+            // Push return
+            cg.emit(Instruction::Stack(A, SP, StackOp::Push));
+        })
     }
 
     fn visit_if(&mut self, if_expr: &If) {
         let block_id = format!("{}{}", if_expr.span.start, if_expr.span.end);
-        let true_label = format!("lbl_{}_if_true", block_id);
         let out_label = format!("lbl_{}_if_out", block_id);
-        if_expr.condition.walk(self);
+        self.with_span(if_expr.span, |cg| {
+            if_expr.condition.walk(cg);
 
-        // test cond == false
-        self.emit(Instruction::Stack(C, SP, StackOp::Pop));
-        self.emit(Instruction::Test(C, Zero, TestOp::BothZero));
-        self.emit(Instruction::AddIf(PC, PC, Nibble::new_checked(2).unwrap()));
-        self.imm_future(PC, true_label.clone());
+            // test cond == false
+            cg.emit(Instruction::Stack(C, SP, StackOp::Pop));
+            cg.emit(Instruction::Test(C, Zero, TestOp::BothZero));
+            cg.branch_future(FutureType::BranchIf, out_label.clone());
 
-        self.imm_future(PC, out_label.clone());
+            // if cond == true
+            cg.enter_scope();
+            if_expr.then.walk(cg);
+            cg.exit_scope();
 
-        // if cond == true
-        self.define_label(true_label);
-        self.enter_scope();
-        if_expr.then.walk(self);
-        self.exit_scope();
-
-        self.imm_future(PC, out_label.clone());
-        self.define_label(out_label);
+            cg.define_label(out_label);
+        })
     }
 
     fn visit_while(&mut self, while_expr: &While) {
         let block_id = format!("{}{}", while_expr.span.start, while_expr.span.end);
         let cond_label = format!("lbl_{}_while_cond", block_id);
         let out_label = format!("lbl_{}_while_out", block_id);
-        self.define_label(cond_label.clone());
-        while_expr.condition.walk(self);
-
-        // Cond
-        self.emit(Instruction::Stack(C, SP, StackOp::Pop));
-        self.emit(Instruction::Test(C, Zero, TestOp::EitherNonZero));
-        self.emit(Instruction::AddIf(PC, PC, Nibble::new_checked(2).unwrap()));
-        self.imm_future(PC, out_label.clone());
-
-        // Resolution
-        self.enter_scope();
-        while_expr.then.walk(self);
-        self.exit_scope();
-        self.imm_future(PC, cond_label);
-        self.define_label(out_label);
+        self.with_span(while_expr.span, |cg| {
+            cg.define_label(cond_label.clone());
+            while_expr.condition.walk(cg);
+
+            // Cond
+            cg.emit(Instruction::Stack(C, SP, StackOp::Pop));
+            cg.emit(Instruction::Test(C, Zero, TestOp::BothZero));
+            cg.branch_future(FutureType::BranchIf, out_label.clone());
+
+            // Resolution
+            cg.enter_scope();
+            while_expr.then.walk(cg);
+            cg.exit_scope();
+            cg.branch_future(FutureType::Branch, cond_label);
+            cg.define_label(out_label);
+        })
     }
 
     fn visit_definition(&mut self, def: &Definition) {
-        def.value.walk(self);
-
-        let local_idx = self
-            .symbol_table
-            .lookup_symbol(&def.pattern, self.current_scope)
-            .unwrap()
-            .symbol_idx;
-        let addr = local_idx as u8 * 2;
-
-        // Walk value to stack and store
-        self.emit(Instruction::Stack(C, SP, StackOp::Pop));
-        self.emit(Instruction::Add(BP, Zero, B));
-        self.emit(Instruction::AddImm(
-            B,
-            Literal7Bit::new_checked(addr).unwrap(),
-        ));
-        self.emit(Instruction::StoreWord(B, Zero, C));
+        self.with_span(def.span, |cg| {
+            def.value.walk(cg);
+
+            let local_idx = cg
+                .symbol_table
+                .lookup_symbol(&def.pattern, cg.current_scope)
+                .unwrap()
+                .symbol_idx;
+            let addr = local_idx as u8 * 2;
+            cg.add_local(&def.pattern.name, addr as i16);
+
+            // Walk value to stack and store
+            cg.emit(Instruction::Stack(C, SP, StackOp::Pop));
+            cg.emit(Instruction::Add(BP, Zero, B));
+            cg.emit(Instruction::AddImm(
+                B,
+                Literal7Bit::new_checked(addr).unwrap(),
+            ));
+            cg.emit(Instruction::StoreWord(B, Zero, C));
+        })
     }
 
     fn visit_assignment(&mut self, def: &Assignment) {
-        def.value.walk(self);
-
-        let local_idx = self
-            .symbol_table
-            .lookup_symbol(&def.pattern, self.current_scope)
-            .unwrap()
-            .symbol_idx;
-        let addr = local_idx as u8 * 2;
-
-        // Walk value to stack and store
-        self.emit(Instruction::Stack(C, SP, StackOp::Pop));
-        self.emit(Instruction::Add(BP, Zero, B));
-        self.emit(Instruction::AddImm(
-            B,
-            Literal7Bit::new_checked(addr).unwrap(),
-        ));
-        self.emit(Instruction::StoreWord(B, Zero, C));
+        self.with_span(def.span, |cg| {
+            def.value.walk(cg);
+
+            let local_idx = cg
+                .symbol_table
+                .lookup_symbol(&def.pattern, cg.current_scope)
+                .unwrap()
+                .symbol_idx;
+            let addr = local_idx as u8 * 2;
+
+            // Walk value to stack and store
+            cg.emit(Instruction::Stack(C, SP, StackOp::Pop));
+            cg.emit(Instruction::Add(BP, Zero, B));
+            cg.emit(Instruction::AddImm(
+                B,
+                Literal7Bit::new_checked(addr).unwrap(),
+            ));
+            cg.emit(Instruction::StoreWord(B, Zero, C));
+        })
     }
 
     fn visit_variable(&mut self, var: &Variable) {
-        let (var_idx, def_type) = {
-            let var_info = self
-                .symbol_table
-                .lookup_symbol(var, self.current_scope)
-                .unwrap();
-            (var_info.symbol_idx, var_info.def_type.clone())
-        };
-
-        match def_type {
-            DefinitionType::Local => {
-                // Load value from stack
-                self.emit(Instruction::Add(BP, Zero, C));
-                self.emit(Instruction::AddImm(
-                    C,
-                    Literal7Bit::new_checked(var_idx as u8 * 2).unwrap(),
-                ));
-                self.emit(Instruction::LoadWord(C, C, Zero));
-                self.emit(Instruction::Stack(C, SP, StackOp::Push));
-            }
-            DefinitionType::Argument => {
-                self.emit(Instruction::LoadStackOffset(
-                    C,
-                    BP,
-                    Nibble::new_checked(var_idx as u8 + 3).unwrap(),
-                ));
-                self.emit(Instruction::Stack(C, SP, StackOp::Push));
+        self.with_span(var.span, |cg| {
+            let (var_idx, def_type) = {
+                let var_info = cg
+                    .symbol_table
+                    .lookup_symbol(var, cg.current_scope)
+                    .unwrap();
+                (var_info.symbol_idx, var_info.def_type.clone())
+            };
+
+            match def_type {
+                DefinitionType::Local => {
+                    // Load value from stack
+                    cg.emit(Instruction::Add(BP, Zero, C));
+                    cg.emit(Instruction::AddImm(
+                        C,
+                        Literal7Bit::new_checked(var_idx as u8 * 2).unwrap(),
+                    ));
+                    cg.emit(Instruction::LoadWord(C, C, Zero));
+                    cg.emit(Instruction::Stack(C, SP, StackOp::Push));
+                }
+                DefinitionType::Argument => {
+                    cg.emit(Instruction::LoadStackOffset(
+                        C,
+                        BP,
+                        Nibble::new_checked(var_idx as u8 + 3).unwrap(),
+                    ));
+                    cg.emit(Instruction::Stack(C, SP, StackOp::Push));
+                }
             }
-        }
+        })
     }
 
     fn visit_binary(&mut self, bin: &Binary) {
-        bin.right.walk(self);
-        bin.left.walk(self);
-
-        match bin.op {
-            BinOp::Add => self.emit(Instruction::Stack(Zero, SP, StackOp::Add)),
-            BinOp::Sub => self.emit(Instruction::Stack(Zero, SP, StackOp::Sub)),
-            BinOp::Mul => unimplemented!("mul"),
-            BinOp::Div => unimplemented!("div"),
-            BinOp::Eq => self.emit_compare(Instruction::Test(B, C, TestOp::Eq)),
-            BinOp::NotEq => self.emit_compare(Instruction::Test(B, C, TestOp::Neq)),
-            BinOp::LessThan => self.emit_compare(Instruction::Test(B, C, TestOp::Lt)),
-            BinOp::LessThanEq => self.emit_compare(Instruction::Test(B, C, TestOp::Lte)),
-            BinOp::GreaterThan => self.emit_compare(Instruction::Test(B, C, TestOp::Gt)),
-            BinOp::GreaterThanEq => self.emit_compare(Instruction::Test(B, C, TestOp::Gte)),
-        }
+        self.with_span(bin.span, |cg| {
+            if cg.opt_level >= OptLevel::Full {
+                if let Some(value) = fold(bin) {
+                    cg.emit_int(value as u64);
+                    return;
+                }
+            }
+            bin.right.walk(cg);
+            bin.left.walk(cg);
+
+            match bin.op {
+                BinOp::Add => cg.emit(Instruction::Stack(Zero, SP, StackOp::Add)),
+                BinOp::Sub => cg.emit(Instruction::Stack(Zero, SP, StackOp::Sub)),
+                BinOp::Mul => unimplemented!("mul"),
+                BinOp::Div => unimplemented!("div"),
+                BinOp::Eq => cg.emit_compare(Instruction::Test(B, C, TestOp::Eq)),
+                BinOp::NotEq => cg.emit_compare(Instruction::Test(B, C, TestOp::Neq)),
+                BinOp::LessThan => cg.emit_compare(Instruction::Test(B, C, TestOp::Lt)),
+                BinOp::LessThanEq => cg.emit_compare(Instruction::Test(B, C, TestOp::Lte)),
+                BinOp::GreaterThan => cg.emit_compare(Instruction::Test(B, C, TestOp::Gt)),
+                BinOp::GreaterThanEq => cg.emit_compare(Instruction::Test(B, C, TestOp::Gte)),
+            }
+        })
     }
 
     fn visit_unary(&mut self, _un: &Unary) {
@@ -190,52 +319,16 @@ impl Visitor for CodeGenerator<'_> {
     }
 
     fn visit_literal(&mut self, lit: &Literal) {
-        match &lit.kind {
-            LiteralKind::Int(i) => {
-                if *i <= 0xfff {
-                    self.emit(Instruction::Imm(
-                        C,
-                        Literal12Bit::new_checked(*i as u16).unwrap(),
-                    ));
-                    self.emit(Instruction::Stack(C, SP, StackOp::Push));
-                } else if *i <= 0xffff && (i & 0xf) == 0 {
-                    self.emit(Instruction::Imm(
-                        C,
-                        Literal12Bit::new_checked((i >> 4) as u16).unwrap(),
-                    ));
-                    self.emit(Instruction::ShiftLeft(
-                        C,
-                        C,
-                        Nibble::new_checked(4).unwrap(),
-                    ));
-                    self.emit(Instruction::Stack(C, SP, StackOp::Push));
-                } else if *i <= 0xffff {
-                    self.emit(Instruction::Imm(
-                        C,
-                        Literal12Bit::new_checked((i >> 4) as u16).unwrap(),
-                    ));
-                    self.emit(Instruction::ShiftLeft(
-                        C,
-                        C,
-                        Nibble::new_checked(4).unwrap(),
-                    ));
-                    self.emit(Instruction::AddImm(
-                        C,
-                        Literal7Bit::new_checked((i & 0xf) as u8).unwrap(),
-                    ));
-                    self.emit(Instruction::Stack(C, SP, StackOp::Push));
-                } else {
-                    unimplemented!("int too large");
-                }
-            }
+        self.with_span(lit.span, |cg| match &lit.kind {
+            LiteralKind::Int(i) => cg.emit_int(*i),
             LiteralKind::Char(ch) => {
-                self.emit(Instruction::Imm(
+                cg.emit(Instruction::Imm(
                     C,
                     Literal12Bit::new_checked(*ch as u16).unwrap(),
                 ));
-                self.emit(Instruction::Stack(C, SP, StackOp::Push));
+                cg.emit(Instruction::Stack(C, SP, StackOp::Push));
             }
             LiteralKind::String(_) => unimplemented!("string literal"),
-        }
+        })
     }
 }
@@ -1,19 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::mem;
 
 use crate::ast::visitor::Visitor;
 use crate::ast::Program;
 use crate::passes::SymbolTable;
+use crate::source::Source;
+use crate::span::Span;
 
-use flipvm::op::{Instruction, Literal12Bit, Literal7Bit, Nibble, StackOp};
+use flipvm::exe::SectionKind;
+use flipvm::obj::{Object, Relocation, RelocationKind};
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit, Literal7Bit, Nibble, StackOp};
 use flipvm::Register::{self, *};
+use flipvm::{DebugInfo, FunctionInfo, LocalSlot, SourceLocation};
 
 use super::Pass;
 
+pub use asm::to_asm;
+
+mod asm;
 mod generators;
 #[cfg(test)]
 mod tests;
 
+/// How much effort the code generator puts into smaller code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// Every node is generated as is.
+    #[default]
+    None,
+    /// Drops values pushed and popped straight back into the same register, and repeated
+    /// returns.
+    Peephole,
+    /// Also folds arithmetic on constants.
+    Full,
+}
+
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    /// Address the code is loaded at.
+    pub offset: u32,
+    /// Function called by the startup code.
+    pub entry: String,
+    pub opt_level: OptLevel,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        Self {
+            offset: 0x0,
+            entry: "main".to_string(),
+            opt_level: OptLevel::None,
+        }
+    }
+}
+
 pub struct CodeGenerator<'a> {
+    opt_level: OptLevel,
     inital_offset: u32,
     current_offset: u32,
 
@@ -24,40 +66,144 @@ pub struct CodeGenerator<'a> {
     current_scope: usize,
 
     labels: HashMap<String, u32>,
+    // function boundaries and frame slots, for profilers and debuggers
+    debug_info: DebugInfo,
+    function: Option<FunctionInfo>,
+    // span of the node being generated, recorded for each emitted instruction
+    current_span: Option<Span>,
+    line_table: LineTable,
     // TODO: Look into alternatives that arent O(n)
     //unlinked_references: HashMap<String, Vec<(usize, Register)>>, // O(1)
     unlinked_references: Vec<(usize, FutureType, Register, String)>,
+    // labels too far for a short branch, reached with `long_branch` instead
+    far_labels: HashSet<String>,
+    // a forward branch was out of range, the code has to be generated again
+    relax: bool,
+    // absolute references to function addresses, patched again if the code is moved by `link`
+    relocations: Vec<Relocation>,
+    // the first label that doesn't fit the immediate referring to it, reported after generation
+    error: Option<String>,
+}
+
+/// The source span each instruction was generated from, in address order.
+///
+/// Instructions emitted outside of any node, such as the startup code, have no entry.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LineTable {
+    entries: Vec<(u16, Span)>,
+}
+
+impl LineTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn span_at(&self, addr: u16) -> Option<Span> {
+        self.entries
+            .binary_search_by_key(&addr, |&(a, _)| a)
+            .ok()
+            .map(|i| self.entries[i].1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(u16, Span)> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds the line and column of each instruction in `source` to `debug_info`, skipping
+    /// instructions on the same position as the one before them.
+    pub fn resolve_into(&self, source: &str, debug_info: &mut DebugInfo) {
+        let source = Source::new(source.to_string());
+        let mut last = None;
+        for &(addr, span) in &self.entries {
+            let line = source.line_index(span.start);
+            let location = SourceLocation {
+                line: line as u32 + 1,
+                column: (span.start - source.line_start(line)) as u32 + 1,
+            };
+            if last != Some(location) {
+                debug_info.add_line(addr, location);
+                last = Some(location);
+            }
+        }
+    }
 }
 
 #[repr(u8)]
 enum FutureType {
     Imm,
     AddImm,
+    Branch,
+    BranchIf,
+    LongBranch,
 }
 
 impl<'a> Pass for CodeGenerator<'a> {
-    type Input = (&'a Program, &'a SymbolTable, u32);
+    type Input = (&'a Program, &'a SymbolTable, &'a CodegenOptions);
 
-    type Output = Vec<Instruction>;
+    type Output = Result<(Vec<Instruction>, DebugInfo, LineTable, Vec<Relocation>), String>;
 
-    fn run((ast, symbol_table, inital_offset): Self::Input) -> Self::Output {
-        let mut gen = CodeGenerator::new(symbol_table, inital_offset);
+    fn run((ast, symbol_table, options): Self::Input) -> Self::Output {
+        let mut far_labels = HashSet::new();
+        let mut gen = loop {
+            let mut gen = CodeGenerator::new(symbol_table, options.offset);
+            gen.opt_level = options.opt_level;
+            gen.far_labels = far_labels;
 
-        gen.emit_init();
-        gen.visit_program(ast);
+            gen.emit_init(&options.entry);
+            gen.visit_program(ast);
+            // long branches only make the code bigger, so this settles
+            if !gen.relax {
+                break gen;
+            }
+            far_labels = gen.far_labels;
+        };
 
-        // TODO: Do i keep this? + error handling
-        // Techincaly Instruction::Invalid will emit error
-        assert!(gen.unlinked_references.is_empty());
+        // Calls to `extern` functions are only known to the linker, they already have a relocation
+        for (loc, ft, r, _) in mem::take(&mut gen.unlinked_references) {
+            // TODO: Do i keep this? + error handling
+            // Techincaly Instruction::Invalid will emit error
+            assert!(matches!(ft, FutureType::Imm));
+            gen.instructions[loc] = Instruction::Imm(r, Literal12Bit::new_checked(0).unwrap());
+        }
 
-        gen.instructions
+        match gen.error {
+            Some(e) => Err(e),
+            None => Ok((
+                gen.instructions,
+                gen.debug_info,
+                gen.line_table,
+                gen.relocations,
+            )),
+        }
     }
 }
 
+/// Packages generated code as a relocatable object for `link`, with every function exported.
+///
+/// The code must have been generated at offset 0.
+pub fn to_object(
+    instructions: &[Instruction],
+    debug_info: &DebugInfo,
+    relocations: Vec<Relocation>,
+) -> Object {
+    let mut obj = Object::from_instructions(instructions);
+    for func in debug_info.functions() {
+        let global = !func.name.starts_with("__");
+        obj.define(&func.name, SectionKind::Code, func.start as u32, global);
+    }
+    obj.relocations = relocations;
+    obj
+}
+
 // FIXME: Impl Pass?
 impl<'a> CodeGenerator<'a> {
     fn new(symbol_table: &'a SymbolTable, inital_offset: u32) -> Self {
         Self {
+            opt_level: OptLevel::None,
             inital_offset,
             current_offset: inital_offset,
             instructions: Vec::new(),
@@ -65,11 +211,19 @@ impl<'a> CodeGenerator<'a> {
             max_scope: 0,
             current_scope: 0,
             labels: HashMap::new(),
+            debug_info: DebugInfo::new(),
+            function: None,
+            current_span: None,
+            line_table: LineTable::new(),
             unlinked_references: Vec::new(),
+            far_labels: HashSet::new(),
+            relax: false,
+            error: None,
+            relocations: Vec::new(),
         }
     }
 
-    fn emit_init(&mut self) {
+    fn emit_init(&mut self, entry: &str) {
         self.emit(Instruction::Imm(
             SP,
             Literal12Bit::new_checked(0x3ff).unwrap(),
@@ -79,10 +233,8 @@ impl<'a> CodeGenerator<'a> {
             SP,
             Nibble::new_checked(4).unwrap(),
         ));
-        self.emit(Instruction::Stack(BP, SP, StackOp::Push));
-        self.emit(Instruction::Stack(PC, SP, StackOp::Push));
-        self.emit(Instruction::Add(SP, Zero, BP));
-        self.imm_future(PC, "main".to_string());
+        self.imm_future(C, entry.to_string());
+        self.emit(Instruction::Call(C));
 
         self.emit(Instruction::Imm(
             C,
@@ -93,9 +245,30 @@ impl<'a> CodeGenerator<'a> {
             Zero,
             Nibble::new_checked(0).unwrap(),
         ));
+
+        self.debug_info.add_function(FunctionInfo {
+            name: "__init".to_string(),
+            start: self.inital_offset as u16,
+            end: self.current_offset as u16,
+            locals: Vec::new(),
+        });
     }
 
     fn emit(&mut self, ins: Instruction) {
+        if self.opt_level >= OptLevel::Peephole && self.pops_last_push(&ins) {
+            self.instructions.pop();
+            self.current_offset -= 2;
+            if matches!(self.line_table.entries.last(), Some(&(addr, _)) if addr as u32 == self.current_offset)
+            {
+                self.line_table.entries.pop();
+            }
+            return;
+        }
+        if let Some(span) = self.current_span {
+            self.line_table
+                .entries
+                .push((self.current_offset as u16, span));
+        }
         self.instructions.push(ins);
 
         self.current_offset += 2;
@@ -110,35 +283,48 @@ impl<'a> CodeGenerator<'a> {
         self.emit(Instruction::Stack(C, SP, StackOp::Push));
     }
 
+    /// True if `ins` pops the value the last instruction pushed back into the same register, so
+    /// neither has any effect.
+    fn pops_last_push(&self, ins: &Instruction) -> bool {
+        match (self.instructions.last(), ins) {
+            (
+                Some(Instruction::Stack(pushed, SP, StackOp::Push)),
+                Instruction::Stack(r, SP, StackOp::Pop),
+            ) => pushed == r && !self.is_label_target(),
+            _ => false,
+        }
+    }
+
+    /// True if a label points at the next instruction, so code can reach it from elsewhere.
+    fn is_label_target(&self) -> bool {
+        self.labels
+            .values()
+            .any(|&offset| offset == self.current_offset)
+    }
+
     fn emit_function_exit(&mut self) {
-        // Load return addr
-        self.emit(Instruction::LoadStackOffset(
-            C,
-            BP,
-            Nibble::new_checked(1).unwrap(),
-        ));
-        // Reload previous stack pointer ( BP - 2 )
-        self.emit(Instruction::Add(BP, Zero, SP));
-        self.emit(Instruction::AddImmSigned(
-            SP,
-            Literal7Bit::from_signed(-2).unwrap(),
-        ));
-        // Load previous base pointer
-        self.emit(Instruction::LoadStackOffset(
-            BP,
-            BP,
-            Nibble::new_checked(2).unwrap(),
-        ));
-        // Jump to return addr
-        self.emit(Instruction::AddImm(C, Literal7Bit::new_checked(6).unwrap()));
-        self.emit(Instruction::Add(C, Zero, PC));
+        // a `return` already left the function
+        if self.opt_level >= OptLevel::Peephole
+            && matches!(self.instructions.last(), Some(Instruction::Ret))
+            && !self.is_label_target()
+        {
+            return;
+        }
+        // Restores SP, BP and jumps to the return addr
+        self.emit(Instruction::Ret);
     }
 
     fn imm_future(&mut self, r: Register, label: String) {
+        self.relocations.push(Relocation {
+            section: SectionKind::Code,
+            offset: self.current_offset - self.inital_offset,
+            kind: RelocationKind::Imm12,
+            symbol: label.clone(),
+        });
         match self.labels.get(&label) {
-            Some(offset) => {
-                let imm = Literal12Bit::new_checked(*offset as u16).unwrap();
-                self.emit(Instruction::Imm(r, imm));
+            Some(&offset) => {
+                let ins = self.linked_instruction(&FutureType::Imm, r, 0, offset);
+                self.emit_linked(ins);
             }
             None => {
                 self.unlinked_references
@@ -151,9 +337,9 @@ impl<'a> CodeGenerator<'a> {
 
     fn addimm_future(&mut self, r: Register, label: String) {
         match self.labels.get(&label) {
-            Some(offset) => {
-                let imm = Literal7Bit::new_checked(*offset as u8).unwrap();
-                self.emit(Instruction::AddImm(r, imm));
+            Some(&offset) => {
+                let ins = self.linked_instruction(&FutureType::AddImm, r, 0, offset);
+                self.emit_linked(ins);
             }
             None => {
                 self.unlinked_references.push((
@@ -168,6 +354,151 @@ impl<'a> CodeGenerator<'a> {
         }
     }
 
+    fn branch_future(&mut self, ft: FutureType, label: String) {
+        let loc = self.instructions.len();
+        let far = self.far_labels.contains(&label)
+            || matches!(self.labels.get(&label), Some(&offset) if self.branch_offset(loc, offset).is_none());
+        if far {
+            self.long_branch_future(ft, label);
+            return;
+        }
+
+        match self.labels.get(&label) {
+            Some(&offset) => {
+                let ins = self.linked_instruction(&ft, PC, loc, offset);
+                self.emit_linked(ins);
+            }
+            None => {
+                self.unlinked_references.push((loc, ft, PC, label));
+
+                self.emit(Instruction::Invalid); // Placeholder for labeled branch
+            }
+        }
+    }
+
+    /// Branches to `label` through C, skipping the jump with an inverted branch if conditional.
+    fn long_branch_future(&mut self, ft: FutureType, label: String) {
+        if let FutureType::BranchIf = ft {
+            self.emit(Instruction::BranchIfNot(
+                Literal10Bit::from_signed(5).unwrap(),
+            ));
+        }
+        let loc = self.instructions.len();
+        match self.labels.get(&label) {
+            Some(&offset) => {
+                for ins in self.long_branch(loc, offset) {
+                    self.emit(ins);
+                }
+            }
+            None => {
+                self.unlinked_references
+                    .push((loc, FutureType::LongBranch, C, label));
+
+                // Placeholders for the jump
+                for _ in 0..4 {
+                    self.emit(Instruction::Invalid);
+                }
+            }
+        }
+    }
+
+    /// The word offset of a branch at index `loc` to `offset`, if a branch can reach it.
+    fn branch_offset(&self, loc: usize, offset: u32) -> Option<Literal10Bit> {
+        let address = self.inital_offset + (loc as u32) * 2;
+        Literal10Bit::from_word_offset((offset as i64 - address as i64) / 2).ok()
+    }
+
+    /// Jumps from the four instructions at index `loc` to `offset`, adding its distance from the
+    /// last of them to PC.
+    fn long_branch(&self, loc: usize, offset: u32) -> [Instruction; 4] {
+        let base = self.inital_offset + (loc as u32 + 3) * 2;
+        let (distance, jump) = match offset > base {
+            true => (offset - base, Instruction::Add(PC, C, PC)),
+            false => (base - offset, Instruction::Sub(PC, C, PC)),
+        };
+        [
+            Instruction::Imm(
+                C,
+                Literal12Bit::new_checked((distance >> 4) as u16).unwrap(),
+            ),
+            Instruction::ShiftLeft(C, C, Nibble::new_checked(4).unwrap()),
+            Instruction::AddImm(C, Literal7Bit::new_checked((distance & 0xf) as u8).unwrap()),
+            jump,
+        ]
+    }
+
+    /// Builds the instruction at index `loc` that refers to a label at `offset`, `Ok(None)` if
+    /// it's a branch that can't reach it and an error if the label doesn't fit an immediate.
+    fn linked_instruction(
+        &self,
+        ft: &FutureType,
+        r: Register,
+        loc: usize,
+        offset: u32,
+    ) -> Result<Option<Instruction>, String> {
+        let too_large = |e| format!("label at 0x{:X} doesn't fit an immediate: {}", offset, e);
+        let value = u16::try_from(offset).map_err(|e| too_large(e.to_string()))?;
+        let ins = match ft {
+            FutureType::Imm => {
+                Instruction::Imm(r, Literal12Bit::new_checked(value).map_err(too_large)?)
+            }
+            FutureType::AddImm => {
+                let value = u8::try_from(value).map_err(|e| too_large(e.to_string()))?;
+                Instruction::AddImm(r, Literal7Bit::new_checked(value).map_err(too_large)?)
+            }
+            FutureType::Branch | FutureType::BranchIf => {
+                let lit = match self.branch_offset(loc, offset) {
+                    Some(lit) => lit,
+                    None => return Ok(None),
+                };
+                match ft {
+                    FutureType::BranchIf => Instruction::BranchIf(lit),
+                    _ => Instruction::Branch(lit),
+                }
+            }
+            FutureType::LongBranch => unreachable!("long branches are linked by `long_branch`"),
+        };
+        Ok(Some(ins))
+    }
+
+    /// Emits a linked instruction, or a placeholder after recording why it couldn't be built.
+    fn emit_linked(&mut self, ins: Result<Option<Instruction>, String>) {
+        match ins {
+            Ok(Some(ins)) => self.emit(ins),
+            Ok(None) => unreachable!("branches out of range are generated as long branches"),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self.emit(Instruction::Invalid);
+            }
+        }
+    }
+
+    /// Records the frame slot of a local or argument of the function being generated.
+    fn add_local(&mut self, name: &str, offset: i16) {
+        if let Some(function) = self.function.as_mut() {
+            let slot = LocalSlot {
+                name: name.to_string(),
+                offset,
+            };
+            if !function.locals.contains(&slot) {
+                function.locals.push(slot);
+            }
+        }
+    }
+
+    /// Attributes the following instructions to `span`, returning the span to restore after.
+    /// Runs `f` with `span` as the source of the instructions it emits, then restores the outer
+    /// span. Without a span the outer one is kept.
+    fn with_span<R>(&mut self, span: impl Into<Option<Span>>, f: impl FnOnce(&mut Self) -> R) -> R {
+        let outer = self.current_span;
+        if let Some(span) = span.into() {
+            self.current_span = Some(span);
+        }
+        let res = f(self);
+        self.current_span = outer;
+        res
+    }
+
     fn define_label(&mut self, label: String) {
         self.define_label_offset(label, self.current_offset)
     }
@@ -175,23 +506,30 @@ impl<'a> CodeGenerator<'a> {
     fn define_label_offset(&mut self, label: String, offset: u32) {
         self.labels.insert(label.clone(), offset);
 
-        self.unlinked_references.retain(|(loc, ft, r, l)| {
-            if *l == label {
-                match ft {
-                    FutureType::Imm => {
-                        let imm = Literal12Bit::new_checked(offset as u16).unwrap();
-                        self.instructions[*loc] = Instruction::Imm(*r, imm);
-                    }
-                    FutureType::AddImm => {
-                        let imm = Literal7Bit::new_checked(offset as u8).unwrap();
-                        self.instructions[*loc] = Instruction::AddImm(*r, imm);
-                    }
+        let (linked, unlinked) = mem::take(&mut self.unlinked_references)
+            .into_iter()
+            .partition(|(_, _, _, l)| *l == label);
+        self.unlinked_references = unlinked;
+
+        for (loc, ft, r, _) in linked {
+            if let FutureType::LongBranch = ft {
+                for (i, ins) in self.long_branch(loc, offset).into_iter().enumerate() {
+                    self.instructions[loc + i] = ins;
                 }
-                false
-            } else {
-                true
+                continue;
             }
-        });
+            match self.linked_instruction(&ft, r, loc, offset) {
+                Ok(Some(ins)) => self.instructions[loc] = ins,
+                // generated again with a long branch
+                Ok(None) => {
+                    self.far_labels.insert(label.clone());
+                    self.relax = true;
+                }
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                }
+            }
+        }
     }
 
     fn enter_scope(&mut self) {
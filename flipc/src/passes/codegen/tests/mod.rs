@@ -1,5 +1,8 @@
-use flipvm::op::{Instruction, Literal12Bit, Literal7Bit, Nibble, StackOp, TestOp};
+use flipvm::exe::Executable;
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit, Literal7Bit, Nibble, StackOp, TestOp};
+use flipvm::pp::{macros, PreProcessor};
 use flipvm::Register::*;
+use flipvm::{LinearMemory, Machine};
 
 use crate::diagnostics::DiagnosticBag;
 use crate::lexer::Lexer;
@@ -8,7 +11,7 @@ use crate::passes::nameresolver::NameResolver;
 use crate::passes::symbol_table::SymbolTableBuilder;
 use crate::passes::Pass;
 
-use super::CodeGenerator;
+use super::{to_asm, CodeGenerator, CodegenOptions};
 
 mod expression;
 
@@ -31,18 +34,17 @@ main() {
 
     let root = parser.parse();
 
-    let (mut st, mut ft) = SymbolTableBuilder::run((&root, diagnostics.clone()));
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
     NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
 
-    let actual = CodeGenerator::run((&root, &st, 0x0));
+    let (actual, debug_info, _, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
 
     let expected = vec![
         Instruction::Imm(SP, Literal12Bit { value: 1023 }),
         Instruction::ShiftLeft(SP, SP, Nibble { value: 4 }),
-        Instruction::Stack(BP, SP, StackOp::Push),
-        Instruction::Stack(PC, SP, StackOp::Push),
-        Instruction::Add(SP, Zero, BP),
-        Instruction::Imm(PC, Literal12Bit { value: 16 }),
+        Instruction::Imm(C, Literal12Bit { value: 12 }),
+        Instruction::Call(C),
         Instruction::Imm(C, Literal12Bit { value: 240 }),
         Instruction::System(C, Zero, Nibble { value: 0 }),
         Instruction::AddImm(SP, Literal7Bit { value: 2 }),
@@ -66,23 +68,225 @@ main() {
         Instruction::Stack(C, SP, StackOp::Push),
         Instruction::Stack(C, SP, StackOp::Pop),
         Instruction::Test(C, Zero, TestOp::BothZero),
-        Instruction::AddIf(PC, PC, Nibble { value: 2 }),
-        Instruction::Imm(PC, Literal12Bit { value: 64 }),
-        Instruction::Imm(PC, Literal12Bit { value: 78 }),
+        Instruction::BranchIf(Literal10Bit { value: 7 }),
         Instruction::Imm(C, Literal12Bit { value: 3 }),
         Instruction::Stack(C, SP, StackOp::Push),
         Instruction::Stack(C, SP, StackOp::Pop),
         Instruction::Add(BP, Zero, B),
         Instruction::AddImm(B, Literal7Bit { value: 0 }),
         Instruction::StoreWord(B, Zero, C),
-        Instruction::Imm(PC, Literal12Bit { value: 78 }),
-        Instruction::LoadStackOffset(C, BP, Nibble { value: 1 }),
-        Instruction::Add(BP, Zero, SP),
-        Instruction::AddImmSigned(SP, Literal7Bit { value: 126 }),
-        Instruction::LoadStackOffset(BP, BP, Nibble { value: 2 }),
-        Instruction::AddImm(C, Literal7Bit { value: 6 }),
-        Instruction::Add(C, Zero, PC),
+        Instruction::Ret,
     ];
 
     assert_eq!(actual, expected);
+    let symbols = debug_info.symbols();
+    assert_eq!(symbols.lookup("__init"), Some(0));
+    assert_eq!(symbols.lookup("main"), Some(12));
+}
+
+#[test]
+fn line_table() {
+    let input = "main() {\n    let x = 1;\n}";
+
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
+
+    let (_, _, line_table, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
+
+    // startup code has no source
+    assert_eq!(line_table.span_at(0), None);
+    let function = line_table.span_at(12).unwrap();
+    assert_eq!(function.start, 0);
+    // the definition's literal, then its store
+    let literal = line_table.span_at(14).unwrap();
+    assert_eq!(&input[literal.start..=literal.end], "1");
+    let definition = line_table.span_at(24).unwrap();
+    assert_eq!(&input[definition.start..=definition.end], "x = 1;");
+}
+
+#[test]
+fn functions() {
+    let input =
+        "int main() {\n    let x = 1;\n    return f(x);\n}\n\nint f(a, b) {\n    return a;\n}";
+
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
+
+    let (instructions, mut debug_info, line_table, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
+    let names: Vec<_> = debug_info
+        .functions()
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["__init", "main", "f"]);
+
+    let main = debug_info.function("main").unwrap();
+    assert_eq!(main.start, 12);
+    assert_eq!(main.end, debug_info.function("f").unwrap().start);
+    assert_eq!(main.locals[0].name, "x");
+    assert_eq!(main.locals[0].offset, 0);
+
+    let f = debug_info.function("f").unwrap();
+    assert_eq!(f.end as usize, instructions.len() * 2);
+    let offsets: Vec<_> = f
+        .locals
+        .iter()
+        .map(|l| (l.name.as_str(), l.offset))
+        .collect();
+    assert_eq!(offsets, vec![("a", -6), ("b", -8)]);
+
+    let return_addr = f.start + 2;
+    line_table.resolve_into(input, &mut debug_info);
+    let location = debug_info.location(return_addr).unwrap();
+    assert_eq!((location.line, location.column), (7, 12));
+}
+
+#[test]
+fn asm() {
+    let input = "int main() {\n    let x = 1;\n    if x == 2 {\n        return f(x);\n    };\n    return 0;\n}\n\nint f(a) {\n    return a;\n}";
+
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
+
+    let options = CodegenOptions {
+        offset: 0x100,
+        ..Default::default()
+    };
+    let (instructions, debug_info, _, relocations) =
+        CodeGenerator::run((&root, &st, &options)).unwrap();
+    let text = to_asm(&instructions, &debug_info, &relocations, 0x100);
+    assert!(text.starts_with(".offsetPC 128\n:__init\n"));
+    assert!(text.contains("\n\n:main\n"));
+    assert!(text.contains("Imm C !f\nCall C\n"));
+    assert!(text.contains("BranchIf @lbl_"));
+
+    // re-assembles to the same program
+    let mut pp = PreProcessor::new();
+    macros::setup_std_macros(&mut pp);
+    let lines = pp.resolve(&text).map_err(String::from).unwrap();
+    let assembled: Vec<Instruction> = lines
+        .iter()
+        .map(|line| {
+            let line = line.resolve(&pp).map_err(String::from).unwrap();
+            match line.parse() {
+                Ok(ins) => ins,
+                Err(_) => panic!("failed to parse: {}", line),
+            }
+        })
+        .collect();
+    assert_eq!(assembled, instructions);
+}
+
+#[test]
+fn address_out_of_range() {
+    let input = "int main() {\n    return f(1);\n}\n\nint f(a) {\n    return a;\n}";
+
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
+
+    // function addresses don't fit the 12 bit immediates loading them
+    let options = CodegenOptions {
+        offset: 0x2000,
+        ..Default::default()
+    };
+    let err = CodeGenerator::run((&root, &st, &options)).unwrap_err();
+    assert!(err.contains("0x20"), "{}", err);
+}
+
+#[test]
+fn many_arguments() {
+    let params: Vec<_> = (0..40).map(|i| format!("a{}", i)).collect();
+    let args = vec!["1"; 40].join(", ");
+    let input = format!(
+        "int main() {{\n    return f({});\n}}\n\nint f({}) {{\n    return a0;\n}}",
+        args,
+        params.join(", ")
+    );
+
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input);
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
+
+    let (instructions, _, _, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
+    // the arguments are dropped in steps, past the startup code's call
+    let call = instructions
+        .iter()
+        .rposition(|i| *i == Instruction::Call(C))
+        .unwrap();
+    let dropped: i32 = instructions[call + 1..]
+        .iter()
+        .map_while(|i| match i {
+            Instruction::AddImmSigned(SP, lit) => Some(lit.as_signed() as i32),
+            _ => None,
+        })
+        .sum();
+    assert_eq!(dropped, -80);
+}
+
+fn compile_and_run(input: &str) -> (Vec<Instruction>, Option<u16>) {
+    let diagnostics = DiagnosticBag::new();
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(&mut lexer, diagnostics.clone());
+    let root = parser.parse();
+    let (mut st, mut ft) = SymbolTableBuilder::run((&root, "main", diagnostics.clone()));
+    NameResolver::run((&root, &mut st, &mut ft, diagnostics.clone()));
+    let (instructions, _, _, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
+
+    let bytes = instructions
+        .iter()
+        .flat_map(|i| i.encode_u16().to_le_bytes())
+        .collect();
+    let mut m = Machine::with_std_syscalls();
+    m.map(0x1000, 0x7000, Box::new(LinearMemory::new(0x7000)))
+        .unwrap();
+    Executable::from_raw(bytes, 0x0).load(&mut m).unwrap();
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    (instructions, m.exit_code())
+}
+
+#[test]
+fn far_branches() {
+    // bodies longer than a branch can reach
+    let body = "        y = y + 1;\n".repeat(60);
+    let input = format!(
+        "int main() {{\n    let x = 4;\n    let y = 0;\n    if x == 4 {{\n{0}    }};\n    while x < 7 {{\n        x = x + 1;\n{0}    }};\n    return y;\n}}",
+        body
+    );
+    let (instructions, exit_code) = compile_and_run(&input);
+    assert_eq!(exit_code, Some(240));
+    // each loop is left with an inverted branch over a long jump, and the while loops back with
+    // one
+    let long_jumps = instructions
+        .iter()
+        .filter(|i| matches!(i, Instruction::Add(PC, C, PC) | Instruction::Sub(PC, C, PC)))
+        .count();
+    assert_eq!(long_jumps, 3);
+    assert!(instructions.contains(&Instruction::BranchIfNot(
+        Literal10Bit::from_signed(5).unwrap()
+    )));
 }
@@ -4,6 +4,6 @@ mod pass;
 pub mod symbol_table;
 pub mod typechecker;
 
-pub use codegen::CodeGenerator;
+pub use codegen::{to_asm, to_object, CodeGenerator, CodegenOptions, LineTable, OptLevel};
 pub use pass::Pass;
 pub use symbol_table::SymbolTable;
@@ -18,7 +18,7 @@ use super::symbol_table::{FunctionTable, SymbolTable};
 use super::Pass;
 use crate::ast::visitor::{Visitor, Walkable};
 use crate::ast::{Assignment, Call, Function, If, Program, Variable, While};
-use crate::diagnostics::DiagnosticsCell;
+use crate::diagnostics::{closest, DiagnosticsCell};
 
 pub trait ResolveVisitor {
     fn define(&mut self, resolver: &mut NameResolver);
@@ -75,7 +75,11 @@ impl<'a> NameResolver<'a> {
 
     fn exit_scope(&mut self) {
         self.check_usage();
+        self.leave_scope();
+    }
 
+    /// Exits the current scope without checking its symbols are used.
+    fn leave_scope(&mut self) {
         self.current_scope = self
             .symbol_table
             .lookup_scope(self.current_scope)
@@ -110,7 +114,12 @@ impl Visitor for NameResolver<'_> {
     fn visit_function(&mut self, func: &Function) {
         self.enter_scope();
         func.body.walk(self);
-        self.exit_scope();
+        // the parameters of an extern function only name its arguments
+        if func.external {
+            self.leave_scope();
+        } else {
+            self.exit_scope();
+        }
     }
 
     fn visit_if(&mut self, if_expr: &If) {
@@ -133,9 +142,14 @@ impl Visitor for NameResolver<'_> {
             .lookup_symbol(&def.pattern, self.current_scope)
             .is_none()
         {
-            self.diagnostics
-                .borrow_mut()
-                .undeclared_assignment(&def.pattern.name, def.pattern.span);
+            let similar = self
+                .symbol_table
+                .similar_symbol(&def.pattern, self.current_scope);
+            self.diagnostics.borrow_mut().undeclared_assignment(
+                &def.pattern.name,
+                def.pattern.span,
+                similar,
+            );
         }
 
         def.value.walk(self);
@@ -147,9 +161,10 @@ impl Visitor for NameResolver<'_> {
             .lookup_symbol(var, self.current_scope)
             .is_none()
         {
+            let similar = self.symbol_table.similar_symbol(var, self.current_scope);
             self.diagnostics
                 .borrow_mut()
-                .undefined_reference(&var.name, var.span);
+                .undefined_reference(&var.name, var.span, similar);
         } else {
             self.symbol_table
                 .update_symbol(var, self.current_scope, |def| def.uses += 1);
@@ -158,9 +173,14 @@ impl Visitor for NameResolver<'_> {
 
     fn visit_call(&mut self, call: &Call) {
         if self.functions.get(&call.pattern).is_none() {
-            self.diagnostics
-                .borrow_mut()
-                .undefined_reference(&call.pattern.name, call.pattern.span);
+            let mut names: Vec<&str> = self.functions.keys().map(|p| p.name.as_str()).collect();
+            names.sort_unstable();
+            let similar = closest(&call.pattern.name, names);
+            self.diagnostics.borrow_mut().undefined_reference(
+                &call.pattern.name,
+                call.pattern.span,
+                similar,
+            );
         } else {
             let func = self.functions.get_mut(&call.pattern).expect("unreachable");
             func.uses += 1;
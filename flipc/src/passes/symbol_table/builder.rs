@@ -58,10 +58,12 @@ impl SymbolTableBuilder<'_> {
         ty: Type,
         def_type: DefinitionType,
     ) {
-        if self.symbol_table.is_shadowing(pattern, self.current_scope) {
-            self.diagnostics
-                .borrow_mut()
-                .variable_already_declared(&pattern.name, pattern.span);
+        if let Some(previous) = self.symbol_table.lookup_symbol(pattern, self.current_scope) {
+            self.diagnostics.borrow_mut().variable_already_declared(
+                &pattern.name,
+                pattern.span,
+                previous.span,
+            );
         } else {
             let symbol_idx = match def_type {
                 DefinitionType::Local => self.symbol_table.scopes[self.current_scope].symbols.len(),
@@ -88,24 +90,24 @@ impl SymbolTableBuilder<'_> {
 }
 
 impl<'a> Pass for SymbolTableBuilder<'a> {
-    type Input = (&'a Program, DiagnosticsCell);
+    type Input = (&'a Program, &'a str, DiagnosticsCell);
 
     type Output = (SymbolTable, FunctionTable);
 
-    fn run((ast, diagnostics): Self::Input) -> Self::Output {
+    fn run((ast, entry, diagnostics): Self::Input) -> Self::Output {
         let mut builder = SymbolTableBuilder::new(diagnostics);
         builder.visit_program(ast);
 
-        let main_pat = Pattern {
-            name: "main".to_owned(),
+        let entry_pat = Pattern {
+            name: entry.to_owned(),
             span: Default::default(),
         };
-        match builder.functions.get_mut(&main_pat) {
+        match builder.functions.get_mut(&entry_pat) {
             Some(main) => {
                 main.uses += 1;
             }
             None => {
-                builder.diagnostics.borrow_mut().main_not_found();
+                builder.diagnostics.borrow_mut().entry_not_found(entry);
             }
         }
 
@@ -115,10 +117,12 @@ impl<'a> Pass for SymbolTableBuilder<'a> {
 
 impl Visitor for SymbolTableBuilder<'_> {
     fn visit_function(&mut self, func: &Function) {
-        if self.functions.contains_key(&func.pattern) {
-            self.diagnostics
-                .borrow_mut()
-                .function_already_declared(&func.pattern.name, func.pattern.span);
+        if let Some((previous, _)) = self.functions.get_key_value(&func.pattern) {
+            self.diagnostics.borrow_mut().function_already_declared(
+                &func.pattern.name,
+                func.pattern.span,
+                previous.span,
+            );
         } else {
             let local_idx = self.functions.len();
             self.functions.insert(
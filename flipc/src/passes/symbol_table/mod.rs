@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
 use crate::ast::{Pattern, Type};
+use crate::diagnostics::closest;
+use crate::json;
 use crate::span::Span;
 
 mod builder;
@@ -15,7 +17,7 @@ pub struct SymbolInfo {
     pub def_type: DefinitionType,
     pub uses: usize,
     pub symbol_idx: usize,
-    span: Span,
+    pub span: Span,
 }
 
 #[repr(u8)]
@@ -80,6 +82,26 @@ impl SymbolTable {
         }
     }
 
+    /// The name closest to `ident` among the symbols visible from `scope_idx` and declared before
+    /// it, for "did you mean" suggestions.
+    pub fn similar_symbol(&self, ident: &Pattern, scope_idx: usize) -> Option<&str> {
+        let mut candidates = Vec::new();
+        let mut scope = Some(scope_idx);
+        while let Some(idx) = scope {
+            candidates.extend(
+                self.scopes[idx]
+                    .symbols
+                    .iter()
+                    .filter(|(_, info)| info.span.start < ident.span.start)
+                    .map(|(pat, _)| pat.name.as_str()),
+            );
+            scope = self.scopes[idx].parent;
+        }
+        // symbols are hashed, keep suggestions stable
+        candidates.sort_unstable();
+        closest(&ident.name, candidates)
+    }
+
     pub fn update_symbol<F>(&mut self, ident: &Pattern, scope_idx: usize, f: F)
     where
         F: FnOnce(&mut SymbolInfo),
@@ -117,4 +139,46 @@ impl SymbolTable {
     pub fn insert_symbol(&mut self, ident: Pattern, scope_idx: usize, variable: SymbolInfo) {
         self.scopes[scope_idx].symbols.insert(ident, variable);
     }
+
+    /// The scopes and their symbols as JSON, symbols in declaration order.
+    pub fn to_json(&self) -> String {
+        let scopes: Vec<String> = self
+            .scopes
+            .iter()
+            .enumerate()
+            .map(|(idx, scope)| {
+                let mut symbols: Vec<_> = scope.symbols.iter().collect();
+                symbols.sort_by_key(|(_, info)| info.span.start);
+                let symbols: Vec<String> = symbols
+                    .iter()
+                    .map(|(pat, info)| {
+                        let kind = match info.def_type {
+                            DefinitionType::Local => "local",
+                            DefinitionType::Argument => "argument",
+                        };
+                        format!(
+                            "{{\"name\": {}, \"kind\": \"{}\", \"type\": {}, \"index\": {}, \"uses\": {}, \"span\": {}}}",
+                            json::string(&pat.name),
+                            kind,
+                            json::string(&info.ty.to_string()),
+                            info.symbol_idx,
+                            info.uses,
+                            json::span(&info.span)
+                        )
+                    })
+                    .collect();
+                let parent = match scope.parent {
+                    Some(parent) => parent.to_string(),
+                    None => "null".to_string(),
+                };
+                format!(
+                    "{{\"scope\": {}, \"parent\": {}, \"symbols\": [{}]}}",
+                    idx,
+                    parent,
+                    symbols.join(", ")
+                )
+            })
+            .collect();
+        format!("{{\"scopes\": [{}]}}", scopes.join(", "))
+    }
 }
@@ -94,9 +94,12 @@ impl Visitor for TypeChecker<'_> {
             let assigned_ty = def.value.deref().into();
 
             if symbol.ty != assigned_ty {
-                self.diagnostics
-                    .borrow_mut()
-                    .mismatched_type(&symbol.ty, &assigned_ty, def.span);
+                self.diagnostics.borrow_mut().mismatched_type(
+                    &symbol.ty,
+                    &assigned_ty,
+                    def.span,
+                    symbol.span,
+                );
             }
         }
     }
@@ -12,6 +12,10 @@ impl Source {
         Source { text }
     }
 
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
     pub fn line_index(&self, index: usize) -> usize {
         self.text[..index].chars().filter(|&c| c == '\n').count()
     }
@@ -0,0 +1,90 @@
+use flipc::coverage::{BranchCoverage, CoverageReport};
+use flipc::{frontend, CodeGenerator, CodegenOptions, Pass};
+use flipvm::{Addressable, LinearMemory, Machine, Register};
+
+use self::common::read_source_file;
+
+mod common;
+
+fn report(file: &str) -> CoverageReport {
+    let src = read_source_file(file);
+    let (root, st) = frontend::check(&src).unwrap();
+    let (instructions, _, line_table, _) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
+
+    let bytecode: Vec<u8> = instructions
+        .iter()
+        .flat_map(|i| i.encode_u16().to_le_bytes())
+        .collect();
+    let mut m = Machine::with_std_syscalls();
+    m.map(0x0, 0x8000, Box::new(LinearMemory::new(0x8000)))
+        .unwrap();
+    m.vm.memory.load_from_vec(&bytecode, 0).unwrap();
+    m.set_register(Register::SP, 0x1000);
+    m.enable_coverage();
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_eq!(m.exit_code(), Some(2));
+
+    CoverageReport::new(
+        file,
+        &src,
+        &instructions,
+        0x0,
+        &line_table,
+        m.coverage().unwrap(),
+    )
+}
+
+#[test]
+fn lines() {
+    let report = report("coverage.fl");
+    assert_eq!(report.line_count(2), Some(1));
+    assert_eq!(report.line_count(4), Some(0));
+    assert_eq!(report.line_count(8), None);
+    // fib(3) makes 5 calls
+    assert_eq!(report.line_count(10), Some(5));
+    assert_eq!(report.lines_hit(), (10, 11));
+}
+
+#[test]
+fn branches() {
+    let report = report("coverage.fl");
+    assert_eq!(
+        report.branches(),
+        &[
+            BranchCoverage {
+                line: 3,
+                when_true: 0,
+                when_false: 1
+            },
+            BranchCoverage {
+                line: 10,
+                when_true: 1,
+                when_false: 4
+            },
+            BranchCoverage {
+                line: 14,
+                when_true: 2,
+                when_false: 2
+            },
+        ]
+    );
+    assert_eq!(report.branches_hit(), (5, 6));
+}
+
+#[test]
+fn output() {
+    let report = report("coverage.fl");
+    let lcov = report.lcov();
+    assert!(lcov.starts_with("TN:\nSF:coverage.fl\n"));
+    assert!(lcov.contains("BRDA:3,0,0,0\nBRDA:3,0,1,1\n"));
+    assert!(lcov.contains("DA:4,0\n"));
+    assert!(lcov.ends_with("LF:11\nLH:10\nend_of_record\n"));
+
+    let summary = report.summary();
+    assert!(summary.starts_with("coverage.fl: lines 10/11 (90.9%), branches 5/6 (83.3%)\n"));
+    assert!(summary.contains("lines never executed: 4\n"));
+    assert!(summary.contains("line 3: condition never true\n"));
+}
@@ -0,0 +1,56 @@
+use flipc::frontend;
+
+use self::common::read_source_file;
+
+mod common;
+
+#[test]
+fn tokens() {
+    let tokens = frontend::tokens("int main() {\n    return 1;\n}");
+    let lines: Vec<_> = tokens.lines().collect();
+    assert_eq!(lines[0], "0..2 Ident(int)");
+    assert_eq!(lines[1], "4..7 Ident(main)");
+    assert_eq!(lines[5], "12..12 \\n");
+    assert_eq!(lines[6], "17..22 return");
+    assert_eq!(lines[7], "24..24 Integer(1)");
+    assert_eq!(lines.last(), Some(&"28..28 EoF"));
+}
+
+#[test]
+fn ast() {
+    let (root, _) = frontend::check(&read_source_file("fib.fl")).unwrap();
+    let plain = format!("{:#}", root);
+    assert!(!plain.contains('\x1b'));
+    assert!(plain.contains("Function: main"));
+    assert!(format!("{}", root).contains('\x1b'));
+}
+
+#[test]
+fn ast_json() {
+    let (root, _) = frontend::check("int main() {\n    let x = \"ab\";\n    return 0;\n}").unwrap();
+    let json = root.to_json();
+    assert!(json.starts_with(
+        "{\"functions\": [{\"kind\": \"Function\", \"name\": \"main\", \"return_type\": \"int\", \"parameters\": [], \"external\": false"
+    ));
+    assert!(json.contains(
+        "{\"kind\": \"Definition\", \"span\": [21, 29], \"name\": \"x\", \"value\": {\"kind\": \"Literal\", \"span\": [25, 28], \"type\": \"string\", \"value\": \"ab\"}}"
+    ));
+    assert!(json
+        .contains("{\"kind\": \"Return\", \"span\": [42, 42], \"value\": {\"kind\": \"Literal\""));
+}
+
+#[test]
+fn symbols() {
+    let (_, st) = frontend::check(
+        "int main() {\n    let x = 1;\n    return f(x);\n}\n\nint f(a) {\n    return a;\n}",
+    )
+    .unwrap();
+    assert_eq!(
+        st.to_json(),
+        "{\"scopes\": [\
+         {\"scope\": 0, \"parent\": null, \"symbols\": []}, \
+         {\"scope\": 1, \"parent\": 0, \"symbols\": [{\"name\": \"x\", \"kind\": \"local\", \"type\": \"int\", \"index\": 0, \"uses\": 1, \"span\": [21, 26]}]}, \
+         {\"scope\": 2, \"parent\": 0, \"symbols\": [{\"name\": \"a\", \"kind\": \"argument\", \"type\": \"unresolved\", \"index\": 0, \"uses\": 1, \"span\": [54, 54]}]}\
+         ]}"
+    );
+}
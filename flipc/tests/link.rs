@@ -0,0 +1,57 @@
+use flipc::{frontend, to_object, CodeGenerator, CodegenOptions, Pass};
+use flipvm::exe::{self, SectionKind};
+use flipvm::obj::{self, LinkError, Object};
+use flipvm::op::Instruction::*;
+use flipvm::op::Nibble;
+use flipvm::{LinearMemory, Machine, Register};
+
+use self::common::read_source_file;
+
+mod common;
+
+fn compile(file: &str) -> Object {
+    let src = read_source_file(file);
+    let (root, st) = frontend::check(&src).unwrap();
+    let (instructions, debug_info, _, relocations) =
+        CodeGenerator::run((&root, &st, &CodegenOptions::default())).unwrap();
+    to_object(&instructions, &debug_info, relocations)
+}
+
+// int add(a, b), written by hand
+fn add() -> Object {
+    let mut obj = Object::from_instructions(&[
+        LoadStackOffset(Register::A, Register::BP, Nibble::new_checked(3).unwrap()),
+        LoadStackOffset(Register::B, Register::BP, Nibble::new_checked(4).unwrap()),
+        Add(Register::A, Register::B, Register::A),
+        Ret,
+    ]);
+    obj.define("add", SectionKind::Code, 0, true);
+    obj
+}
+
+#[test]
+fn object() {
+    let obj = compile("extern.fl");
+    assert_eq!(obj.undefined(), vec!["add"]);
+    assert!(obj.symbol("main").unwrap().global);
+    assert!(!obj.symbol("__init").unwrap().global);
+    assert!(obj.symbol("add").is_none());
+}
+
+#[test]
+fn call_assembly() {
+    assert_eq!(
+        obj::link(&[compile("extern.fl")], 0x0, None),
+        Err(LinkError::UndefinedSymbol("add".to_string()))
+    );
+
+    let exe = obj::link(&[compile("extern.fl"), add()], 0x100, None).unwrap();
+    let mut m = Machine::with_std_syscalls();
+    m.map(0x1000, 0x7000, Box::new(LinearMemory::new(0x7000)))
+        .unwrap();
+    exe::load(&mut m, exe.to_bytes().unwrap()).unwrap();
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_eq!(m.exit_code(), Some(42));
+}
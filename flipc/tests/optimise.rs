@@ -0,0 +1,79 @@
+use flipc::{frontend, CodeGenerator, CodegenOptions, OptLevel, Pass};
+use flipvm::exe::Executable;
+use flipvm::op::Instruction;
+use flipvm::{LinearMemory, Machine};
+
+use self::common::read_source_file;
+
+mod common;
+
+fn compile(src: &str, options: &CodegenOptions) -> Vec<Instruction> {
+    let frontend_options = frontend::Options {
+        entry: options.entry.clone(),
+        ..Default::default()
+    };
+    let (root, st) = frontend::check_with(src, &frontend_options).unwrap();
+    let (instructions, _, _, _) = CodeGenerator::run((&root, &st, options)).unwrap();
+    instructions
+}
+
+fn run(instructions: &[Instruction], load_offset: u32) -> Option<u16> {
+    let bytes = instructions
+        .iter()
+        .flat_map(|i| i.encode_u16().to_le_bytes())
+        .collect();
+    let mut m = Machine::with_std_syscalls();
+    m.map(0x1000, 0x7000, Box::new(LinearMemory::new(0x7000)))
+        .unwrap();
+    Executable::from_raw(bytes, load_offset)
+        .load(&mut m)
+        .unwrap();
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    m.exit_code()
+}
+
+#[test]
+fn levels() {
+    let src = read_source_file("optimise.fl");
+    let mut sizes = Vec::new();
+    for opt_level in [OptLevel::None, OptLevel::Peephole, OptLevel::Full] {
+        let options = CodegenOptions {
+            opt_level,
+            ..Default::default()
+        };
+        let instructions = compile(&src, &options);
+        assert_eq!(run(&instructions, 0x0), Some(20), "{:?}", opt_level);
+        sizes.push(instructions.len());
+    }
+    assert!(sizes[0] > sizes[1] && sizes[1] > sizes[2], "{:?}", sizes);
+}
+
+#[test]
+fn folding_matches_the_machine() {
+    // unsigned, wrapping word arithmetic: 0 - 1 is 0xffff
+    let src = "int main() {\n    return (0 - 1 < 1) + (65535 + 2);\n}";
+    for opt_level in [OptLevel::None, OptLevel::Full] {
+        let options = CodegenOptions {
+            opt_level,
+            ..Default::default()
+        };
+        let instructions = compile(src, &options);
+        assert_eq!(run(&instructions, 0x0), Some(1), "{:?}", opt_level);
+    }
+}
+
+#[test]
+fn load_offset_and_entry() {
+    let src = "int start() {\n    return 3;\n}";
+    assert!(frontend::check(src).is_err());
+
+    let options = CodegenOptions {
+        offset: 0x200,
+        entry: "start".to_string(),
+        ..Default::default()
+    };
+    let instructions = compile(src, &options);
+    assert_eq!(run(&instructions, 0x200), Some(3));
+}
@@ -0,0 +1,48 @@
+use flipc::frontend::{self, Code, Level, Levels, Options};
+
+const SOURCE: &str = "int main() {\n    let x = 1;\n    return 0;\n}";
+
+fn check(src: &str, warnings: Levels) -> bool {
+    let options = Options {
+        warnings,
+        ..Default::default()
+    };
+    frontend::check_with(src, &options).is_ok()
+}
+
+#[test]
+fn levels() {
+    assert!(check(SOURCE, Levels::default()));
+    assert!(!check(SOURCE, Levels::new(Level::Deny)));
+
+    let mut levels = Levels::new(Level::Deny);
+    levels.set(Code::UnusedVariable, Level::Allow).unwrap();
+    assert!(check(SOURCE, levels));
+
+    let mut levels = Levels::default();
+    levels.set(Code::UnusedVariable, Level::Deny).unwrap();
+    assert!(!check(SOURCE, levels));
+}
+
+#[test]
+fn source_directives() {
+    let allowed = SOURCE.replace("    let x", "    # allow(unused_variable)\n    let x");
+    assert!(check(&allowed, Levels::new(Level::Deny)));
+    let file_wide = format!("#! allow(unused_variable)\n{}", SOURCE);
+    assert!(check(&file_wide, Levels::new(Level::Deny)));
+
+    let denied = SOURCE.replace("let x = 1;", "let x = 1; # deny(unused_variable)");
+    assert!(!check(&denied, Levels::default()));
+
+    // only the next line with code
+    let elsewhere = SOURCE.replace("return 0;", "# allow(unused_variable)\n    return 0;");
+    assert!(!check(&elsewhere, Levels::new(Level::Deny)));
+}
+
+#[test]
+fn codes() {
+    for code in Code::ALL {
+        assert_eq!(code.as_str().parse::<Code>(), Ok(code));
+    }
+    assert!("unused".parse::<Code>().is_err());
+}
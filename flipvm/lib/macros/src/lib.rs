@@ -99,8 +99,9 @@ fn impl_opcode_struct(ast: &syn::ItemEnum) -> Result<proc_macro2::TokenStream, S
         let name = &x.ident;
         let opcode_value = variant_opcode_value(x);
         if let syn::Fields::Unit = &x.fields {
+            let opcode_mask = ((opcode_value as u16) & 0x1f) << 4;
             field_u16_encodings.extend(quote! {
-                Self::#name => #opcode_value as u16,
+                Self::#name => #opcode_mask,
             });
             field_u16_decodings.extend(quote! {
                 #opcode_value => Ok(Self::#name),
@@ -3,6 +3,8 @@ use std::fmt;
 pub enum ArgsError {
     ExtraInput,
     UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue(String, String),
 }
 
 impl fmt::Display for ArgsError {
@@ -10,6 +12,8 @@ impl fmt::Display for ArgsError {
         match self {
             ArgsError::ExtraInput => write!(f, "extra input file(s)"),
             ArgsError::UnknownFlag(s) => write!(f, "unknown flag: {}", s),
+            ArgsError::MissingValue(s) => write!(f, "missing value for flag: {}", s),
+            ArgsError::InvalidValue(s, v) => write!(f, "invalid value for flag {}: {}", s, v),
         }
     }
 }
@@ -19,6 +23,8 @@ pub struct Args {
     pub input_file: Option<String>,
     pub preprocess_only: bool,
     pub map_binary_at: usize,
+    pub raw: bool,
+    pub object: bool,
     show_help: bool,
 }
 
@@ -37,7 +43,9 @@ impl Args {
 options:
     -h, --help\tShow this message.
     -p, --preprocess-only\tStop after running the preprocessor and print the instructions.
-    -x, --program-offset\tAddress to load program at initialzie PC register.
+    -x, --program-offset <addr>\tAddress to load program at initialzie PC register.
+    --raw\tWrite the bare instruction words instead of an executable.
+    -c, --object\tWrite a relocatable object for `link`. Only `.global` labels are exported, labels from other objects need `.extern`.
 
 ",
             self.bin_name
@@ -53,26 +61,52 @@ impl Default for Args {
             preprocess_only: false,
             show_help: false,
             map_binary_at: 0x0,
+            raw: false,
+            object: false,
         }
     }
 }
 
+fn parse_address(flag: &str, value: &str) -> Result<usize, ArgsError> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| ArgsError::InvalidValue(flag.to_string(), value.to_string()))
+}
+
 pub fn process(args: &[String]) -> Result<Args, ArgsError> {
     let mut out = Args {
         bin_name: args[0].to_string(),
         ..Default::default()
     };
 
-    for a in &args[1..] {
+    let mut iter = args[1..].iter();
+    while let Some(a) = iter.next() {
         if let Some(flag) = a.strip_prefix("--") {
             match flag {
                 "preprocess-only" => out.preprocess_only = true,
+                "program-offset" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.map_binary_at = parse_address(flag, value)?;
+                }
+                "raw" => out.raw = true,
+                "object" => out.object = true,
                 "help" => out.show_help = true,
                 x => return Err(ArgsError::UnknownFlag(x.to_string())),
             }
         } else if let Some(flag) = a.strip_prefix('-') {
             match flag {
                 "p" => out.preprocess_only = true,
+                "x" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.map_binary_at = parse_address(flag, value)?;
+                }
+                "c" => out.object = true,
                 "h" => out.show_help = true,
                 x => return Err(ArgsError::UnknownFlag(x.to_string())),
             }
@@ -4,6 +4,8 @@ use std::io::{stdout, BufReader, Read, Write};
 use std::path::Path;
 use std::str::FromStr;
 
+use flipvm::exe::{Executable, SectionKind};
+use flipvm::obj::{Object, RelocationKind};
 use flipvm::op::{Instruction, InstructionParseError};
 use flipvm::pp::{macros, PreProcessor};
 
@@ -24,6 +26,12 @@ fn main() -> Result<(), String> {
     let mut output: Vec<u8> = Vec::new();
     let mut processor = PreProcessor::new();
     macros::setup_std_macros(&mut processor);
+    if args.object {
+        processor.set_relocatable(true);
+    } else {
+        processor.set_origin(args.map_binary_at as u32);
+    }
+    let mut object = Object::new();
 
     let mut reader = BufReader::new(file);
     let mut content = String::new();
@@ -35,9 +43,12 @@ fn main() -> Result<(), String> {
         .resolve(&content)
         .map_err(|_| "failed to resolve")?;
     for line in processed {
-        let resolved = line
-            .resolve(&processor)
-            .map_err(|_| format!("failed to resolve line: {}", line.get_line_number()))?;
+        let (resolved, label_ref) = if args.object && !args.preprocess_only {
+            line.resolve_relocatable(&processor)
+        } else {
+            line.resolve(&processor).map(|resolved| (resolved, None))
+        }
+        .map_err(|e| format!("failed to resolve line {}: {}", line.get_line_number(), e))?;
         if args.preprocess_only {
             for &b in format!("{}: {}", line.get_line_number(), resolved).as_bytes() {
                 output.push(b);
@@ -54,6 +65,18 @@ fn main() -> Result<(), String> {
 
             match Instruction::from_str(&resolved) {
                 Ok(instruction) => {
+                    if let Some((label, relative)) = label_ref {
+                        let kind = RelocationKind::for_instruction(&instruction, relative)
+                            .ok_or_else(|| {
+                                format!(
+                                    "line {} ({}): cannot relocate {}",
+                                    line.get_line_number(),
+                                    resolved,
+                                    label
+                                )
+                            })?;
+                        object.relocate(SectionKind::Code, output.len() as u32, kind, &label);
+                    }
                     let raw_instruction: u16 = instruction.encode_u16();
                     // >> 8 only valid without mask for u16
                     output.push((raw_instruction & 0xff) as u8);
@@ -67,6 +90,28 @@ fn main() -> Result<(), String> {
         }
     }
 
+    if args.object && !args.preprocess_only {
+        for (addr, label) in processor.labels().iter() {
+            let global = processor.globals().iter().any(|g| g == label);
+            object.define(label, SectionKind::Code, addr as u32, global);
+        }
+        if let Some(label) = processor
+            .globals()
+            .iter()
+            .find(|g| processor.labels().lookup(g).is_none())
+        {
+            return Err(format!("global label is never defined: {}", label));
+        }
+        object.code = output;
+        output = object.to_bytes()?;
+    } else if !args.preprocess_only && !args.raw {
+        let mut exe = Executable::from_raw(output, args.map_binary_at as u32);
+        if !processor.labels().is_empty() {
+            exe.symbols = Some(processor.labels().clone());
+        }
+        output = exe.to_bytes()?;
+    }
+
     let mut stdout = stdout().lock();
     stdout.write_all(&output).map_err(|e| format!("{}", e))?;
     Ok(())
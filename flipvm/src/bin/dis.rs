@@ -3,9 +3,10 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
+use flipvm::exe::Executable;
 use flipvm::op::Instruction;
 
-// ./asm <input>
+// ./dis <input>
 fn main() -> Result<(), String> {
     let args: Vec<_> = env::args().collect();
     if args.len() != 2 {
@@ -18,10 +19,23 @@ fn main() -> Result<(), String> {
     reader
         .read_to_end(&mut program)
         .map_err(|e| format!("read: {}", e))?;
-    let (_, instructions, _) = unsafe { program.align_to::<u16>() };
-    for ins in instructions {
-        let value = Instruction::try_from(*ins)?;
-        println!("{}", value);
+
+    // raw images are disassembled as a single code section
+    let exe = if Executable::is_executable(&program) {
+        Executable::from_bytes(&program)?
+    } else {
+        Executable::from_raw(program, 0x0)
+    };
+    for section in exe.code() {
+        let start = exe.load_address + section.offset;
+        for (i, word) in section.data.chunks_exact(2).enumerate() {
+            let addr = start + (i as u32) * 2;
+            if let Some(label) = exe.symbols.as_ref().and_then(|s| s.label_at(addr as u16)) {
+                println!(":{}", label);
+            }
+            let value = Instruction::try_from(u16::from_le_bytes([word[0], word[1]]))?;
+            println!("{}", value);
+        }
     }
 
     Ok(())
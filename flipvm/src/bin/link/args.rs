@@ -0,0 +1,101 @@
+use std::fmt;
+
+pub enum ArgsError {
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::UnknownFlag(s) => write!(f, "unknown flag: {}", s),
+            ArgsError::MissingValue(s) => write!(f, "missing value for flag: {}", s),
+            ArgsError::InvalidValue(s, v) => write!(f, "invalid value for flag {}: {}", s, v),
+        }
+    }
+}
+
+pub struct Args {
+    pub bin_name: String,
+    pub input_files: Vec<String>,
+    pub output_file: Option<String>,
+    pub entry: Option<String>,
+    pub load_address: u32,
+    show_help: bool,
+}
+
+impl Args {
+    pub fn validate(&self) -> bool {
+        if self.show_help {
+            return false;
+        };
+        !self.input_files.is_empty()
+    }
+
+    pub fn usage(&self) -> String {
+        format!(
+            "usage: {} [OPTIONS] <object file>...
+
+options:
+    -h, --help\tShow this message.
+    -o, --output <file>\tWrite the executable to <file> instead of stdout.
+    -e, --entry <symbol>\tStart at the global <symbol>, default the first instruction of the first object.
+    -x, --load-address <addr>\tAddress to load the executable at.
+
+",
+            self.bin_name
+        )
+    }
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            bin_name: ".".to_string(),
+            input_files: Vec::new(),
+            output_file: None,
+            entry: None,
+            load_address: 0x0,
+            show_help: false,
+        }
+    }
+}
+
+fn parse_address(flag: &str, value: &str) -> Result<u32, ArgsError> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| ArgsError::InvalidValue(flag.to_string(), value.to_string()))
+}
+
+pub fn process(args: &[String]) -> Result<Args, ArgsError> {
+    let mut out = Args {
+        bin_name: args[0].to_string(),
+        ..Default::default()
+    };
+
+    let mut iter = args[1..].iter();
+    while let Some(a) = iter.next() {
+        let flag = match a.strip_prefix("--").or_else(|| a.strip_prefix('-')) {
+            Some(flag) => flag,
+            None => {
+                out.input_files.push(a.to_string());
+                continue;
+            }
+        };
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))
+        };
+        match flag {
+            "o" | "output" => out.output_file = Some(value()?.to_string()),
+            "e" | "entry" => out.entry = Some(value()?.to_string()),
+            "x" | "load-address" => out.load_address = parse_address(flag, value()?)?,
+            "h" | "help" => out.show_help = true,
+            x => return Err(ArgsError::UnknownFlag(x.to_string())),
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,35 @@
+use std::env;
+use std::fs;
+use std::io::{stdout, Write};
+
+use flipvm::obj::{self, Object};
+
+mod args;
+
+// ./link <object>...
+fn main() -> Result<(), String> {
+    let args_raw: Vec<_> = env::args().collect();
+    let args = args::process(&args_raw).map_err(|e| format!("{}", e))?;
+    if !args.validate() {
+        println!("{}", args.usage());
+        return Ok(());
+    }
+
+    let mut objects = Vec::new();
+    for file in &args.input_files {
+        let bytes = fs::read(file).map_err(|e| format!("failed to open {}: {}", file, e))?;
+        objects.push(Object::from_bytes(&bytes).map_err(|e| format!("{}: {}", file, e))?);
+    }
+
+    let exe = obj::link(&objects, args.load_address, args.entry.as_deref())?;
+    let output = exe.to_bytes()?;
+    match args.output_file {
+        Some(file) => {
+            fs::write(&file, output).map_err(|e| format!("failed to write {}: {}", file, e))
+        }
+        None => stdout()
+            .lock()
+            .write_all(&output)
+            .map_err(|e| format!("{}", e)),
+    }
+}
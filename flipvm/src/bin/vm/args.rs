@@ -0,0 +1,146 @@
+use std::fmt;
+
+pub enum ArgsError {
+    ExtraInput,
+    UnknownFlag(String),
+    MissingValue(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::ExtraInput => write!(f, "extra input file(s)"),
+            ArgsError::UnknownFlag(s) => write!(f, "unknown flag: {}", s),
+            ArgsError::MissingValue(s) => write!(f, "missing value for flag: {}", s),
+        }
+    }
+}
+
+pub struct Args {
+    pub bin_name: String,
+    pub input_file: Option<String>,
+    pub trace: bool,
+    pub trace_file: Option<String>,
+    pub debug: bool,
+    pub symbols_file: Option<String>,
+    pub debug_info_file: Option<String>,
+    pub gdb_addr: Option<String>,
+    pub profile_file: Option<String>,
+    pub folded_file: Option<String>,
+    show_help: bool,
+}
+
+impl Args {
+    pub fn validate(&self) -> bool {
+        if self.show_help {
+            return false;
+        };
+        self.input_file.is_some()
+    }
+
+    pub fn usage(&self) -> String {
+        format!(
+            "usage: {} [OPTIONS] <input file>
+
+options:
+    -h, --help\tShow this message.
+    -t, --trace\tWrite an execution trace to stderr, one JSON object per step.
+    --trace-file <file>\tWrite the execution trace to <file>.
+    -d, --debug\tRun the program in the interactive debugger.
+    --symbols <file>\tLoad labels for the debugger and profiler, one `<address> <label>` per line.
+    --debug-info <file>\tLoad source locations shown on faults, default `<input file>.dbg` if present.
+    --gdb <addr>\tWait for a GDB remote connection on <addr>, e.g. 127.0.0.1:1234.
+    --profile <file>\tWrite per-function, per-opcode and per-address instruction counts to <file>.
+    --profile-folded <file>\tWrite call stacks in folded format for flamegraph tools to <file>.
+
+",
+            self.bin_name
+        )
+    }
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            bin_name: ".".to_string(),
+            input_file: None,
+            trace: false,
+            trace_file: None,
+            debug: false,
+            symbols_file: None,
+            debug_info_file: None,
+            gdb_addr: None,
+            profile_file: None,
+            folded_file: None,
+            show_help: false,
+        }
+    }
+}
+
+pub fn process(args: &[String]) -> Result<Args, ArgsError> {
+    let mut out = Args {
+        bin_name: args[0].to_string(),
+        ..Default::default()
+    };
+
+    let mut iter = args[1..].iter();
+    while let Some(a) = iter.next() {
+        if let Some(flag) = a.strip_prefix("--") {
+            match flag {
+                "trace" => out.trace = true,
+                "trace-file" => {
+                    let file = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.trace_file = Some(file.to_string());
+                }
+                "debug" => out.debug = true,
+                "symbols" => {
+                    let file = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.symbols_file = Some(file.to_string());
+                }
+                "debug-info" => {
+                    let file = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.debug_info_file = Some(file.to_string());
+                }
+                "gdb" => {
+                    let addr = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.gdb_addr = Some(addr.to_string());
+                }
+                "profile" => {
+                    let file = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.profile_file = Some(file.to_string());
+                }
+                "profile-folded" => {
+                    let file = iter
+                        .next()
+                        .ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+                    out.folded_file = Some(file.to_string());
+                }
+                "help" => out.show_help = true,
+                x => return Err(ArgsError::UnknownFlag(x.to_string())),
+            }
+        } else if let Some(flag) = a.strip_prefix('-').filter(|f| !f.is_empty()) {
+            match flag {
+                "t" => out.trace = true,
+                "d" => out.debug = true,
+                "h" => out.show_help = true,
+                x => return Err(ArgsError::UnknownFlag(x.to_string())),
+            }
+        } else {
+            if out.input_file.is_some() {
+                return Err(ArgsError::ExtraInput);
+            };
+            out.input_file = Some(a.to_string());
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,520 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use flipvm::op::Instruction;
+use flipvm::{
+    Addressable, DebugInfo, Fault, Flag, Machine, Register, SymbolMap, WatchHit, WatchKind,
+};
+
+const HELP: &str = "commands:
+    b, break <loc>\tSet a breakpoint at an address or label.
+    d, delete <loc>\tRemove a breakpoint.
+    bl, breakpoints\tList breakpoints.
+    watch <addr> [n] [r|w|rw]\tPause on reads or writes of n bytes (default 2, w).
+    unwatch <addr>\tRemove a watchpoint.
+    s, step [n]\t\tExecute n instructions (default 1).
+    n, next\t\tExecute one instruction, stepping over calls.
+    c, continue\t\tRun until a breakpoint is hit or the program halts.
+    back [n]\t\tUndo n instructions (default 1).
+    bw <addr>\t\tRun backwards to the last write of an address.
+    r, regs\t\tShow registers and flags.
+    set <reg> <value>\tSet a register.
+    x <addr> [n]\tShow n memory words (default 8).
+    w <addr> <value>\tWrite a memory word.
+    l, dis [n]\t\tDisassemble n instructions around PC (default 5).
+    h, help\t\tShow this message.
+    q, quit\t\tStop debugging.
+";
+
+fn parse_number(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s
+            .parse::<u16>()
+            .ok()
+            .or_else(|| s.parse::<i16>().ok().map(|x| x as u16)),
+    }
+}
+
+enum Stop {
+    Breakpoint(u16),
+    Watch(Vec<WatchHit>),
+    Halted,
+    Fault(Fault),
+    Steps,
+}
+
+pub struct Debugger<'a> {
+    machine: &'a mut Machine,
+    symbols: SymbolMap,
+    debug_info: DebugInfo,
+    breakpoints: HashSet<u16>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(machine: &'a mut Machine, symbols: SymbolMap) -> Self {
+        Self {
+            machine,
+            symbols,
+            debug_info: DebugInfo::default(),
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    /// Shows source locations next to addresses.
+    pub fn with_debug_info(mut self, debug_info: DebugInfo) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    /// Reads command lines from `input` until it is exhausted or `quit` is entered.
+    pub fn run(
+        &mut self,
+        input: impl IntoIterator<Item = io::Result<String>>,
+        mut output: impl Write,
+    ) -> Result<(), String> {
+        let io_err = |e: io::Error| format!("debugger: {}", e);
+        write!(output, "{}\n(fdb) ", self.location(self.pc())).map_err(io_err)?;
+        output.flush().map_err(io_err)?;
+        for line in input {
+            let line = line.map_err(io_err)?;
+            let words: Vec<_> = line.split_whitespace().collect();
+            if let Some(&"q" | &"quit") = words.first() {
+                break;
+            }
+            let response = match self.command(&words) {
+                Ok(s) => s,
+                Err(e) => format!("error: {}\n", e),
+            };
+            write!(output, "{}(fdb) ", response).map_err(io_err)?;
+            output.flush().map_err(io_err)?;
+        }
+        writeln!(output).map_err(io_err)
+    }
+
+    fn command(&mut self, words: &[&str]) -> Result<String, String> {
+        match words {
+            [] => Ok(String::new()),
+            ["b" | "break", loc] => {
+                let addr = self.resolve(loc)?;
+                self.breakpoints.insert(addr);
+                Ok(format!("breakpoint at {}\n", self.location(addr)))
+            }
+            ["d" | "delete", loc] => {
+                let addr = self.resolve(loc)?;
+                if self.breakpoints.remove(&addr) {
+                    Ok(format!("deleted breakpoint at {}\n", self.location(addr)))
+                } else {
+                    Err(format!("no breakpoint at {}", self.location(addr)))
+                }
+            }
+            ["bl" | "breakpoints"] => {
+                let mut addrs: Vec<_> = self.breakpoints.iter().copied().collect();
+                addrs.sort();
+                Ok(addrs
+                    .into_iter()
+                    .map(|a| format!("{}\n", self.location(a)))
+                    .collect())
+            }
+            ["watch", addr, rest @ ..] if rest.len() <= 2 => {
+                let addr = self.resolve(addr)?;
+                let len = match rest.first() {
+                    Some(n) => parse_number(n).ok_or_else(|| format!("invalid length: {}", n))?,
+                    None => 2,
+                };
+                let kind = match rest.get(1) {
+                    None | Some(&"w") => WatchKind::Write,
+                    Some(&"r") => WatchKind::Read,
+                    Some(&"rw") => WatchKind::Access,
+                    Some(k) => return Err(format!("invalid watch kind: {}", k)),
+                };
+                self.machine.add_watchpoint(addr as u32, len as u32, kind);
+                Ok(format!(
+                    "watching {} bytes at 0x{:04X} for {}\n",
+                    len, addr, kind
+                ))
+            }
+            ["unwatch", addr] => {
+                let addr = self.resolve(addr)?;
+                if self.machine.remove_watchpoint(addr as u32) {
+                    Ok(format!("removed watchpoint at 0x{:04X}\n", addr))
+                } else {
+                    Err(format!("no watchpoint at 0x{:04X}", addr))
+                }
+            }
+            ["s" | "step"] => {
+                let stop = self.step_n(1);
+                Ok(self.report(stop))
+            }
+            ["s" | "step", n] => {
+                let n = parse_number(n).ok_or_else(|| format!("invalid count: {}", n))?;
+                let stop = self.step_n(n);
+                Ok(self.report(stop))
+            }
+            ["n" | "next"] => {
+                let stop = self.next();
+                Ok(self.report(stop))
+            }
+            ["c" | "continue"] => {
+                let stop = self.continue_();
+                Ok(self.report(stop))
+            }
+            ["back"] => self.back(1),
+            ["back", n] => {
+                let n = parse_number(n).ok_or_else(|| format!("invalid count: {}", n))?;
+                self.back(n as usize)
+            }
+            ["bw", addr] => {
+                let addr = self.resolve(addr)?;
+                match self.machine.step_back_to_write(addr as u32)? {
+                    Some(pc) => Ok(format!(
+                        "0x{:04X} last written at {}\n{}",
+                        addr,
+                        self.location(pc),
+                        self.disassemble(0)
+                    )),
+                    None => Err(format!("no recorded write of 0x{:04X}", addr)),
+                }
+            }
+            ["r" | "regs"] => Ok(self.registers()),
+            ["set", reg, value] => {
+                let reg = Register::from_str(reg)?;
+                let value =
+                    parse_number(value).ok_or_else(|| format!("invalid value: {}", value))?;
+                self.machine.set_register(reg, value);
+                Ok(format!("{} = 0x{:04X}\n", reg, value))
+            }
+            ["x", addr] => self.memory(addr, 8),
+            ["x", addr, n] => {
+                let n = parse_number(n).ok_or_else(|| format!("invalid count: {}", n))?;
+                self.memory(addr, n)
+            }
+            ["w", addr, value] => {
+                let addr = self.resolve(addr)?;
+                let value =
+                    parse_number(value).ok_or_else(|| format!("invalid value: {}", value))?;
+                self.machine
+                    .vm
+                    .memory
+                    .write2(addr as u32, value)
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("0x{:04X} = 0x{:04X}\n", addr, value))
+            }
+            ["l" | "dis"] => Ok(self.disassemble(5)),
+            ["l" | "dis", n] => {
+                let n = parse_number(n).ok_or_else(|| format!("invalid count: {}", n))?;
+                Ok(self.disassemble(n))
+            }
+            ["h" | "help"] => Ok(HELP.to_string()),
+            [cmd, ..] => Err(format!("unknown command: {}, try help", cmd)),
+        }
+    }
+
+    fn back(&mut self, n: usize) -> Result<String, String> {
+        let undone = self.machine.step_back(n)?;
+        if undone < n {
+            Ok(format!(
+                "undid {} instruction(s), start of history\n{}",
+                undone,
+                self.disassemble(0)
+            ))
+        } else {
+            Ok(self.disassemble(0))
+        }
+    }
+
+    fn pc(&self) -> u16 {
+        self.machine.get_register(Register::PC)
+    }
+
+    fn resolve(&self, loc: &str) -> Result<u16, String> {
+        parse_number(loc)
+            .or_else(|| self.symbols.lookup(loc))
+            .ok_or_else(|| format!("unknown location: {}", loc))
+    }
+
+    /// Formats an address with the closest preceding label and its source location.
+    fn location(&self, addr: u16) -> String {
+        let mut out = if self.symbols.containing(addr).is_some() {
+            format!("0x{:04X} <{}>", addr, self.symbols.describe(addr))
+        } else {
+            format!("0x{:04X}", addr)
+        };
+        if let Some(location) = self.debug_info.location(addr) {
+            let file = self.debug_info.file.as_deref().unwrap_or("?");
+            let _ = write!(out, " at {}:{}", file, location);
+        }
+        out
+    }
+
+    fn step_once(&mut self) -> Option<Stop> {
+        if self.machine.is_halted() {
+            return Some(Stop::Halted);
+        }
+        if let Err(fault) = self.machine.step() {
+            return Some(Stop::Fault(fault));
+        }
+        if self.machine.hit_watchpoint() {
+            return Some(Stop::Watch(self.machine.vm.last_watch_hits().to_vec()));
+        }
+        if self.machine.is_halted() {
+            return Some(Stop::Halted);
+        }
+        None
+    }
+
+    fn step_n(&mut self, n: u16) -> Stop {
+        for _ in 0..n {
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+        }
+        Stop::Steps
+    }
+
+    /// Runs until `done` holds after a step, stopping early at breakpoints.
+    fn run_until(&mut self, done: impl Fn(&Machine) -> bool) -> Stop {
+        loop {
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+            if done(self.machine) {
+                return Stop::Steps;
+            }
+            let pc = self.pc();
+            if self.breakpoints.contains(&pc) {
+                return Stop::Breakpoint(pc);
+            }
+        }
+    }
+
+    fn next(&mut self) -> Stop {
+        let pc = self.pc();
+        let is_call = self
+            .machine
+            .vm
+            .memory
+            .peek2(pc as u32)
+            .and_then(|raw| Instruction::try_from(raw).ok())
+            .map(|ins| matches!(ins, Instruction::Call(_)))
+            .unwrap_or(false);
+        if !is_call {
+            return self.step_n(1);
+        }
+
+        // the call returns once PC is past it with the caller's stack pointer restored
+        let sp = self.machine.get_register(Register::SP);
+        self.run_until(|m| {
+            m.get_register(Register::PC) == pc.wrapping_add(2) && m.get_register(Register::SP) == sp
+        })
+    }
+
+    fn continue_(&mut self) -> Stop {
+        self.run_until(|_| false)
+    }
+
+    fn report(&self, stop: Stop) -> String {
+        let mut out = match stop {
+            Stop::Breakpoint(addr) => format!("breakpoint hit at {}\n", self.location(addr)),
+            Stop::Watch(hits) => hits
+                .iter()
+                .map(|h| {
+                    format!(
+                        "watchpoint: {} 0x{:04X} = 0x{:02X} by {}\n",
+                        h.kind,
+                        h.addr,
+                        h.value,
+                        self.location(h.pc)
+                    )
+                })
+                .collect(),
+            Stop::Halted => match self.machine.exit_code() {
+                Some(code) => format!("program halted with exit code {}\n", code),
+                None => "program halted\n".to_string(),
+            },
+            Stop::Fault(fault) => format!("fault at {}: {}\n", self.location(self.pc()), fault),
+            Stop::Steps => String::new(),
+        };
+        out.push_str(&self.disassemble(0));
+        out
+    }
+
+    fn registers(&self) -> String {
+        let mut out = String::new();
+        for r in Register::ALL.iter().skip(1) {
+            let _ = writeln!(
+                out,
+                "{:<4} 0x{:04X}",
+                r.to_string(),
+                self.machine.get_register(*r)
+            );
+        }
+        let flags: Vec<_> = Flag::ALL
+            .iter()
+            .filter(|&&f| self.machine.test_flag(f))
+            .map(|f| f.to_string())
+            .collect();
+        let _ = writeln!(out, "Flags: [{}]", flags.join(", "));
+        out
+    }
+
+    fn memory(&self, addr: &str, n: u16) -> Result<String, String> {
+        let start = self.resolve(addr)?;
+        let mut out = String::new();
+        for i in 0..n {
+            let addr = start.wrapping_add(i * 2);
+            if i % 4 == 0 {
+                if i > 0 {
+                    out.push('\n');
+                }
+                let _ = write!(out, "0x{:04X}:", addr);
+            }
+            match self.machine.vm.memory.peek2(addr as u32) {
+                Some(value) => {
+                    let _ = write!(out, " 0x{:04X}", value);
+                }
+                None => out.push_str(" ??????"),
+            }
+        }
+        out.push('\n');
+        Ok(out)
+    }
+
+    /// Lists `n` instructions before and after PC.
+    fn disassemble(&self, n: u16) -> String {
+        let pc = self.pc();
+        let mut out = String::new();
+        for i in 0..=(2 * n) {
+            let addr = match (pc as i32) + ((i as i32) - (n as i32)) * 2 {
+                x if (0..=0xffff).contains(&x) => x as u16,
+                _ => continue,
+            };
+            if let Some(label) = self.symbols.label_at(addr) {
+                let _ = writeln!(out, "{}:", label);
+            }
+            let marker = match (addr == pc, self.breakpoints.contains(&addr)) {
+                (true, _) => "=>",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
+            let text = match self.machine.vm.memory.peek2(addr as u32) {
+                Some(raw) => match Instruction::try_from(raw) {
+                    Ok(ins) => format!("{:04X}  {}", raw, ins),
+                    Err(_) => format!("{:04X}  ???", raw),
+                },
+                None => continue,
+            };
+            let _ = writeln!(out, "{} 0x{:04X}  {}", marker, addr, text);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flipvm::op::{Literal12Bit, Literal7Bit, Nibble};
+    use flipvm::LinearMemory;
+    use std::io::BufRead;
+    use Instruction::*;
+    use Register::*;
+
+    fn machine(program: &[Instruction]) -> Machine {
+        let mut m = Machine::with_syscalls(&b""[..], Vec::new());
+        m.map(0x0, 0x1000, Box::new(LinearMemory::new(0x1000)))
+            .unwrap();
+        for (i, ins) in program.iter().enumerate() {
+            m.vm.memory
+                .write2((i * 2) as u32, ins.encode_u16())
+                .unwrap();
+        }
+        m.set_register(SP, 0x800);
+        m
+    }
+
+    fn session(m: &mut Machine, symbols: SymbolMap, script: &str) -> String {
+        let mut out = Vec::new();
+        Debugger::new(m, symbols)
+            .run(script.as_bytes().lines(), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // main: A = 1; call f; halt. f: A += 2; ret
+    fn program() -> (Vec<Instruction>, SymbolMap) {
+        let program = vec![
+            Imm(A, Literal12Bit::new_checked(1).unwrap()),
+            Imm(C, Literal12Bit::new_checked(12).unwrap()),
+            Call(C),
+            Imm(C, Literal12Bit::new_checked(0xf0).unwrap()),
+            System(C, Zero, Nibble::new_checked(0).unwrap()),
+            Invalid,
+            AddImm(A, Literal7Bit::new_checked(2).unwrap()),
+            Ret,
+        ];
+        let symbols = SymbolMap::from_str("0x0 main\n0xc f\n").unwrap();
+        (program, symbols)
+    }
+
+    #[test]
+    fn breakpoint_by_label() {
+        let (program, symbols) = program();
+        let mut m = machine(&program);
+        let out = session(&mut m, symbols, "b f\nc\nr\n");
+        assert!(out.contains("breakpoint hit at 0x000C <f>"), "{}", out);
+        assert_eq!(m.get_register(PC), 12);
+        assert_eq!(m.get_register(A), 1);
+    }
+
+    #[test]
+    fn step_over_call() {
+        let (program, symbols) = program();
+        let mut m = machine(&program);
+        session(&mut m, symbols, "s 2\nn\n");
+        assert_eq!(m.get_register(PC), 6);
+        assert_eq!(m.get_register(A), 3);
+    }
+
+    #[test]
+    fn reverse() {
+        let (program, symbols) = program();
+        let mut m = machine(&program);
+        m.enable_journal(100);
+        let out = session(&mut m, symbols, "c\nback 3\nbw 0x802\n");
+        assert!(
+            out.contains("0x0802 last written at 0x0004 <main+4>"),
+            "{}",
+            out
+        );
+        assert_eq!(m.get_register(PC), 4);
+        assert_eq!(m.get_register(A), 1);
+    }
+
+    #[test]
+    fn watchpoint() {
+        let (program, symbols) = program();
+        let mut m = machine(&program);
+        let out = session(&mut m, symbols, "watch 0x802\nc\n");
+        assert!(
+            out.contains("watchpoint: write 0x0802 = 0x06 by 0x0004 <main+4>"),
+            "{}",
+            out
+        );
+        assert_eq!(m.get_register(PC), 12);
+    }
+
+    #[test]
+    fn modify_and_continue() {
+        let (program, symbols) = program();
+        let mut m = machine(&program);
+        let out = session(
+            &mut m,
+            symbols,
+            "s\nset A 40\nw 0x100 0x1234\nx 0x100 1\nc\n",
+        );
+        assert!(out.contains("0x0100: 0x1234"), "{}", out);
+        assert!(out.contains("program halted with exit code 42"), "{}", out);
+    }
+}
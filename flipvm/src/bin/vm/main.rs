@@ -0,0 +1,217 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, stderr, stdin, stdout, BufRead, BufReader, BufWriter, Read};
+use std::iter;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+use flipvm::exe::{self, Executable};
+use flipvm::{gdb, layout};
+use flipvm::{DebugInfo, Fault, JsonTracer, Machine, Register, SymbolMap, Tracer, TrapCause};
+
+mod args;
+mod debug;
+
+// steps that can be undone in the debugger
+const DEBUG_JOURNAL_SIZE: usize = 100_000;
+// addresses listed in the profile report
+const PROFILE_TOP_PCS: usize = 20;
+
+// Exit codes for failures outside of the program, a halted program exits with its own code
+const EXIT_USAGE: i32 = 64;
+const EXIT_NO_INPUT: i32 = 66;
+const EXIT_HOST_FAULT: i32 = 70;
+
+// Unhandled traps exit like a host process killed by the matching signal
+fn fault_exit_code(fault: &Fault) -> i32 {
+    match fault {
+        Fault::Trap(TrapCause::IllegalInstruction, _) => 128 + 4,
+        Fault::Trap(TrapCause::MemoryFault, _) => 128 + 11,
+        Fault::Trap(TrapCause::UnknownSignal, _) => 128 + 31,
+        Fault::Trap(TrapCause::Interrupt, _) | Fault::Host(_) => EXIT_HOST_FAULT,
+    }
+}
+
+fn load_program(path: &str) -> Result<Vec<u8>, String> {
+    let reader: Box<dyn Read> = match path {
+        "-" => Box::new(stdin()),
+        _ => Box::new(File::open(Path::new(path)).map_err(|e| format!("failed to open: {}", e))?),
+    };
+
+    let mut reader = BufReader::new(reader);
+    let mut program: Vec<u8> = Vec::new();
+    reader
+        .read_to_end(&mut program)
+        .map_err(|e| format!("read: {}", e))?;
+    Ok(program)
+}
+
+/// Lines of stdin, locking it only while each is read so the program can read it in between.
+fn stdin_lines() -> impl Iterator<Item = io::Result<String>> {
+    iter::from_fn(|| {
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(line.trim_end_matches(['\r', '\n']).to_string())),
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// Maps memory and devices, then loads the program, an executable or a raw image at 0x0.
+fn init_machine(program: Vec<u8>) -> Result<(Machine, Executable), String> {
+    let mut vm = Machine::with_std_syscalls();
+    layout::map_std(&mut vm)?;
+    let exe = exe::load(&mut vm, program)?;
+    Ok((vm, exe))
+}
+
+fn init_tracer(args: &args::Args) -> Result<Option<Box<dyn Tracer>>, String> {
+    if let Some(path) = &args.trace_file {
+        let file = File::create(path).map_err(|e| format!("failed to create: {}", e))?;
+        Ok(Some(Box::new(JsonTracer::new(BufWriter::new(file)))))
+    } else if args.trace {
+        Ok(Some(Box::new(JsonTracer::new(stderr()))))
+    } else {
+        Ok(None)
+    }
+}
+
+fn load_debug_info(args: &args::Args) -> Result<DebugInfo, String> {
+    let path = match &args.debug_info_file {
+        Some(path) => path.clone(),
+        None => {
+            // the sidecar written by `compile`
+            let sidecar = Path::new(args.input_file.as_ref().unwrap()).with_extension("dbg");
+            if !sidecar.is_file() {
+                return Ok(DebugInfo::default());
+            }
+            sidecar.to_string_lossy().into_owned()
+        }
+    };
+    std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read debug info: {}", e))
+        .and_then(|s| DebugInfo::from_str(&s))
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+fn load_symbols(
+    args: &args::Args,
+    debug_info: &DebugInfo,
+    exe: &Executable,
+) -> Result<SymbolMap, String> {
+    match (&args.symbols_file, &exe.symbols) {
+        (Some(path), _) => std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read symbols: {}", e))
+            .and_then(|s| SymbolMap::from_str(&s)),
+        (None, Some(symbols)) if debug_info.functions().is_empty() => Ok(symbols.clone()),
+        (None, _) => Ok(debug_info.symbols()),
+    }
+}
+
+fn write_profile(vm: &mut Machine, args: &args::Args, symbols: &SymbolMap) -> Result<(), String> {
+    let profiler = match vm.take_profiler() {
+        Some(profiler) => profiler,
+        None => return Ok(()),
+    };
+    if let Some(path) = &args.profile_file {
+        std::fs::write(path, profiler.report(symbols, PROFILE_TOP_PCS))
+            .map_err(|e| format!("failed to write profile: {}", e))?;
+    }
+    if let Some(path) = &args.folded_file {
+        std::fs::write(path, profiler.folded(symbols))
+            .map_err(|e| format!("failed to write profile: {}", e))?;
+    }
+    Ok(())
+}
+
+pub fn main() {
+    let args_raw: Vec<_> = env::args().collect();
+    let args = args::process(&args_raw).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_USAGE);
+    });
+    if !args.validate() {
+        eprintln!("{}", args.usage());
+        process::exit(EXIT_USAGE);
+    }
+
+    let tracer = init_tracer(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_NO_INPUT);
+    });
+    let program = load_program(args.input_file.as_ref().unwrap()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_NO_INPUT);
+    });
+    let (mut vm, exe) = init_machine(program).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_HOST_FAULT);
+    });
+
+    let debug_info = load_debug_info(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_NO_INPUT);
+    });
+    let symbols = load_symbols(&args, &debug_info, &exe).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_NO_INPUT);
+    });
+
+    if let Some(tracer) = tracer {
+        vm.set_tracer(tracer);
+    }
+    if args.profile_file.is_some() || args.folded_file.is_some() {
+        vm.enable_profiler();
+    }
+
+    if let Some(addr) = &args.gdb_addr {
+        let res = gdb::listen(&mut vm, addr.as_str());
+        vm.take_tracer();
+        if let Err(e) = res {
+            eprintln!("{}", e);
+            process::exit(EXIT_HOST_FAULT);
+        }
+        process::exit((vm.exit_code().unwrap_or(0) & 0xff) as i32);
+    }
+
+    if args.debug {
+        vm.enable_journal(DEBUG_JOURNAL_SIZE);
+        let mut debugger = debug::Debugger::new(&mut vm, symbols).with_debug_info(debug_info);
+        // the program keeps stdin when commands can come from the terminal
+        let res = match File::open("/dev/tty") {
+            Ok(tty) => debugger.run(BufReader::new(tty).lines(), stdout()),
+            Err(_) => debugger.run(stdin_lines(), stdout()),
+        };
+        vm.take_tracer();
+        if let Err(e) = res {
+            eprintln!("{}", e);
+            process::exit(EXIT_HOST_FAULT);
+        }
+        process::exit((vm.exit_code().unwrap_or(0) & 0xff) as i32);
+    }
+
+    let mut code = 0;
+    while !vm.is_halted() {
+        if let Err(fault) = vm.step() {
+            let pc = vm.get_register(Register::PC);
+            match debug_info.describe(pc) {
+                Some(location) => eprintln!("fault @ 0x{:X} ({}): {}", pc, location, fault),
+                None => eprintln!("fault @ 0x{:X}: {}", pc, fault),
+            }
+            code = fault_exit_code(&fault);
+            break;
+        }
+    }
+    // drop the tracer to flush buffered output before exiting
+    vm.take_tracer();
+    if let Err(e) = write_profile(&mut vm, &args, &symbols) {
+        eprintln!("{}", e);
+        process::exit(EXIT_HOST_FAULT);
+    }
+    if vm.is_halted() {
+        code = (vm.exit_code().unwrap_or(0) & 0xff) as i32;
+    }
+    process::exit(code);
+}
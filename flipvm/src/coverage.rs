@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+
+use crate::op::Instruction;
+
+/// Execution counts per PC, and the outcomes of each conditional branch.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    hits: BTreeMap<u16, u64>,
+    // (taken, fell through) per branch address
+    branches: BTreeMap<u16, (u64, u64)>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the instruction executed at `pc`, `next_pc` is PC after it executed.
+    pub fn record(&mut self, pc: u16, instruction: Option<&Instruction>, next_pc: u16) {
+        *self.hits.entry(pc).or_default() += 1;
+        if let Some(Instruction::BranchIf(_) | Instruction::BranchIfNot(_)) = instruction {
+            let outcome = self.branches.entry(pc).or_default();
+            if next_pc == pc.wrapping_add(2) {
+                outcome.1 += 1;
+            } else {
+                outcome.0 += 1;
+            }
+        }
+    }
+
+    pub fn hits(&self, pc: u16) -> u64 {
+        self.hits.get(&pc).copied().unwrap_or(0)
+    }
+
+    /// How often the branch at `pc` was taken and fell through.
+    pub fn branch(&self, pc: u16) -> (u64, u64) {
+        self.branches.get(&pc).copied().unwrap_or((0, 0))
+    }
+
+    /// Executed addresses and their counts, in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u64)> + '_ {
+        self.hits.iter().map(|(&pc, &count)| (pc, count))
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::symbols::parse_address;
+use crate::SymbolMap;
+
+/// A local variable or argument, stored at `offset` bytes from BP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalSlot {
+    pub name: String,
+    pub offset: i16,
+}
+
+/// The code of a function, from `start` up to but not including `end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub start: u16,
+    pub end: u16,
+    pub locals: Vec<LocalSlot>,
+}
+
+/// A 1-based position in the source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Maps a program back to its source: the line of each instruction, function boundaries and the
+/// frame slots of their locals.
+///
+/// Stored as text with one entry per line:
+///
+/// ```text
+/// file fib.fl
+/// func 0x000c 0x0030 main
+/// local 0 n
+/// line 0x000c 1:1
+/// ```
+///
+/// `local` entries belong to the preceding `func`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub file: Option<String>,
+    functions: Vec<FunctionInfo>,
+    // sorted by address
+    lines: Vec<(u16, SourceLocation)>,
+}
+
+impl DebugInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_function(&mut self, function: FunctionInfo) {
+        let index = self
+            .functions
+            .partition_point(|f| f.start <= function.start);
+        self.functions.insert(index, function);
+    }
+
+    pub fn add_line(&mut self, addr: u16, location: SourceLocation) {
+        let index = self.lines.partition_point(|&(a, _)| a <= addr);
+        self.lines.insert(index, (addr, location));
+    }
+
+    pub fn functions(&self) -> &[FunctionInfo] {
+        &self.functions
+    }
+
+    pub fn function(&self, name: &str) -> Option<&FunctionInfo> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    pub fn function_at(&self, addr: u16) -> Option<&FunctionInfo> {
+        self.functions
+            .iter()
+            .find(|f| f.start <= addr && addr < f.end)
+    }
+
+    /// The source location of the instruction at `addr`, or of the closest one before it in the
+    /// same function. Addresses outside every function have no location.
+    pub fn location(&self, addr: u16) -> Option<SourceLocation> {
+        let function = self.function_at(addr)?;
+        let index = self.lines.partition_point(|&(a, _)| a <= addr);
+        let (start, location) = *self.lines.get(index.checked_sub(1)?)?;
+        // don't attribute code without a line to the end of the previous function
+        (start >= function.start).then_some(location)
+    }
+
+    /// Formats `addr` as its source location and function, e.g. `fib.fl:10:5 in fib`.
+    pub fn describe(&self, addr: u16) -> Option<String> {
+        let function = self.function_at(addr).map(|f| f.name.as_str());
+        match (self.location(addr), function) {
+            (Some(location), Some(function)) => Some(format!(
+                "{}:{} in {}",
+                self.file.as_deref().unwrap_or("?"),
+                location,
+                function
+            )),
+            (Some(location), None) => Some(format!(
+                "{}:{}",
+                self.file.as_deref().unwrap_or("?"),
+                location
+            )),
+            (None, Some(function)) => Some(format!("in {}", function)),
+            (None, None) => None,
+        }
+    }
+
+    /// The entry point of each function.
+    pub fn symbols(&self) -> SymbolMap {
+        let mut out = SymbolMap::new();
+        for f in &self.functions {
+            out.insert(&f.name, f.start);
+        }
+        out
+    }
+}
+
+fn parse_location(s: &str) -> Option<SourceLocation> {
+    let (line, column) = s.split_once(':')?;
+    Some(SourceLocation {
+        line: line.parse().ok()?,
+        column: column.parse().ok()?,
+    })
+}
+
+impl FromStr for DebugInfo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut out = Self::default();
+        let mut function: Option<FunctionInfo> = None;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let address = |s: &str| {
+                parse_address(s).ok_or_else(|| format!("line {}: invalid address: {}", i + 1, s))
+            };
+            if let Some(path) = line.strip_prefix("file ") {
+                out.file = Some(path.trim().to_string());
+                continue;
+            }
+            let parts: Vec<_> = line.split_whitespace().collect();
+            match parts[..] {
+                ["func", start, end, name] => {
+                    if let Some(f) = function.take() {
+                        out.add_function(f);
+                    }
+                    function = Some(FunctionInfo {
+                        name: name.to_string(),
+                        start: address(start)?,
+                        end: address(end)?,
+                        locals: Vec::new(),
+                    });
+                }
+                ["local", offset, name] => {
+                    let offset = offset
+                        .parse()
+                        .map_err(|_| format!("line {}: invalid offset: {}", i + 1, offset))?;
+                    function
+                        .as_mut()
+                        .ok_or_else(|| format!("line {}: local outside of a function", i + 1))?
+                        .locals
+                        .push(LocalSlot {
+                            name: name.to_string(),
+                            offset,
+                        });
+                }
+                ["line", addr, location] => {
+                    let location = parse_location(location)
+                        .ok_or_else(|| format!("line {}: invalid location: {}", i + 1, location))?;
+                    out.add_line(address(addr)?, location);
+                }
+                _ => return Err(format!("line {}: unknown entry: {}", i + 1, line)),
+            }
+        }
+        if let Some(f) = function {
+            out.add_function(f);
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for DebugInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            writeln!(f, "file {}", file)?;
+        }
+        for function in &self.functions {
+            writeln!(
+                f,
+                "func 0x{:04x} 0x{:04x} {}",
+                function.start, function.end, function.name
+            )?;
+            for local in &function.locals {
+                writeln!(f, "local {} {}", local.offset, local.name)?;
+            }
+        }
+        for (addr, location) in &self.lines {
+            writeln!(f, "line 0x{:04x} {}", addr, location)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+//! The Flip executable format.
+//!
+//! All values are little endian:
+//!
+//! ```text
+//! magic          4  "FLIP"
+//! version        u16
+//! entry          u16  initial PC
+//! load_address   u32  base of every section
+//! section_count  u16
+//! symbol_count   u16  0 when there is no symbol table
+//! sections       section_count * (kind u16, offset u32, size u32)
+//! section data   the bytes of each code and data section, in order
+//! symbols        symbol_count * (address u16, name length u8, name)
+//! ```
+//!
+//! Section offsets are relative to the load address. Bss sections have no data and are mapped
+//! as zeroed memory.
+
+use std::fmt;
+
+use crate::format::{write_name, Reader};
+use crate::{LinearMemory, Machine, MappedMemoryBuffer, Register, SymbolMap};
+
+pub const MAGIC: [u8; 4] = *b"FLIP";
+pub const VERSION: u16 = 1;
+
+const HEADER_SIZE: usize = 16;
+const SECTION_HEADER_SIZE: usize = 10;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FormatError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidSectionKind(u16),
+    InvalidSymbol(String),
+    InvalidRelocationKind(u8),
+    /// A symbol name that doesn't fit its length byte.
+    NameTooLong(String),
+    /// Two sections covering the same bytes, by offset.
+    OverlappingSections(u32, u32),
+    /// A section, by offset, that doesn't fit in the 16 bit address space once loaded.
+    SectionOutOfRange(u32),
+    /// An entry point outside every code section.
+    EntryOutsideCode(u16),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::BadMagic => write!(f, "not a flip executable"),
+            FormatError::UnsupportedVersion(v) => write!(f, "unsupported version: {}", v),
+            FormatError::Truncated => write!(f, "truncated executable"),
+            FormatError::InvalidSectionKind(k) => write!(f, "invalid section kind: {}", k),
+            FormatError::InvalidSymbol(s) => write!(f, "invalid symbol: {}", s),
+            FormatError::InvalidRelocationKind(k) => write!(f, "invalid relocation kind: {}", k),
+            FormatError::NameTooLong(name) => write!(f, "name longer than 255 bytes: {}", name),
+            FormatError::OverlappingSections(a, b) => {
+                write!(f, "sections at 0x{:X} and 0x{:X} overlap", a, b)
+            }
+            FormatError::SectionOutOfRange(offset) => {
+                write!(f, "section at 0x{:X} is outside the address space", offset)
+            }
+            FormatError::EntryOutsideCode(entry) => {
+                write!(f, "entry 0x{:X} is outside the code", entry)
+            }
+        }
+    }
+}
+
+impl From<FormatError> for String {
+    fn from(value: FormatError) -> Self {
+        format!("{}", value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum SectionKind {
+    Code = 1,
+    Data = 2,
+    Bss = 3,
+}
+
+impl TryFrom<u16> for SectionKind {
+    type Error = FormatError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(SectionKind::Code),
+            2 => Ok(SectionKind::Data),
+            3 => Ok(SectionKind::Bss),
+            x => Err(FormatError::InvalidSectionKind(x)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub offset: u32,
+    pub size: u32,
+    // empty for bss
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Executable {
+    pub entry: u16,
+    pub load_address: u32,
+    pub sections: Vec<Section>,
+    pub symbols: Option<SymbolMap>,
+}
+
+impl Executable {
+    pub fn new(entry: u16, load_address: u32) -> Self {
+        Self {
+            entry,
+            load_address,
+            sections: Vec::new(),
+            symbols: None,
+        }
+    }
+
+    /// A raw program image as a single code section, entered at its first instruction.
+    pub fn from_raw(program: Vec<u8>, load_address: u32) -> Self {
+        let mut out = Self::new(load_address as u16, load_address);
+        out.add_section(SectionKind::Code, 0, program);
+        out
+    }
+
+    /// Returns true if `bytes` starts with the executable magic number.
+    pub fn is_executable(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    /// Adds a code or data section at `offset` from the load address.
+    pub fn add_section(&mut self, kind: SectionKind, offset: u32, data: Vec<u8>) {
+        self.sections.push(Section {
+            kind,
+            offset,
+            size: data.len() as u32,
+            data,
+        });
+    }
+
+    /// Adds `size` bytes of zeroed memory at `offset` from the load address.
+    pub fn add_bss(&mut self, offset: u32, size: u32) {
+        self.sections.push(Section {
+            kind: SectionKind::Bss,
+            offset,
+            size,
+            data: Vec::new(),
+        });
+    }
+
+    /// The code sections, in order.
+    pub fn code(&self) -> impl Iterator<Item = &Section> {
+        self.sections.iter().filter(|s| s.kind == SectionKind::Code)
+    }
+
+    /// The loaded address range of `section`, which may lie past the 16 bit address space.
+    fn range(&self, section: &Section) -> (u64, u64) {
+        let start = self.load_address as u64 + section.offset as u64;
+        (start, start + section.size as u64)
+    }
+
+    /// Checks that every section fits in the address space once loaded, that no two sections
+    /// cover the same bytes and that the entry point is in a code section.
+    pub fn check_sections(&self) -> Result<(), FormatError> {
+        if let Some(section) = self.sections.iter().find(|s| self.range(s).1 > 0x10000) {
+            return Err(FormatError::SectionOutOfRange(section.offset));
+        }
+        let entry = self.entry as u64;
+        if !self.code().any(|s| {
+            let (start, end) = self.range(s);
+            start <= entry && entry < end
+        }) {
+            return Err(FormatError::EntryOutsideCode(self.entry));
+        }
+
+        let mut ranges: Vec<_> = self
+            .sections
+            .iter()
+            .filter(|s| s.size > 0)
+            .map(|s| (s.offset, s.offset as u64 + s.size as u64))
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            if (pair[1].0 as u64) < pair[0].1 {
+                return Err(FormatError::OverlappingSections(pair[0].0, pair[1].0));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FormatError> {
+        self.check_sections()?;
+        let symbols: Vec<_> = self
+            .symbols
+            .iter()
+            .flat_map(|symbols| symbols.iter())
+            .collect();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.entry.to_le_bytes());
+        out.extend_from_slice(&self.load_address.to_le_bytes());
+        out.extend_from_slice(&(self.sections.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(symbols.len() as u16).to_le_bytes());
+        for section in &self.sections {
+            out.extend_from_slice(&(section.kind as u16).to_le_bytes());
+            out.extend_from_slice(&section.offset.to_le_bytes());
+            out.extend_from_slice(&section.size.to_le_bytes());
+        }
+        for section in &self.sections {
+            out.extend_from_slice(&section.data);
+        }
+        for (addr, name) in symbols {
+            out.extend_from_slice(&addr.to_le_bytes());
+            write_name(&mut out, name)?;
+        }
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        if !Self::is_executable(bytes) {
+            return Err(FormatError::BadMagic);
+        }
+        let mut r = Reader::new(bytes, MAGIC.len());
+        let version = r.u16()?;
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let mut out = Self::new(r.u16()?, r.u32()?);
+        let section_count = r.u16()? as usize;
+        let symbol_count = r.u16()? as usize;
+        debug_assert_eq!(r.pos, HEADER_SIZE);
+
+        let mut headers = Vec::with_capacity(section_count);
+        for _ in 0..section_count {
+            let kind = SectionKind::try_from(r.u16()?)?;
+            headers.push((kind, r.u32()?, r.u32()?));
+        }
+        debug_assert_eq!(r.pos, HEADER_SIZE + section_count * SECTION_HEADER_SIZE);
+        for (kind, offset, size) in headers {
+            match kind {
+                SectionKind::Bss => out.add_bss(offset, size),
+                _ => {
+                    let data = r.take(size as usize)?.to_vec();
+                    out.add_section(kind, offset, data);
+                }
+            }
+        }
+
+        if symbol_count > 0 {
+            let mut symbols = SymbolMap::new();
+            for _ in 0..symbol_count {
+                let addr = r.u16()?;
+                symbols.insert(&r.name()?, addr);
+            }
+            out.symbols = Some(symbols);
+        }
+        out.check_sections()?;
+        Ok(out)
+    }
+
+    /// Maps every section into `machine` and sets PC to the entry point.
+    pub fn load(&self, machine: &mut Machine) -> Result<(), String> {
+        self.check_sections()?;
+        for section in &self.sections {
+            // in range after check_sections
+            let start = self.range(section).0 as usize;
+            let size = section.size as usize;
+            match section.kind {
+                SectionKind::Bss => machine.map(start, size, Box::new(LinearMemory::new(size)))?,
+                _ => machine.map(
+                    start,
+                    size,
+                    Box::new(MappedMemoryBuffer::new(section.data.clone())),
+                )?,
+            }
+        }
+        machine.set_register(Register::PC, self.entry);
+        Ok(())
+    }
+}
+
+/// Loads an executable, or a raw program image mapped at 0x0, into `machine`.
+pub fn load(machine: &mut Machine, image: Vec<u8>) -> Result<Executable, String> {
+    let exe = if Executable::is_executable(&image) {
+        Executable::from_bytes(&image)?
+    } else {
+        Executable::from_raw(image, 0x0)
+    };
+    exe.load(machine)?;
+    Ok(exe)
+}
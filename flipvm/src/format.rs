@@ -0,0 +1,54 @@
+//! Reading and writing the little endian fields shared by executables and objects.
+
+use crate::exe::FormatError;
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], pos: usize) -> Self {
+        Self { bytes, pos }
+    }
+
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], FormatError> {
+        let end = self.pos.checked_add(n).ok_or(FormatError::Truncated)?;
+        let out = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(FormatError::Truncated)?;
+        self.pos = end;
+        Ok(out)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, FormatError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, FormatError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// A name prefixed with its length byte.
+    pub fn name(&mut self) -> Result<String, FormatError> {
+        let len = self.u8()? as usize;
+        std::str::from_utf8(self.take(len)?)
+            .map(|s| s.to_string())
+            .map_err(|e| FormatError::InvalidSymbol(e.to_string()))
+    }
+}
+
+/// Writes `name` prefixed with its length byte.
+pub(crate) fn write_name(out: &mut Vec<u8>, name: &str) -> Result<(), FormatError> {
+    let len = u8::try_from(name.len()).map_err(|_| FormatError::NameTooLong(name.to_string()))?;
+    out.push(len);
+    out.extend_from_slice(name.as_bytes());
+    Ok(())
+}
@@ -0,0 +1,400 @@
+//! A GDB remote serial protocol stub for `Machine`.
+//!
+//! Registers are reported in `Register` order (`Zero, A, B, C, M, SP, PC, BP`), each as a 16 bit
+//! little endian value.
+
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::{Addressable, Fault, Machine, Register, TrapCause, WatchKind};
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+const SIGSYS: u8 = 31;
+
+// instructions run between checks for an interrupt while continuing
+const POLL_INTERVAL: usize = 1024;
+
+// the largest packet accepted or sent, advertised to the debugger in qSupported
+const MAX_PACKET_SIZE: u32 = 0x1000;
+
+/// A debugger connection that can be checked for input without blocking.
+pub trait Connection: Read + Write {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+enum Packet {
+    Data(String),
+    Interrupt,
+    Eof,
+}
+
+pub struct GdbStub<'a, S> {
+    machine: &'a mut Machine,
+    stream: S,
+    breakpoints: HashSet<u16>,
+    ack: bool,
+    // bytes read while polling for an interrupt, consumed before the stream
+    pending: VecDeque<u8>,
+}
+
+/// Waits for a debugger to connect on `addr` and serves it until it detaches.
+pub fn listen(machine: &mut Machine, addr: impl ToSocketAddrs) -> Result<(), String> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("gdb: bind: {}", e))?;
+    let (stream, _) = listener
+        .accept()
+        .map_err(|e| format!("gdb: accept: {}", e))?;
+    // packets are small and latency bound
+    stream
+        .set_nodelay(true)
+        .map_err(|e| format!("gdb: {}", e))?;
+    GdbStub::new(machine, stream).serve()
+}
+
+fn checksum(data: &str) -> u8 {
+    data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b))
+}
+
+fn parse_hex(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s, 16).map_err(|_| format!("invalid hex: {}", s))
+}
+
+fn decode_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    if !s.len().is_multiple_of(2) {
+        return Err(format!("odd length hex: {}", s));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| format!("invalid hex: {}", s)))
+        .collect()
+}
+
+fn encode_word(value: u16) -> String {
+    format!("{:02x}{:02x}", value & 0xff, value >> 8)
+}
+
+fn decode_word(s: &str) -> Result<u16, String> {
+    match decode_hex_bytes(s)?[..] {
+        [lo, hi] => Ok(u16::from_le_bytes([lo, hi])),
+        _ => Err(format!("invalid register value: {}", s)),
+    }
+}
+
+impl<'a, S: Connection> GdbStub<'a, S> {
+    pub fn new(machine: &'a mut Machine, stream: S) -> Self {
+        Self {
+            machine,
+            stream,
+            breakpoints: HashSet::new(),
+            ack: true,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Handles packets until the debugger detaches, kills the target or disconnects.
+    pub fn serve(mut self) -> Result<(), String> {
+        loop {
+            let packet = match self.read_packet()? {
+                Packet::Data(packet) => packet,
+                Packet::Interrupt => {
+                    self.send(&format!("S{:02x}", SIGINT))?;
+                    continue;
+                }
+                Packet::Eof => return Ok(()),
+            };
+            match packet.as_str() {
+                "D" => return self.send("OK"),
+                "k" => return Ok(()),
+                _ => {
+                    let response = self.handle(&packet).unwrap_or_else(|_| "E01".to_string());
+                    self.send(&response)?;
+                }
+            }
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>, String> {
+        if let Some(b) = self.pending.pop_front() {
+            return Ok(Some(b));
+        }
+        let mut buf = [0u8; 1];
+        match self.stream.read(&mut buf) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(buf[0])),
+            Err(e) => Err(format!("gdb: read: {}", e)),
+        }
+    }
+
+    /// Whether the debugger sent an interrupt, checked without waiting for input. Anything else
+    /// read is kept for the next packet.
+    fn interrupted(&mut self) -> Result<bool, String> {
+        self.stream
+            .set_nonblocking(true)
+            .map_err(|e| format!("gdb: {}", e))?;
+        let mut buf = [0u8; 1];
+        let res = self.stream.read(&mut buf);
+        self.stream
+            .set_nonblocking(false)
+            .map_err(|e| format!("gdb: {}", e))?;
+        match res {
+            Ok(1) if buf[0] == 0x03 => Ok(true),
+            Ok(1) => {
+                self.pending.push_back(buf[0]);
+                Ok(false)
+            }
+            // a disconnect is noticed by the next packet read
+            Ok(_) => Ok(false),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(format!("gdb: read: {}", e)),
+        }
+    }
+
+    fn read_packet(&mut self) -> Result<Packet, String> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(Packet::Eof),
+                Some(b'$') => break,
+                Some(0x03) => return Ok(Packet::Interrupt),
+                // acks and noise between packets
+                Some(_) => continue,
+            }
+        }
+
+        let mut data = Vec::new();
+        loop {
+            match self.read_byte()? {
+                None => return Ok(Packet::Eof),
+                Some(b'#') => break,
+                Some(b) => data.push(b),
+            }
+        }
+        let mut sum = [0u8; 2];
+        for b in sum.iter_mut() {
+            *b = self.read_byte()?.ok_or("gdb: truncated packet")?;
+        }
+
+        let data = String::from_utf8(data).map_err(|_| "gdb: packet is not utf8")?;
+        let valid = std::str::from_utf8(&sum)
+            .ok()
+            .and_then(|s| u8::from_str_radix(s, 16).ok())
+            == Some(checksum(&data));
+        if self.ack {
+            self.write(if valid { b"+" } else { b"-" })?;
+        }
+        if valid {
+            Ok(Packet::Data(data))
+        } else {
+            self.read_packet()
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.stream
+            .write_all(bytes)
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| format!("gdb: write: {}", e))
+    }
+
+    fn send(&mut self, data: &str) -> Result<(), String> {
+        let packet = format!("${}#{:02x}", data, checksum(data));
+        self.write(packet.as_bytes())
+    }
+
+    fn handle(&mut self, packet: &str) -> Result<String, String> {
+        if packet.is_empty() || !packet.is_char_boundary(1) {
+            return Ok(String::new());
+        }
+        let (cmd, args) = packet.split_at(1);
+        match cmd {
+            "?" => Ok(format!("S{:02x}", SIGTRAP)),
+            "g" => Ok(Register::ALL
+                .iter()
+                .map(|&r| encode_word(self.machine.get_register(r)))
+                .collect()),
+            "G" => {
+                if args.len() != Register::ALL.len() * 4 {
+                    return Err("invalid register block".to_string());
+                }
+                for (i, &r) in Register::ALL.iter().enumerate() {
+                    let value = decode_word(&args[i * 4..i * 4 + 4])?;
+                    self.machine.set_register(r, value);
+                }
+                Ok("OK".to_string())
+            }
+            "p" => {
+                let r = self.register(args)?;
+                Ok(encode_word(self.machine.get_register(r)))
+            }
+            "P" => {
+                let (index, value) = args.split_once('=').ok_or("invalid P packet")?;
+                let r = self.register(index)?;
+                self.machine.set_register(r, decode_word(value)?);
+                Ok("OK".to_string())
+            }
+            "m" => {
+                let (addr, len) = args.split_once(',').ok_or("invalid m packet")?;
+                let (addr, len) = (parse_hex(addr)?, parse_hex(len)?);
+                // each byte is sent as two hex digits
+                if len > MAX_PACKET_SIZE / 2 {
+                    return Err(format!("m packet too long: 0x{:X}", len));
+                }
+                let mut out = String::new();
+                for i in 0..len {
+                    // devices such as the console can't be read without side effects
+                    let b = self
+                        .machine
+                        .vm
+                        .memory
+                        .peek(addr + i)
+                        .ok_or_else(|| format!("can't read 0x{:X}", addr + i))?;
+                    out.push_str(&format!("{:02x}", b));
+                }
+                Ok(out)
+            }
+            "M" => {
+                let (range, data) = args.split_once(':').ok_or("invalid M packet")?;
+                let (addr, len) = range.split_once(',').ok_or("invalid M packet")?;
+                let (addr, len) = (parse_hex(addr)?, parse_hex(len)?);
+                let bytes = decode_hex_bytes(data)?;
+                if bytes.len() != len as usize {
+                    return Err("length mismatch".to_string());
+                }
+                self.machine
+                    .vm
+                    .memory
+                    .load_from_vec(&bytes, addr)
+                    .map_err(|e| e.to_string())?;
+                Ok("OK".to_string())
+            }
+            "Z" | "z" => {
+                let mut parts = args.split(',');
+                let kind = parts.next().ok_or("invalid breakpoint packet")?;
+                let addr = parse_hex(parts.next().ok_or("invalid breakpoint packet")?)?;
+                let len = parse_hex(parts.next().ok_or("invalid breakpoint packet")?)?;
+                let watch = match kind {
+                    "0" | "1" => None,
+                    "2" => Some(WatchKind::Write),
+                    "3" => Some(WatchKind::Read),
+                    "4" => Some(WatchKind::Access),
+                    _ => return Ok(String::new()),
+                };
+                match (cmd, watch) {
+                    ("Z", None) => {
+                        self.breakpoints.insert(addr as u16);
+                    }
+                    (_, None) => {
+                        self.breakpoints.remove(&(addr as u16));
+                    }
+                    ("Z", Some(kind)) => self.machine.add_watchpoint(addr, len, kind),
+                    (_, Some(_)) => {
+                        self.machine.remove_watchpoint(addr);
+                    }
+                }
+                Ok("OK".to_string())
+            }
+            "s" => {
+                self.resume_at(args)?;
+                Ok(match self.step() {
+                    Some(stop) => stop,
+                    None => format!("S{:02x}", SIGTRAP),
+                })
+            }
+            "c" => {
+                self.resume_at(args)?;
+                let mut steps = 0;
+                loop {
+                    if let Some(stop) = self.step() {
+                        return Ok(stop);
+                    }
+                    if self
+                        .breakpoints
+                        .contains(&self.machine.get_register(Register::PC))
+                    {
+                        return Ok(format!("S{:02x}", SIGTRAP));
+                    }
+                    steps += 1;
+                    if steps % POLL_INTERVAL == 0 && self.interrupted()? {
+                        return Ok(format!("S{:02x}", SIGINT));
+                    }
+                }
+            }
+            "H" => Ok("OK".to_string()),
+            "q" | "Q" => Ok(self.query(packet)),
+            _ => Ok(String::new()),
+        }
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            format!("PacketSize={:x};QStartNoAckMode+", MAX_PACKET_SIZE)
+        } else if packet == "QStartNoAckMode" {
+            self.ack = false;
+            "OK".to_string()
+        } else if packet == "qAttached" {
+            "1".to_string()
+        } else if packet == "qC" {
+            "QC1".to_string()
+        } else if packet == "qfThreadInfo" {
+            "m1".to_string()
+        } else if packet == "qsThreadInfo" {
+            "l".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    fn register(&self, index: &str) -> Result<Register, String> {
+        let index = parse_hex(index)?;
+        Register::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| format!("invalid register: {}", index))
+    }
+
+    fn resume_at(&mut self, addr: &str) -> Result<(), String> {
+        if !addr.is_empty() {
+            let addr = parse_hex(addr)?;
+            self.machine.set_register(Register::PC, addr as u16);
+        }
+        Ok(())
+    }
+
+    /// Executes one instruction, returning a stop reply when the program halts or faults.
+    fn step(&mut self) -> Option<String> {
+        if self.machine.is_halted() {
+            return Some(self.exit_reply());
+        }
+        match self.machine.step() {
+            Ok(()) if self.machine.is_halted() => Some(self.exit_reply()),
+            Ok(()) => self.machine.vm.last_watch_hits().first().map(|hit| {
+                let kind = match hit.kind {
+                    WatchKind::Read => "rwatch",
+                    _ => "watch",
+                };
+                format!("T{:02x}{}:{:x};", SIGTRAP, kind, hit.addr)
+            }),
+            Err(fault) => {
+                let signal = match fault {
+                    Fault::Trap(TrapCause::IllegalInstruction, _) => SIGILL,
+                    Fault::Trap(TrapCause::MemoryFault, _) => SIGSEGV,
+                    Fault::Trap(TrapCause::UnknownSignal, _) => SIGSYS,
+                    Fault::Trap(TrapCause::Interrupt, _) | Fault::Host(_) => SIGTRAP,
+                };
+                Some(format!("S{:02x}", signal))
+            }
+        }
+    }
+
+    fn exit_reply(&self) -> String {
+        format!("W{:02x}", self.machine.exit_code().unwrap_or(0) & 0xff)
+    }
+}
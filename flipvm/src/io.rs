@@ -1,3 +1,6 @@
+use std::cell::{Cell, RefCell};
+use std::io::{ErrorKind, Read, Write};
+
 use crate::memory::MemoryError;
 use crate::Addressable;
 
@@ -29,4 +32,221 @@ impl Addressable for MappedMemoryBuffer {
     fn zero_all(&mut self) -> Result<(), MemoryError> {
         self.zero(0, self.0.len() as u32)
     }
+
+    fn poke(&mut self, addr: u32, value: u8) -> bool {
+        self.write(addr, value).is_ok()
+    }
+}
+
+/// A programmable countdown timer that raises an interrupt after a number of executed
+/// instructions.
+///
+/// Registers (16 bit, little endian):
+/// - `0x0` RELOAD: the count loaded when the timer is armed
+/// - `0x2` COUNTER: the instructions left until the timer fires
+/// - `0x4` CONTROL: bit 0 enables the timer, bit 1 makes it periodic
+/// - `0x6` STATUS: bit 0 is set when the timer fires, any write clears it
+#[derive(Default)]
+pub struct Timer {
+    reload: u16,
+    counter: u16,
+    control: u16,
+    status: u16,
+}
+
+impl Timer {
+    pub const RELOAD: u32 = 0x0;
+    pub const COUNTER: u32 = 0x2;
+    pub const CONTROL: u32 = 0x4;
+    pub const STATUS: u32 = 0x6;
+    pub const SIZE: u32 = 0x8;
+
+    pub const ENABLE: u16 = 0x1;
+    pub const PERIODIC: u16 = 0x2;
+    pub const FIRED: u16 = 0x1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, addr: u32) -> Result<u16, MemoryError> {
+        match addr & !1 {
+            Self::RELOAD => Ok(self.reload),
+            Self::COUNTER => Ok(self.counter),
+            Self::CONTROL => Ok(self.control),
+            Self::STATUS => Ok(self.status),
+            _ => Err(MemoryError::OutOfBounds(addr)),
+        }
+    }
+
+    fn set_register(&mut self, addr: u32, value: u16) -> Result<(), MemoryError> {
+        match addr & !1 {
+            Self::RELOAD => self.reload = value,
+            Self::COUNTER => self.counter = value,
+            Self::CONTROL => {
+                if value & Self::ENABLE != 0 && self.control & Self::ENABLE == 0 {
+                    self.counter = self.reload;
+                }
+                self.control = value;
+            }
+            Self::STATUS => self.status = 0,
+            _ => return Err(MemoryError::OutOfBounds(addr)),
+        }
+        Ok(())
+    }
+}
+
+impl Addressable for Timer {
+    fn read(&self, addr: u32) -> Result<u8, MemoryError> {
+        let value = self.register(addr)?;
+        if addr & 1 == 0 {
+            Ok((value & 0xff) as u8)
+        } else {
+            Ok((value >> 8) as u8)
+        }
+    }
+
+    fn write(&mut self, addr: u32, value: u8) -> Result<(), MemoryError> {
+        let current = self.register(addr)?;
+        let next = if addr & 1 == 0 {
+            (current & 0xff00) | value as u16
+        } else {
+            (current & 0x00ff) | ((value as u16) << 8)
+        };
+        self.set_register(addr, next)
+    }
+
+    fn zero_all(&mut self) -> Result<(), MemoryError> {
+        *self = Self::default();
+        Ok(())
+    }
+
+    fn tick(&mut self) -> bool {
+        if self.control & Self::ENABLE == 0 {
+            return false;
+        }
+        self.counter = self.counter.saturating_sub(1);
+        if self.counter > 0 {
+            return false;
+        }
+
+        self.status |= Self::FIRED;
+        if self.control & Self::PERIODIC != 0 {
+            self.counter = self.reload;
+        } else {
+            self.control &= !Self::ENABLE;
+        }
+        true
+    }
+}
+
+/// A character console backed by a host reader and writer.
+///
+/// Registers (16 bit, little endian):
+/// - `0x0` DATA: reading consumes the next input byte (0 at end of input), writing outputs the
+///   low byte
+/// - `0x2` STATUS: bit 0 is set when input is available, bit 1 at end of input and bit 2 when
+///   output can be written
+pub struct Console<R, W> {
+    input: RefCell<R>,
+    output: W,
+    // a byte read ahead from input to answer STATUS without consuming it
+    pending: Cell<Option<u8>>,
+    eof: Cell<bool>,
+}
+
+impl<R: Read, W: Write> Console<R, W> {
+    pub const DATA: u32 = 0x0;
+    pub const STATUS: u32 = 0x2;
+    pub const SIZE: u32 = 0x4;
+
+    pub const INPUT_READY: u16 = 0x1;
+    pub const INPUT_EOF: u16 = 0x2;
+    pub const OUTPUT_READY: u16 = 0x4;
+
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input: RefCell::new(input),
+            output,
+            pending: Cell::new(None),
+            eof: Cell::new(false),
+        }
+    }
+
+    fn fill(&self, addr: u32) -> Result<(), MemoryError> {
+        if self.pending.get().is_some() || self.eof.get() {
+            return Ok(());
+        }
+        let mut buf = [0u8; 1];
+        loop {
+            match self.input.borrow_mut().read(&mut buf) {
+                Ok(0) => self.eof.set(true),
+                Ok(_) => self.pending.set(Some(buf[0])),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(MemoryError::Device(addr, e.to_string())),
+            }
+            return Ok(());
+        }
+    }
+
+    fn status(&self, addr: u32) -> Result<u16, MemoryError> {
+        self.fill(addr)?;
+        let mut status = Self::OUTPUT_READY;
+        if self.pending.get().is_some() {
+            status |= Self::INPUT_READY;
+        }
+        if self.eof.get() {
+            status |= Self::INPUT_EOF;
+        }
+        Ok(status)
+    }
+}
+
+impl<R: Read, W: Write> Addressable for Console<R, W> {
+    fn read(&self, addr: u32) -> Result<u8, MemoryError> {
+        match addr {
+            Self::DATA => {
+                self.fill(addr)?;
+                Ok(self.pending.take().unwrap_or(0))
+            }
+            Self::STATUS => Ok(self.status(addr)? as u8),
+            x if x < Self::SIZE => Ok(0),
+            _ => Err(MemoryError::OutOfBounds(addr)),
+        }
+    }
+
+    // only reports input already read ahead, reads would consume or wait for it
+    fn peek(&self, addr: u32) -> Option<u8> {
+        match addr {
+            Self::DATA => self.pending.get(),
+            Self::STATUS => {
+                let mut status = Self::OUTPUT_READY;
+                if self.pending.get().is_some() {
+                    status |= Self::INPUT_READY;
+                }
+                if self.eof.get() {
+                    status |= Self::INPUT_EOF;
+                }
+                Some(status as u8)
+            }
+            x if x < Self::SIZE => Some(0),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, addr: u32, value: u8) -> Result<(), MemoryError> {
+        match addr {
+            Self::DATA => self
+                .output
+                .write_all(&[value])
+                .and_then(|_| self.output.flush())
+                .map_err(|e| MemoryError::Device(addr, e.to_string())),
+            x if x < Self::SIZE => Ok(()),
+            _ => Err(MemoryError::OutOfBounds(addr)),
+        }
+    }
+
+    fn zero_all(&mut self) -> Result<(), MemoryError> {
+        Ok(())
+    }
 }
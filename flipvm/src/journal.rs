@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+
+/// The machine state before one step, enough to undo it.
+pub(crate) struct JournalEntry {
+    pub registers: [u16; 8],
+    pub flags: u16,
+    pub halt: bool,
+    pub exit_code: Option<u16>,
+    pub interrupt_pending: bool,
+    /// Previous values of the bytes written by the step, in write order.
+    pub memory: Vec<(u32, u8)>,
+}
+
+impl JournalEntry {
+    pub fn wrote(&self, addr: u32) -> bool {
+        self.memory.iter().any(|&(a, _)| a == addr)
+    }
+}
+
+/// A ring buffer of the most recent steps, the oldest entry is dropped once `capacity` is reached.
+pub(crate) struct Journal {
+    entries: VecDeque<JournalEntry>,
+    capacity: usize,
+}
+
+impl Journal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity.min(1024)),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, entry: JournalEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn pop(&mut self) -> Option<JournalEntry> {
+        self.entries.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Counts the steps back to the most recent one that wrote `addr`, including that step.
+    pub fn steps_to_write(&self, addr: u32) -> Option<usize> {
+        self.entries
+            .iter()
+            .rev()
+            .position(|e| e.wrote(addr))
+            .map(|i| i + 1)
+    }
+}
@@ -0,0 +1,36 @@
+//! The memory map programs run with: RAM after the program image, a console on stdin/stdout and
+//! a timer.
+//!
+//! Shared by `vm` and the tools that run programs the same way, so a program behaves alike under
+//! each of them.
+
+use std::io::{stdin, stdout, Stdin, Stdout};
+
+use crate::{Console, LinearMemory, Machine, Register, Timer};
+
+pub type StdConsole = Console<Stdin, Stdout>;
+
+pub const RAM_START: usize = 0x1000;
+pub const RAM_SIZE: usize = 0x8000;
+pub const CONSOLE_ADDR: usize = 0xf000;
+pub const TIMER_ADDR: usize = 0xf010;
+/// The trap vector table, in the last 16 bytes of RAM. It starts zeroed, so no handlers are
+/// installed until the program stores their addresses at `TRAP_VECTOR + 2 * cause`.
+pub const TRAP_VECTOR: u16 = (RAM_START + RAM_SIZE - 0x10) as u16;
+/// Initial SP, the stack grows up from the start of RAM.
+pub const STACK_START: u16 = RAM_START as u16;
+
+/// Maps RAM, the console and the timer into `machine`, installs the trap vector table and points
+/// SP at the stack, the program is loaded separately.
+pub fn map_std(machine: &mut Machine) -> Result<(), String> {
+    machine.map(RAM_START, RAM_SIZE, Box::new(LinearMemory::new(RAM_SIZE)))?;
+    machine.map(
+        CONSOLE_ADDR,
+        StdConsole::SIZE as usize,
+        Box::new(StdConsole::new(stdin(), stdout())),
+    )?;
+    machine.map(TIMER_ADDR, Timer::SIZE as usize, Box::new(Timer::new()))?;
+    machine.set_trap_vector(TRAP_VECTOR);
+    machine.set_register(Register::SP, STACK_START);
+    Ok(())
+}
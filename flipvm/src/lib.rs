@@ -1,11 +1,30 @@
+mod coverage;
+mod debug_info;
+pub mod exe;
+mod format;
+pub mod gdb;
 mod io;
+mod journal;
+pub mod layout;
 mod memory;
+pub mod obj;
 pub mod op;
 pub mod pp;
+mod profile;
 mod register;
+mod symbols;
+pub mod syscall;
+mod trace;
+mod trap;
 mod vm;
 
-pub use io::MappedMemoryBuffer;
-pub use memory::{Addressable, LinearMemory};
+pub use coverage::Coverage;
+pub use debug_info::{DebugInfo, FunctionInfo, LocalSlot, SourceLocation};
+pub use io::{Console, MappedMemoryBuffer, Timer};
+pub use memory::{Addressable, LinearMemory, WatchHit, WatchKind, Watchpoint};
+pub use profile::Profiler;
 pub use register::{Flag, Register};
-pub use vm::{Machine, VM};
+pub use symbols::SymbolMap;
+pub use trace::{JsonTracer, RegisterDelta, TraceStep, Tracer};
+pub use trap::TrapCause;
+pub use vm::{Fault, Machine, MAX_WATCH_HITS, VM};
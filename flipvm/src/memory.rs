@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::fmt;
 
 #[derive(Debug)]
@@ -6,6 +7,7 @@ pub enum MemoryError {
     AddressTranslation(u32, Box<MemoryError>),
     NoMap(u32),
     InvalidMap(u32, usize),
+    Device(u32, String),
 }
 
 use MemoryError::*;
@@ -16,6 +18,7 @@ impl fmt::Display for MemoryError {
             NoMap(a) => write!(f, "no mapping: {:X}", a),
             InvalidMap(a, i) => write!(f, "invalid mapping index: {:X}, {}", a, i),
             AddressTranslation(a, e) => write!(f, "translation @{:X}: {}", a, e),
+            Device(a, e) => write!(f, "device error @{:X}: {}", a, e),
         }
     }
 }
@@ -25,6 +28,30 @@ pub trait Addressable {
     fn write(&mut self, addr: u32, value: u8) -> Result<(), MemoryError>;
     fn zero_all(&mut self) -> Result<(), MemoryError>;
 
+    /// Advances the device by one executed instruction. Returns true when the device raises an
+    /// interrupt.
+    fn tick(&mut self) -> bool {
+        false
+    }
+
+    /// Reads a byte without side effects, `None` when the value can't be observed that way.
+    fn peek(&self, addr: u32) -> Option<u8> {
+        self.read(addr).ok()
+    }
+
+    /// Writes a byte without side effects, returning false when the device can't be written that
+    /// way.
+    fn poke(&mut self, _addr: u32, _value: u8) -> bool {
+        false
+    }
+
+    /// Reads a word without side effects, see `peek`.
+    fn peek2(&self, addr: u32) -> Option<u16> {
+        let x0 = self.peek(addr)?;
+        let x1 = self.peek(addr + 1)?;
+        Some((x0 as u16) | ((x1 as u16) << 8))
+    }
+
     fn read2(&self, addr: u32) -> Result<u16, MemoryError> {
         let x0 = self.read(addr)?;
         let x1 = self.read(addr + 1)?;
@@ -61,14 +88,74 @@ pub trait Addressable {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    fn matches(self, access: WatchKind) -> bool {
+        self == WatchKind::Access || self == access
+    }
+}
+
+impl fmt::Display for WatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WatchKind::Read => write!(f, "read"),
+            WatchKind::Write => write!(f, "write"),
+            WatchKind::Access => write!(f, "access"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u32,
+    pub len: u32,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    fn contains(&self, addr: u32) -> bool {
+        addr >= self.start && addr - self.start < self.len
+    }
+}
+
+/// A watched byte access. `kind` is `Read` or `Write`, `value` is the byte read or written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub pc: u16,
+    pub addr: u32,
+    pub kind: WatchKind,
+    pub value: u8,
+}
+
 #[derive(Default)]
 pub struct MemoryMapper {
     mapped: Vec<(usize, usize, Box<dyn Addressable>)>,
+    // previous values of written bytes, while recording
+    recorded: Option<Vec<(u32, u8)>>,
+    watchpoints: Vec<Watchpoint>,
+    // watched accesses as (addr, kind, value), reads record through a shared reference
+    watch_hits: RefCell<Vec<(u32, WatchKind, u8)>>,
 }
 
 impl MemoryMapper {
     pub fn new() -> MemoryMapper {
-        MemoryMapper { mapped: Vec::new() }
+        MemoryMapper::default()
+    }
+
+    /// Starts recording the previous value of every written byte.
+    pub fn begin_recording(&mut self) {
+        self.recorded = Some(Vec::new());
+    }
+
+    /// Stops recording, returning the addresses and previous values in write order.
+    pub fn end_recording(&mut self) -> Vec<(u32, u8)> {
+        self.recorded.take().unwrap_or_default()
     }
 
     pub fn map(
@@ -81,18 +168,45 @@ impl MemoryMapper {
         Ok(())
     }
 
-    pub fn lookup_mapping(&self, addr: u32) -> Option<usize> {
-        self.mapped
+    /// Watches `len` bytes from `start` for accesses of `kind`, in every mapped region.
+    pub fn add_watchpoint(&mut self, start: u32, len: u32, kind: WatchKind) {
+        self.watchpoints.push(Watchpoint { start, len, kind });
+    }
+
+    /// Removes the watchpoints starting at `start`, returning true if any were removed.
+    pub fn remove_watchpoint(&mut self, start: u32) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|w| w.start != start);
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Drains the watched accesses made since the last call.
+    pub fn take_watch_hits(&mut self) -> Vec<(u32, WatchKind, u8)> {
+        std::mem::take(self.watch_hits.get_mut())
+    }
+
+    fn watch(&self, addr: u32, access: WatchKind, value: u8) {
+        if self
+            .watchpoints
             .iter()
-            .enumerate()
-            .filter(|&(_, &(start, _, _))| start <= addr as usize)
-            .max_by_key(|&(_, &(start, _, _))| start)
-            .map(|(index, _)| index)
+            .any(|w| w.kind.matches(access) && w.contains(addr))
+        {
+            self.watch_hits.borrow_mut().push((addr, access, value));
+        }
     }
-}
 
-impl Addressable for MemoryMapper {
-    fn read(&self, addr: u32) -> Result<u8, MemoryError> {
+    /// Reads an instruction word, bypassing watchpoints.
+    pub fn fetch(&self, addr: u32) -> Result<u16, MemoryError> {
+        let x0 = self.read_unwatched(addr)?;
+        let x1 = self.read_unwatched(addr + 1)?;
+        Ok((x0 as u16) | ((x1 as u16) << 8))
+    }
+
+    fn read_unwatched(&self, addr: u32) -> Result<u8, MemoryError> {
         let index = self.lookup_mapping(addr).ok_or(NoMap(addr))?;
         let (start, size, a) = &self.mapped[index];
         let addr_local = addr - (*start as u32);
@@ -103,7 +217,38 @@ impl Addressable for MemoryMapper {
         }
     }
 
+    /// The mapping containing `addr` with the highest start, so a mapping placed over part of
+    /// another shadows only that part. Falls back to the nearest mapping below `addr`, which
+    /// reports it out of bounds.
+    pub fn lookup_mapping(&self, addr: u32) -> Option<usize> {
+        let addr = addr as usize;
+        self.mapped
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(start, _, _))| start <= addr)
+            .max_by_key(|&(_, &(start, size, _))| (addr - start < size, start))
+            .map(|(index, _)| index)
+    }
+}
+
+impl Addressable for MemoryMapper {
+    fn read(&self, addr: u32) -> Result<u8, MemoryError> {
+        let value = self.read_unwatched(addr)?;
+        if !self.watchpoints.is_empty() {
+            self.watch(addr, WatchKind::Read, value);
+        }
+        Ok(value)
+    }
+
     fn write(&mut self, addr: u32, value: u8) -> Result<(), MemoryError> {
+        if !self.watchpoints.is_empty() {
+            self.watch(addr, WatchKind::Write, value);
+        }
+        if self.recorded.is_some() {
+            if let Some(old) = self.peek(addr) {
+                self.recorded.get_or_insert_with(Vec::new).push((addr, old));
+            }
+        }
         match self.lookup_mapping(addr) {
             Some(index) => {
                 let (start, size, ref mut a) = &mut self.mapped[index];
@@ -122,6 +267,32 @@ impl Addressable for MemoryMapper {
         // TODO: Change
         Ok(())
     }
+
+    fn peek(&self, addr: u32) -> Option<u8> {
+        let (start, size, a) = &self.mapped[self.lookup_mapping(addr)?];
+        let addr_local = addr - (*start as u32);
+        if addr_local < (*size as u32) {
+            a.peek(addr_local)
+        } else {
+            None
+        }
+    }
+
+    fn poke(&mut self, addr: u32, value: u8) -> bool {
+        let index = match self.lookup_mapping(addr) {
+            Some(index) => index,
+            None => return false,
+        };
+        let (start, size, ref mut a) = &mut self.mapped[index];
+        let addr_local = addr - (*start as u32);
+        addr_local < (*size as u32) && a.poke(addr_local, value)
+    }
+
+    fn tick(&mut self) -> bool {
+        self.mapped
+            .iter_mut()
+            .fold(false, |raised, (_, _, a)| a.tick() || raised)
+    }
 }
 
 pub struct LinearMemory {
@@ -159,4 +330,8 @@ impl Addressable for LinearMemory {
     fn zero_all(&mut self) -> Result<(), MemoryError> {
         self.zero(0, self.size as u32)
     }
+
+    fn poke(&mut self, addr: u32, value: u8) -> bool {
+        self.write(addr, value).is_ok()
+    }
 }
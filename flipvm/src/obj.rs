@@ -0,0 +1,456 @@
+//! Relocatable object files and the linker that combines them into an `Executable`.
+//!
+//! All values are little endian:
+//!
+//! ```text
+//! magic             4  "FLPO"
+//! version           u16
+//! code_size         u32
+//! data_size         u32
+//! bss_size          u32
+//! symbol_count      u16
+//! relocation_count  u16
+//! code              code_size bytes
+//! data              data_size bytes
+//! symbols           symbol_count * (section u16, offset u32, global u8, name length u8, name)
+//! relocations       relocation_count * (section u16, offset u32, kind u8, name length u8, name)
+//! ```
+//!
+//! Symbol and relocation offsets are relative to the start of their section. A relocation names
+//! the symbol whose final address is patched into the instruction or word at its offset. Names
+//! are looked up in the defining object first and then in the global symbols of every object, a
+//! relocation naming neither is an undefined reference.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::exe::{Executable, FormatError, SectionKind};
+use crate::format::{write_name, Reader};
+use crate::op::{Instruction, Literal10Bit, Literal12Bit, Literal7Bit};
+use crate::SymbolMap;
+
+pub const MAGIC: [u8; 4] = *b"FLPO";
+pub const VERSION: u16 = 1;
+
+const HEADER_SIZE: usize = 22;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RelocationKind {
+    /// The absolute address, as the literal of an `Imm`.
+    Imm12 = 1,
+    /// The absolute address, as the literal of an `AddImm`.
+    AddImm7 = 2,
+    /// The signed word offset from the instruction, as the literal of a branch.
+    Branch10 = 3,
+    /// The absolute address, as a data word.
+    Word16 = 4,
+}
+
+impl RelocationKind {
+    /// The relocation that patches a reference in `ins`, relative to its address if `relative`.
+    pub fn for_instruction(ins: &Instruction, relative: bool) -> Option<Self> {
+        match (ins, relative) {
+            (Instruction::Imm(..), false) => Some(RelocationKind::Imm12),
+            (Instruction::AddImm(..), false) => Some(RelocationKind::AddImm7),
+            (
+                Instruction::Branch(_) | Instruction::BranchIf(_) | Instruction::BranchIfNot(_),
+                true,
+            ) => Some(RelocationKind::Branch10),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u8> for RelocationKind {
+    type Error = FormatError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(RelocationKind::Imm12),
+            2 => Ok(RelocationKind::AddImm7),
+            3 => Ok(RelocationKind::Branch10),
+            4 => Ok(RelocationKind::Word16),
+            x => Err(FormatError::InvalidRelocationKind(x)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub section: SectionKind,
+    pub offset: u32,
+    // visible to other objects
+    pub global: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    pub section: SectionKind,
+    pub offset: u32,
+    pub kind: RelocationKind,
+    pub symbol: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub code: Vec<u8>,
+    pub data: Vec<u8>,
+    pub bss: u32,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkError {
+    DuplicateSymbol(String),
+    UndefinedSymbol(String),
+    // symbol, address of the relocation, reason
+    Relocation(String, u32, String),
+    TooLarge(u32),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol(s) => write!(f, "duplicate symbol: {}", s),
+            LinkError::UndefinedSymbol(s) => write!(f, "undefined reference to {}", s),
+            LinkError::Relocation(s, addr, e) => {
+                write!(f, "relocation of {} at 0x{:04x}: {}", s, addr, e)
+            }
+            LinkError::TooLarge(size) => write!(f, "program too large: {} bytes", size),
+        }
+    }
+}
+
+impl From<LinkError> for String {
+    fn from(value: LinkError) -> Self {
+        format!("{}", value)
+    }
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An object whose code section holds `program`.
+    pub fn from_instructions(program: &[Instruction]) -> Self {
+        Self {
+            code: program
+                .iter()
+                .flat_map(|i| i.encode_u16().to_le_bytes())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if `bytes` starts with the object magic number.
+    pub fn is_object(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    /// Defines `name` at `offset` in `section`, visible to other objects if `global`.
+    pub fn define(&mut self, name: &str, section: SectionKind, offset: u32, global: bool) {
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            section,
+            offset,
+            global,
+        });
+    }
+
+    /// Patches the reference at `offset` in `section` with the address of `symbol` when linked.
+    pub fn relocate(
+        &mut self,
+        section: SectionKind,
+        offset: u32,
+        kind: RelocationKind,
+        symbol: &str,
+    ) {
+        self.relocations.push(Relocation {
+            section,
+            offset,
+            kind,
+            symbol: symbol.to_string(),
+        });
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    /// The names referenced by relocations but not defined in this object, in first use order.
+    pub fn undefined(&self) -> Vec<&str> {
+        let mut out: Vec<&str> = Vec::new();
+        for reloc in &self.relocations {
+            let name = reloc.symbol.as_str();
+            if self.symbol(name).is_none() && !out.contains(&name) {
+                out.push(name);
+            }
+        }
+        out
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FormatError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.code.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.bss.to_le_bytes());
+        out.extend_from_slice(&(self.symbols.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(self.relocations.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.code);
+        out.extend_from_slice(&self.data);
+        for symbol in &self.symbols {
+            out.extend_from_slice(&(symbol.section as u16).to_le_bytes());
+            out.extend_from_slice(&symbol.offset.to_le_bytes());
+            out.push(symbol.global as u8);
+            write_name(&mut out, &symbol.name)?;
+        }
+        for reloc in &self.relocations {
+            out.extend_from_slice(&(reloc.section as u16).to_le_bytes());
+            out.extend_from_slice(&reloc.offset.to_le_bytes());
+            out.push(reloc.kind as u8);
+            write_name(&mut out, &reloc.symbol)?;
+        }
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        if !Self::is_object(bytes) {
+            return Err(FormatError::BadMagic);
+        }
+        let mut r = Reader::new(bytes, MAGIC.len());
+        let version = r.u16()?;
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let code_size = r.u32()? as usize;
+        let data_size = r.u32()? as usize;
+        let mut out = Self {
+            bss: r.u32()?,
+            ..Default::default()
+        };
+        let symbol_count = r.u16()? as usize;
+        let relocation_count = r.u16()? as usize;
+        debug_assert_eq!(r.pos, HEADER_SIZE);
+
+        out.code = r.take(code_size)?.to_vec();
+        out.data = r.take(data_size)?.to_vec();
+        for _ in 0..symbol_count {
+            let section = SectionKind::try_from(r.u16()?)?;
+            let offset = r.u32()?;
+            let global = r.u8()? != 0;
+            out.define(&r.name()?, section, offset, global);
+        }
+        for _ in 0..relocation_count {
+            let section = SectionKind::try_from(r.u16()?)?;
+            let offset = r.u32()?;
+            let kind = RelocationKind::try_from(r.u8()?)?;
+            out.relocate(section, offset, kind, &r.name()?);
+        }
+        Ok(out)
+    }
+}
+
+fn align2(n: u32) -> u32 {
+    n.saturating_add(1) & !1
+}
+
+/// Where each object's sections start, relative to the load address.
+struct Layout {
+    bases: Vec<[u32; 3]>,
+    code_size: u32,
+    data_size: u32,
+    bss_size: u32,
+}
+
+impl Layout {
+    /// Places every code section, then every data section, then every bss section. Offsets
+    /// saturate, `link` rejects layouts past the address space.
+    fn new(objects: &[Object]) -> Self {
+        let mut bases = vec![[0; 3]; objects.len()];
+        let mut offset: u32 = 0;
+        for (i, obj) in objects.iter().enumerate() {
+            bases[i][0] = offset;
+            offset = align2(offset.saturating_add(obj.code.len() as u32));
+        }
+        let code_size = offset;
+        for (i, obj) in objects.iter().enumerate() {
+            bases[i][1] = offset;
+            offset = align2(offset.saturating_add(obj.data.len() as u32));
+        }
+        let data_size = offset - code_size;
+        for (i, obj) in objects.iter().enumerate() {
+            bases[i][2] = offset;
+            offset = align2(offset.saturating_add(obj.bss));
+        }
+        Self {
+            bases,
+            code_size,
+            data_size,
+            bss_size: offset - code_size - data_size,
+        }
+    }
+
+    fn base(&self, object: usize, section: SectionKind) -> u32 {
+        let index = match section {
+            SectionKind::Code => 0,
+            SectionKind::Data => 1,
+            SectionKind::Bss => 2,
+        };
+        self.bases[object][index]
+    }
+
+    fn end(&self) -> u32 {
+        self.code_size + self.data_size + self.bss_size
+    }
+}
+
+/// Patches the instruction or word at `at` in `bytes` to refer to `target`. `address` is the
+/// final address of the patched location.
+fn apply(
+    bytes: &mut [u8],
+    at: usize,
+    kind: RelocationKind,
+    address: u32,
+    target: u32,
+) -> Result<(), String> {
+    let word = bytes
+        .get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or("offset out of bounds")?;
+    let patched = match kind {
+        RelocationKind::Branch10 => {
+            let lit = Literal10Bit::from_word_offset((target as i64 - address as i64) / 2)?;
+            match Instruction::try_from(word)? {
+                Instruction::Branch(_) => Instruction::Branch(lit),
+                Instruction::BranchIf(_) => Instruction::BranchIf(lit),
+                Instruction::BranchIfNot(_) => Instruction::BranchIfNot(lit),
+                ins => return Err(format!("not a branch: {}", ins)),
+            }
+            .encode_u16()
+        }
+        _ => {
+            let value = u16::try_from(target).map_err(|_| "address out of range")?;
+            match (kind, Instruction::try_from(word)?) {
+                (RelocationKind::Word16, _) => value,
+                (RelocationKind::Imm12, Instruction::Imm(r, _)) => {
+                    Instruction::Imm(r, Literal12Bit::new_checked(value)?).encode_u16()
+                }
+                (RelocationKind::AddImm7, Instruction::AddImm(r, _)) => {
+                    let value = u8::try_from(value).map_err(|_| "address out of range")?;
+                    Instruction::AddImm(r, Literal7Bit::new_checked(value)?).encode_u16()
+                }
+                (_, ins) => return Err(format!("{:?} does not apply to {}", kind, ins)),
+            }
+        }
+    };
+    bytes[at..at + 2].copy_from_slice(&patched.to_le_bytes());
+    Ok(())
+}
+
+/// Links `objects` into an executable loaded at `load_address`.
+///
+/// Sections are placed in object order, so without an `entry` symbol the program starts at the
+/// first instruction of the first object.
+pub fn link(
+    objects: &[Object],
+    load_address: u32,
+    entry: Option<&str>,
+) -> Result<Executable, LinkError> {
+    let layout = Layout::new(objects);
+    if load_address
+        .checked_add(layout.end())
+        .is_none_or(|end| end > u16::MAX as u32 + 1)
+    {
+        return Err(LinkError::TooLarge(layout.end()));
+    }
+
+    let mut globals: HashMap<&str, u32> = HashMap::new();
+    let mut locals: Vec<HashMap<&str, u32>> = Vec::new();
+    for (i, obj) in objects.iter().enumerate() {
+        let mut defined = HashMap::new();
+        for symbol in &obj.symbols {
+            // out of range addresses are rejected by the relocations that use them
+            let address =
+                (load_address + layout.base(i, symbol.section)).saturating_add(symbol.offset);
+            if defined.insert(symbol.name.as_str(), address).is_some() {
+                return Err(LinkError::DuplicateSymbol(symbol.name.clone()));
+            }
+            if symbol.global && globals.insert(symbol.name.as_str(), address).is_some() {
+                return Err(LinkError::DuplicateSymbol(symbol.name.clone()));
+            }
+        }
+        locals.push(defined);
+    }
+    let lookup = |object: usize, name: &str| {
+        locals[object]
+            .get(name)
+            .or_else(|| globals.get(name))
+            .copied()
+            .ok_or_else(|| LinkError::UndefinedSymbol(name.to_string()))
+    };
+
+    let mut code = vec![0; layout.code_size as usize];
+    let mut data = vec![0; layout.data_size as usize];
+    for (i, obj) in objects.iter().enumerate() {
+        let base = layout.base(i, SectionKind::Code) as usize;
+        code[base..base + obj.code.len()].copy_from_slice(&obj.code);
+        let base = (layout.base(i, SectionKind::Data) - layout.code_size) as usize;
+        data[base..base + obj.data.len()].copy_from_slice(&obj.data);
+    }
+
+    for (i, obj) in objects.iter().enumerate() {
+        for reloc in &obj.relocations {
+            let target = lookup(i, &reloc.symbol)?;
+            let offset = layout.base(i, reloc.section).saturating_add(reloc.offset);
+            let address = load_address.saturating_add(offset);
+            let (bytes, at) = match reloc.section {
+                SectionKind::Code => (&mut code, offset),
+                SectionKind::Data => (&mut data, offset - layout.code_size),
+                SectionKind::Bss => {
+                    return Err(LinkError::Relocation(
+                        reloc.symbol.clone(),
+                        address,
+                        "relocation in bss".to_string(),
+                    ))
+                }
+            };
+            apply(bytes, at as usize, reloc.kind, address, target)
+                .map_err(|e| LinkError::Relocation(reloc.symbol.clone(), address, e))?;
+        }
+    }
+
+    let entry = match entry {
+        Some(name) => globals
+            .get(name)
+            .copied()
+            .ok_or_else(|| LinkError::UndefinedSymbol(name.to_string()))?,
+        None => load_address,
+    };
+    let mut exe = Executable::new(entry as u16, load_address);
+    if !code.is_empty() {
+        exe.add_section(SectionKind::Code, 0, code);
+    }
+    if !data.is_empty() {
+        exe.add_section(SectionKind::Data, layout.code_size, data);
+    }
+    if layout.bss_size > 0 {
+        exe.add_bss(layout.code_size + layout.data_size, layout.bss_size);
+    }
+
+    let mut symbols = SymbolMap::new();
+    for (i, obj) in objects.iter().enumerate() {
+        for symbol in &obj.symbols {
+            symbols.insert(&symbol.name, locals[i][symbol.name.as_str()] as u16);
+        }
+    }
+    if !symbols.is_empty() {
+        exe.symbols = Some(symbols);
+    }
+    Ok(exe)
+}
@@ -58,7 +58,7 @@ impl Literal7Bit {
         if sgn == 0 {
             (self.value & 0x7f) as i8
         } else {
-            unsafe { std::mem::transmute(self.value | 0x80) }
+            (self.value | 0x80) as i8
         }
     }
 }
@@ -93,6 +93,17 @@ impl Literal10Bit {
         }
     }
 
+    /// A branch's signed word offset, which must be in [-512, 511].
+    pub fn from_word_offset(offset: i64) -> Result<Self, String> {
+        match i16::try_from(offset) {
+            Ok(offset) if (-0x200..0x200).contains(&offset) => Self::from_signed(offset),
+            _ => Err(format!(
+                "branch offset out of range [-512, 511]: {}",
+                offset
+            )),
+        }
+    }
+
     pub fn from_signed(value: i16) -> Result<Self, String> {
         if value >= 0 {
             Self::new_checked(value.unsigned_abs())
@@ -108,7 +119,7 @@ impl Literal10Bit {
         if sgn == 0 {
             (self.value & 0x3ff) as i16
         } else {
-            unsafe { std::mem::transmute(self.value | 0xfc00) }
+            (self.value | 0xfc00) as i16
         }
     }
 }
@@ -158,7 +169,7 @@ impl Literal12Bit {
         if sgn == 0 {
             (self.value & 0xfff) as i16
         } else {
-            unsafe { std::mem::transmute(self.value | 0xf000) }
+            (self.value | 0xf000) as i16
         }
     }
 }
@@ -181,6 +192,13 @@ pub enum TestOp {
     BothZero,
     EitherNonZero,
     BothNonZero,
+    ZeroSet,
+    ZeroClear,
+    CarrySet,
+    CarryClear,
+    OverflowSet,
+    OverflowClear,
+    NegativeSet,
 }
 
 impl TryFrom<u16> for TestOp {
@@ -196,6 +214,13 @@ impl TryFrom<u16> for TestOp {
             x if x == TestOp::BothZero as u16 => Ok(TestOp::BothZero),
             x if x == TestOp::BothNonZero as u16 => Ok(TestOp::BothNonZero),
             x if x == TestOp::EitherNonZero as u16 => Ok(TestOp::EitherNonZero),
+            x if x == TestOp::ZeroSet as u16 => Ok(TestOp::ZeroSet),
+            x if x == TestOp::ZeroClear as u16 => Ok(TestOp::ZeroClear),
+            x if x == TestOp::CarrySet as u16 => Ok(TestOp::CarrySet),
+            x if x == TestOp::CarryClear as u16 => Ok(TestOp::CarryClear),
+            x if x == TestOp::OverflowSet as u16 => Ok(TestOp::OverflowSet),
+            x if x == TestOp::OverflowClear as u16 => Ok(TestOp::OverflowClear),
+            x if x == TestOp::NegativeSet as u16 => Ok(TestOp::NegativeSet),
             _ => Err(format!("unknown test op value {}", value)),
         }
     }
@@ -269,7 +294,7 @@ impl Nibble {
         if sgn == 0 {
             (self.value & 0xf) as i8
         } else {
-            unsafe { std::mem::transmute(self.value | 0xf0) }
+            (self.value | 0xf0) as i8
         }
     }
 }
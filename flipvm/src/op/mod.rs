@@ -62,6 +62,26 @@ pub enum Instruction {
     LoadByte(Register, Register, Register),
     #[opcode(0x13)]
     StoreByte(Register, Register, Register),
+
+    #[opcode(0x14)]
+    Call(Register), // push BP, push PC + 2, BP = SP, PC = R0
+    #[opcode(0x15)]
+    Ret, // SP = BP - 4, PC = RAM[BP - 2], BP = RAM[BP - 4]
+
+    // Signed word offsets, relative to the branch instruction
+    #[opcode(0x16)]
+    Branch(Literal10Bit),
+    #[opcode(0x17)]
+    BranchIf(Literal10Bit), // Taken when the compare flag is set
+    #[opcode(0x18)]
+    BranchIfNot(Literal10Bit), // Taken when the compare flag is unset
+
+    #[opcode(0x19)]
+    TrapReturn, // Pops the cause, flags and PC pushed on trap entry
+    #[opcode(0x1a)]
+    EnableInterrupts,
+    #[opcode(0x1b)]
+    DisableInterrupts,
 }
 
 pub trait InstructionPart {
@@ -109,6 +129,14 @@ mod test {
             Stack(B, SP, StackOp::Dup),
             LoadStackOffset(A, BP, Nibble::new_checked(0x3)?),
             System(A, B, Nibble::new_checked(0x3)?),
+            Call(C),
+            Ret,
+            Branch(Literal10Bit::from_signed(-3)?),
+            BranchIf(Literal10Bit::from_signed(200)?),
+            BranchIfNot(Literal10Bit::from_signed(-512)?),
+            TrapReturn,
+            EnableInterrupts,
+            DisableInterrupts,
         ];
         let encoded: Vec<_> = ops.iter().map(|x| x.encode_u16()).collect();
         for (l, r) in ops.iter().zip(encoded.iter()) {
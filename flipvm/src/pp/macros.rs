@@ -10,6 +10,8 @@ pub fn setup_std_macros(pp: &mut PreProcessor) {
     pp.define_macro("include", include);
     pp.define_macro("defmacro", defmacro);
     pp.define_macro("offsetPC", set_pc_offset);
+    pp.define_macro("global", global);
+    pp.define_macro("extern", extern_label);
 }
 
 fn defvar(pp: &mut PreProcessor, input: Vec<&str>) -> Result<Vec<String>, Error> {
@@ -77,3 +79,25 @@ fn set_pc_offset(pp: &mut PreProcessor, input: Vec<&str>) -> Result<Vec<String>,
     pp.instruction_count = offset;
     Ok(Vec::new())
 }
+
+fn global(pp: &mut PreProcessor, input: Vec<&str>) -> Result<Vec<String>, Error> {
+    if input.is_empty() {
+        return Err(Error::BadMacroFormat(".global <label>...".to_string()));
+    }
+
+    for label in input {
+        pp.define_global(label);
+    }
+    Ok(Vec::new())
+}
+
+fn extern_label(pp: &mut PreProcessor, input: Vec<&str>) -> Result<Vec<String>, Error> {
+    if input.is_empty() {
+        return Err(Error::BadMacroFormat(".extern <label>...".to_string()));
+    }
+
+    for label in input {
+        pp.define_extern(label);
+    }
+    Ok(Vec::new())
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 use std::fmt;
 
+use crate::op::Literal10Bit;
+use crate::SymbolMap;
+
 pub mod macros;
 
 pub enum Error {
@@ -52,21 +55,53 @@ pub struct ProcessedLine {
 enum ProcessedLinePart {
     Line(String),
     Unresolved(String, Box<ProcessedLinePart>, Box<ProcessedLinePart>),
+    UnresolvedRelative(String, Box<ProcessedLinePart>, Box<ProcessedLinePart>),
 }
 
+// a label referenced by a line, and whether the reference is relative to the line's address
+type LabelRef = (String, bool);
+
 impl ProcessedLinePart {
-    pub fn resolve(&self, pp: &PreProcessor) -> Result<String, Error> {
+    /// Resolves the line. When collecting `refs`, label references are left to the linker: they
+    /// are resolved as 0 and recorded instead.
+    fn resolve(
+        &self,
+        pp: &PreProcessor,
+        address: u32,
+        refs: &mut Option<Vec<LabelRef>>,
+    ) -> Result<String, Error> {
         match self {
             ProcessedLinePart::Line(s) => Ok(s.to_string()),
             ProcessedLinePart::Unresolved(varname, pre, post) => {
-                let value = pp
-                    .get_variable(varname)
-                    .ok_or(Error::UnknownToken(varname.to_string()))?;
+                let value = match refs {
+                    Some(refs) if pp.labels.lookup(varname).is_some() || pp.is_extern(varname) => {
+                        refs.push((varname.to_string(), false));
+                        "0".to_string()
+                    }
+                    _ => pp
+                        .get_variable(varname)
+                        .ok_or(Error::UnknownToken(varname.to_string()))?,
+                };
+                Ok(format!(
+                    "{} {} {}",
+                    pre.resolve(pp, address, refs)?,
+                    value,
+                    post.resolve(pp, address, refs)?
+                ))
+            }
+            ProcessedLinePart::UnresolvedRelative(varname, pre, post) => {
+                let value = match refs {
+                    Some(refs) if pp.get_variable(varname).is_none() && pp.is_extern(varname) => {
+                        refs.push((varname.to_string(), true));
+                        "0".to_string()
+                    }
+                    _ => pp.get_relative_offset(varname, address)?,
+                };
                 Ok(format!(
                     "{} {} {}",
-                    pre.resolve(pp)?,
+                    pre.resolve(pp, address, refs)?,
                     value,
-                    post.resolve(pp)?
+                    post.resolve(pp, address, refs)?
                 ))
             }
         }
@@ -82,7 +117,28 @@ impl ProcessedLine {
     }
 
     pub fn resolve(&self, pp: &PreProcessor) -> Result<String, Error> {
-        self.line.resolve(pp)
+        self.line
+            .resolve(pp, (self.source_line_number as u32) * 2, &mut None)
+    }
+
+    /// Resolves the line for a relocatable object, see `PreProcessor::set_relocatable`. Returns
+    /// the line with its label reference resolved as 0, and the reference to relocate.
+    pub fn resolve_relocatable(
+        &self,
+        pp: &PreProcessor,
+    ) -> Result<(String, Option<LabelRef>), Error> {
+        let mut refs = Some(Vec::new());
+        let line = self
+            .line
+            .resolve(pp, (self.source_line_number as u32) * 2, &mut refs)?;
+        let mut refs = refs.unwrap_or_default();
+        if refs.len() > 1 {
+            return Err(Error::Unexpected(format!(
+                "more than one label reference: {}",
+                line
+            )));
+        }
+        Ok((line, refs.pop()))
     }
 
     pub fn get_line_number(&self) -> usize {
@@ -93,6 +149,10 @@ impl ProcessedLine {
 pub struct PreProcessor {
     variables: HashMap<String, String>,
     macros: HashMap<String, Macro>,
+    labels: SymbolMap,
+    globals: Vec<String>,
+    externs: Vec<String>,
+    relocatable: bool,
     instruction_count: u32,
 }
 
@@ -107,6 +167,10 @@ impl PreProcessor {
         Self {
             variables: HashMap::new(),
             macros: HashMap::new(),
+            labels: SymbolMap::new(),
+            globals: Vec::new(),
+            externs: Vec::new(),
+            relocatable: false,
             instruction_count: 0,
         }
     }
@@ -174,6 +238,7 @@ impl PreProcessor {
                         let label = &head[1..];
                         let offset = self.instruction_count * 2;
                         self.define_variable(label, &offset.to_string());
+                        self.labels.insert(label, offset as u16);
                         continue;
                     }
                     _ => (),
@@ -212,9 +277,26 @@ impl PreProcessor {
     fn build_parts(&self, parts: Vec<&str>) -> ProcessedLinePart {
         let mut line: Vec<String> = Vec::new();
         for i in 0..parts.len() {
-            if let Some('!') = parts[i].chars().nth(0) {
+            if let Some('@') = parts[i].chars().nth(0) {
+                let varname = &parts[i][1..].to_string();
+                match self.get_relative_offset(varname, self.instruction_count * 2) {
+                    Ok(x) => line.push(x),
+                    Err(_) => {
+                        return ProcessedLinePart::UnresolvedRelative(
+                            varname.to_string(),
+                            Box::new(ProcessedLinePart::Line(line.join(" "))),
+                            Box::new(self.build_parts(parts[i + 1..].to_vec())),
+                        )
+                    }
+                }
+            } else if let Some('!') = parts[i].chars().nth(0) {
                 let varname = &parts[i][1..].to_string();
-                match self.get_variable(varname) {
+                // absolute label addresses are only known once linked
+                let value = match self.relocatable && self.labels.lookup(varname).is_some() {
+                    true => None,
+                    false => self.get_variable(varname),
+                };
+                match value {
                     Some(x) => line.push(x),
                     None => {
                         return ProcessedLinePart::Unresolved(
@@ -235,6 +317,60 @@ impl PreProcessor {
         self.variables.get(name).cloned()
     }
 
+    /// Returns the signed word offset from `address` to the label `name`.
+    fn get_relative_offset(&self, name: &str, address: u32) -> Result<String, Error> {
+        let value = self
+            .get_variable(name)
+            .ok_or(Error::UnknownToken(name.to_string()))?;
+        let target = value
+            .parse::<i64>()
+            .map_err(|_| Error::Unexpected(format!("{} is not an address: {}", name, value)))?;
+        let offset = (target - address as i64) / 2;
+        Literal10Bit::from_word_offset(offset)
+            .map_err(|e| Error::Unexpected(format!("@{}: {}", name, e)))?;
+        Ok(offset.to_string())
+    }
+
+    /// Assembles the following lines to run from `address`, like `.offsetPC` with a byte address.
+    pub fn set_origin(&mut self, address: u32) {
+        self.instruction_count = address / 2;
+    }
+
+    /// The address of every label defined so far.
+    pub fn labels(&self) -> &SymbolMap {
+        &self.labels
+    }
+
+    /// Assembles position independent code for an object file. Absolute label references are
+    /// left for the linker, as are references to labels declared with `.extern`.
+    pub fn set_relocatable(&mut self, relocatable: bool) {
+        self.relocatable = relocatable;
+    }
+
+    /// Exports `label` to other objects, as with `.global`.
+    pub fn define_global(&mut self, label: &str) {
+        if !self.globals.iter().any(|g| g == label) {
+            self.globals.push(label.to_string());
+        }
+    }
+
+    /// The labels exported with `.global`, in declaration order.
+    pub fn globals(&self) -> &[String] {
+        &self.globals
+    }
+
+    /// Declares `label` as defined by another object, as with `.extern`. Other labels that are
+    /// never defined are unknown tokens.
+    pub fn define_extern(&mut self, label: &str) {
+        if !self.is_extern(label) {
+            self.externs.push(label.to_string());
+        }
+    }
+
+    fn is_extern(&self, label: &str) -> bool {
+        self.externs.iter().any(|e| e == label)
+    }
+
     pub fn define_variable(&mut self, name: &str, value: &str) {
         self.variables.insert(name.to_string(), value.to_string());
     }
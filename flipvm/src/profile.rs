@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::op::Instruction;
+use crate::SymbolMap;
+
+/// Counts executed instructions per PC, per opcode and per call stack.
+///
+/// Call stacks are tracked through `Call`/`Ret` and trap entry/`TrapReturn`, each frame is the
+/// address its function was entered at. Stacks are interned as nodes of a tree so recording an
+/// instruction only bumps the count of the current node.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    total: u64,
+    pcs: HashMap<u16, u64>,
+    opcodes: HashMap<&'static str, u64>,
+    // the parent and entry address of each interned stack
+    nodes: Vec<(Option<usize>, u16)>,
+    node_ids: HashMap<(Option<usize>, u16), usize>,
+    stacks: HashMap<usize, u64>,
+    stack: Vec<usize>,
+}
+
+/// The name of an instruction without its operands.
+fn opcode_name(ins: &Instruction) -> &'static str {
+    match ins {
+        Instruction::Imm(..) => "Imm",
+        Instruction::Invalid => "Invalid",
+        Instruction::Add(..) => "Add",
+        Instruction::Sub(..) => "Sub",
+        Instruction::AddImm(..) => "AddImm",
+        Instruction::AddImmSigned(..) => "AddImmSigned",
+        Instruction::ShiftLeft(..) => "ShiftLeft",
+        Instruction::ShiftRightLogical(..) => "ShiftRightLogical",
+        Instruction::ShiftRightArithmetic(..) => "ShiftRightArithmetic",
+        Instruction::LoadWord(..) => "LoadWord",
+        Instruction::StoreWord(..) => "StoreWord",
+        Instruction::JumpOffset(..) => "JumpOffset",
+        Instruction::SetAndSave(..) => "SetAndSave",
+        Instruction::AddAndSave(..) => "AddAndSave",
+        Instruction::Test(..) => "Test",
+        Instruction::AddIf(..) => "AddIf",
+        Instruction::Stack(..) => "Stack",
+        Instruction::LoadStackOffset(..) => "LoadStackOffset",
+        Instruction::System(..) => "System",
+        Instruction::LoadByte(..) => "LoadByte",
+        Instruction::StoreByte(..) => "StoreByte",
+        Instruction::Call(..) => "Call",
+        Instruction::Ret => "Ret",
+        Instruction::Branch(..) => "Branch",
+        Instruction::BranchIf(..) => "BranchIf",
+        Instruction::BranchIfNot(..) => "BranchIfNot",
+        Instruction::TrapReturn => "TrapReturn",
+        Instruction::EnableInterrupts => "EnableInterrupts",
+        Instruction::DisableInterrupts => "DisableInterrupts",
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of the stack `parent` with a frame for `entry` on top.
+    fn intern(&mut self, parent: Option<usize>, entry: u16) -> usize {
+        if let Some(&id) = self.node_ids.get(&(parent, entry)) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push((parent, entry));
+        self.node_ids.insert((parent, entry), id);
+        id
+    }
+
+    /// The entry addresses of the stack `id`, outermost first.
+    fn frames(&self, id: usize) -> Vec<u16> {
+        let mut frames = Vec::new();
+        let mut node = Some(id);
+        while let Some(id) = node {
+            let (parent, entry) = self.nodes[id];
+            frames.push(entry);
+            node = parent;
+        }
+        frames.reverse();
+        frames
+    }
+
+    /// Records the instruction executed at `pc` in the current call stack.
+    pub fn record(&mut self, pc: u16, instruction: Option<&Instruction>) {
+        if self.stack.is_empty() {
+            self.push_frame(pc);
+        }
+        self.total += 1;
+        *self.pcs.entry(pc).or_default() += 1;
+        *self
+            .opcodes
+            .entry(instruction.map(opcode_name).unwrap_or("???"))
+            .or_default() += 1;
+        *self
+            .stacks
+            .entry(self.stack[self.stack.len() - 1])
+            .or_default() += 1;
+    }
+
+    /// Enters a function or handler at `entry`.
+    pub fn push_frame(&mut self, entry: u16) {
+        let id = self.intern(self.stack.last().copied(), entry);
+        self.stack.push(id);
+    }
+
+    /// Returns from the current function, the outermost frame is kept.
+    pub fn pop_frame(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn pc_count(&self, pc: u16) -> u64 {
+        self.pcs.get(&pc).copied().unwrap_or(0)
+    }
+
+    pub fn opcode_count(&self, opcode: &str) -> u64 {
+        self.opcodes.get(opcode).copied().unwrap_or(0)
+    }
+
+    fn frame_name(symbols: &SymbolMap, addr: u16) -> String {
+        match symbols.containing(addr) {
+            Some((name, base)) if base == addr => name.to_string(),
+            _ => format!("0x{:04x}", addr),
+        }
+    }
+
+    /// Self and inclusive instruction counts per function, sorted by inclusive count.
+    pub fn functions(&self, symbols: &SymbolMap) -> Vec<(String, u64, u64)> {
+        let mut counts: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for (&stack, &count) in &self.stacks {
+            let names: Vec<_> = self
+                .frames(stack)
+                .iter()
+                .map(|&a| Self::frame_name(symbols, a))
+                .collect();
+            if let Some(last) = names.last() {
+                counts.entry(last.clone()).or_default().0 += count;
+            }
+            // recursive frames count once towards inclusive time
+            let unique: HashSet<_> = names.into_iter().collect();
+            for name in unique {
+                counts.entry(name).or_default().1 += count;
+            }
+        }
+        let mut out: Vec<_> = counts
+            .into_iter()
+            .map(|(name, (own, inclusive))| (name, own, inclusive))
+            .collect();
+        out.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)));
+        out
+    }
+
+    /// Call stacks in the folded format used by flamegraph tools, `main;fib;fib 42`.
+    pub fn folded(&self, symbols: &SymbolMap) -> String {
+        let mut lines: Vec<_> = self
+            .stacks
+            .iter()
+            .map(|(&stack, count)| {
+                let names: Vec<_> = self
+                    .frames(stack)
+                    .iter()
+                    .map(|&a| Self::frame_name(symbols, a))
+                    .collect();
+                format!("{} {}\n", names.join(";"), count)
+            })
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+
+    /// A text report of the functions, opcodes and the `top` most executed addresses.
+    pub fn report(&self, symbols: &SymbolMap, top: usize) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "instructions executed: {}", self.total);
+        let percent = |n: u64| 100.0 * n as f64 / self.total.max(1) as f64;
+
+        let _ = writeln!(
+            out,
+            "\n{:>10} {:>7} {:>10} {:>7}  function",
+            "self", "%", "incl", "%"
+        );
+        for (name, own, inclusive) in self.functions(symbols) {
+            let _ = writeln!(
+                out,
+                "{:>10} {:>6.2}% {:>10} {:>6.2}%  {}",
+                own,
+                percent(own),
+                inclusive,
+                percent(inclusive),
+                name
+            );
+        }
+
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let _ = writeln!(out, "\n{:>10} {:>7}  opcode", "count", "%");
+        for (name, &count) in opcodes {
+            let _ = writeln!(out, "{:>10} {:>6.2}%  {}", count, percent(count), name);
+        }
+
+        let mut pcs: Vec<_> = self.pcs.iter().collect();
+        pcs.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let _ = writeln!(out, "\n{:>10} {:>7}  address", "count", "%");
+        for (&pc, &count) in pcs.into_iter().take(top) {
+            let _ = writeln!(
+                out,
+                "{:>10} {:>6.2}%  0x{:04X} {}",
+                count,
+                percent(count),
+                pc,
+                symbols.describe(pc)
+            );
+        }
+        out
+    }
+}
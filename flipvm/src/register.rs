@@ -6,6 +6,37 @@ use std::str::FromStr;
 pub enum Flag {
     Compare = 0b1,
     HasJumped = 0b10,
+    Zero = 0b100,
+    Carry = 0b1000,
+    Overflow = 0b10000,
+    Negative = 0b100000,
+    InterruptEnable = 0b1000000,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 7] = [
+        Flag::Compare,
+        Flag::HasJumped,
+        Flag::Zero,
+        Flag::Carry,
+        Flag::Overflow,
+        Flag::Negative,
+        Flag::InterruptEnable,
+    ];
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Compare => write!(f, "Compare"),
+            Self::HasJumped => write!(f, "HasJumped"),
+            Self::Zero => write!(f, "Zero"),
+            Self::Carry => write!(f, "Carry"),
+            Self::Overflow => write!(f, "Overflow"),
+            Self::Negative => write!(f, "Negative"),
+            Self::InterruptEnable => write!(f, "InterruptEnable"),
+        }
+    }
 }
 
 #[repr(u8)]
@@ -22,6 +53,17 @@ pub enum Register {
 }
 
 impl Register {
+    pub const ALL: [Register; 8] = [
+        Register::Zero,
+        Register::A,
+        Register::B,
+        Register::C,
+        Register::M,
+        Register::SP,
+        Register::PC,
+        Register::BP,
+    ];
+
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
             x if x == Register::A as u8 => Some(Register::A),
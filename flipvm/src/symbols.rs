@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Label addresses, stored as text with one `<address> <label>` per line.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SymbolMap {
+    by_name: BTreeMap<String, u16>,
+    by_addr: BTreeMap<u16, String>,
+}
+
+impl SymbolMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, addr: u16) {
+        self.by_name.insert(name.to_string(), addr);
+        self.by_addr.insert(addr, name.to_string());
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<u16> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn label_at(&self, addr: u16) -> Option<&str> {
+        self.by_addr.get(&addr).map(|s| s.as_str())
+    }
+
+    /// The closest label at or before `addr`.
+    pub fn containing(&self, addr: u16) -> Option<(&str, u16)> {
+        self.by_addr
+            .range(..=addr)
+            .next_back()
+            .map(|(&base, name)| (name.as_str(), base))
+    }
+
+    /// Formats `addr` relative to its containing label, e.g. `main+4`.
+    pub fn describe(&self, addr: u16) -> String {
+        match self.containing(addr) {
+            Some((name, base)) if base == addr => name.to_string(),
+            Some((name, base)) => format!("{}+{}", name, addr - base),
+            None => format!("0x{:04X}", addr),
+        }
+    }
+
+    /// Labels in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.by_addr
+            .iter()
+            .map(|(&addr, name)| (addr, name.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_addr.is_empty()
+    }
+}
+
+pub(crate) fn parse_address(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+impl FromStr for SymbolMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut out = Self::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let parts: Vec<_> = line.split_whitespace().collect();
+            match parts[..] {
+                [addr, name] => {
+                    let addr = parse_address(addr)
+                        .ok_or_else(|| format!("line {}: invalid address: {}", i + 1, addr))?;
+                    out.insert(name, addr);
+                }
+                _ => return Err(format!("line {}: expected <address> <label>", i + 1)),
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for SymbolMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (addr, name) in self.iter() {
+            writeln!(f, "0x{:04x} {}", addr, name)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! The standard syscall table.
+//!
+//! Syscalls are raised with `System` using the signal numbers below, for example
+//! `Imm C 0xf1; System C Zero 0`. Arguments are passed in `A` and `B` and results are returned
+//! in `A`.
+
+use std::cell::RefCell;
+use std::io::{ErrorKind, Read, Write};
+use std::rc::Rc;
+
+use crate::{Addressable, Machine, Register, VM};
+
+/// Halts the machine, `A` holds the exit code.
+pub const EXIT: u8 = 0xf0;
+/// Writes the low byte of `A`.
+pub const PUTCHAR: u8 = 0xf1;
+/// Writes `A` as a signed decimal number.
+pub const PRINT_INT: u8 = 0xf2;
+/// Reads one byte into `A`, `A = 0xffff` at end of input.
+pub const GETCHAR: u8 = 0xf3;
+/// Reads up to `B` bytes to the buffer at `A`, `A` = the number of bytes read.
+pub const READ: u8 = 0xf4;
+/// Writes `B` bytes from the buffer at `A`, `A` = the number of bytes written.
+pub const WRITE: u8 = 0xf5;
+
+/// Returned by `GETCHAR` at end of input.
+pub const EOF: u16 = 0xffff;
+
+fn io_error(name: &str, e: std::io::Error) -> String {
+    format!("{}: {}", name, e)
+}
+
+fn read_byte(input: &mut impl Read) -> Result<Option<u8>, std::io::Error> {
+    let mut buf = [0u8; 1];
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(buf[0])),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Installs the standard syscalls on `m`, reading from `input` and writing to `output`.
+pub fn install(m: &mut Machine, input: impl Read + 'static, output: impl Write + 'static) {
+    let input = Rc::new(RefCell::new(input));
+    let output = Rc::new(RefCell::new(output));
+
+    m.define_handler(EXIT, |vm: &mut VM, _| {
+        let code = vm.get_register(Register::A);
+        vm.exit(code);
+        Ok(())
+    });
+
+    let out = output.clone();
+    m.define_handler(PUTCHAR, move |vm: &mut VM, _| {
+        let c = vm.get_register(Register::A) as u8;
+        let mut out = out.borrow_mut();
+        out.write_all(&[c])
+            .and_then(|_| out.flush())
+            .map_err(|e| io_error("putchar", e))
+    });
+
+    let out = output.clone();
+    m.define_handler(PRINT_INT, move |vm: &mut VM, _| {
+        let value = vm.get_register(Register::A) as i16;
+        let mut out = out.borrow_mut();
+        write!(out, "{}", value)
+            .and_then(|_| out.flush())
+            .map_err(|e| io_error("print_int", e))
+    });
+
+    let inp = input.clone();
+    m.define_handler(GETCHAR, move |vm: &mut VM, _| {
+        let c = read_byte(&mut *inp.borrow_mut()).map_err(|e| io_error("getchar", e))?;
+        vm.set_register(Register::A, c.map(u16::from).unwrap_or(EOF));
+        Ok(())
+    });
+
+    m.define_handler(READ, move |vm: &mut VM, _| {
+        let addr = vm.get_register(Register::A);
+        let len = vm.get_register(Register::B);
+        let mut input = input.borrow_mut();
+        let mut count = 0;
+        while count < len {
+            match read_byte(&mut *input).map_err(|e| io_error("read", e))? {
+                Some(b) => vm
+                    .memory
+                    .write(addr.wrapping_add(count) as u32, b)
+                    .map_err(|e| format!("read: {}", e))?,
+                None => break,
+            }
+            count += 1;
+        }
+        vm.set_register(Register::A, count);
+        Ok(())
+    });
+
+    m.define_handler(WRITE, move |vm: &mut VM, _| {
+        let addr = vm.get_register(Register::A);
+        let len = vm.get_register(Register::B);
+        let buf = (0..len)
+            .map(|i| vm.memory.read(addr.wrapping_add(i) as u32))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("write: {}", e))?;
+        let mut out = output.borrow_mut();
+        out.write_all(&buf)
+            .and_then(|_| out.flush())
+            .map_err(|e| io_error("write", e))?;
+        vm.set_register(Register::A, len);
+        Ok(())
+    });
+}
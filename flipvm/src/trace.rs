@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use crate::op::Instruction;
+use crate::{Register, TrapCause, WatchHit};
+
+/// A register changed by a single step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterDelta {
+    pub register: Register,
+    pub before: u16,
+    pub after: u16,
+}
+
+/// One executed instruction.
+#[derive(Debug)]
+pub struct TraceStep {
+    pub pc: u16,
+    /// The raw instruction word, `None` when PC could not be read.
+    pub raw: Option<u16>,
+    /// The decoded instruction, `None` when the word is not a valid instruction.
+    pub instruction: Option<Instruction>,
+    /// Registers that changed, including PC.
+    pub deltas: Vec<RegisterDelta>,
+    /// Flags before and after the step.
+    pub flags: (u16, u16),
+}
+
+/// Observes execution of a `Machine`, see `Machine::set_tracer`.
+pub trait Tracer {
+    /// Called after every executed instruction, including ones that trapped.
+    fn step(&mut self, step: &TraceStep);
+
+    /// Called when a trap or interrupt handler is entered from `pc`.
+    fn trap(&mut self, _pc: u16, _cause: TrapCause) {}
+
+    /// Called for every access of a watched address.
+    fn watch(&mut self, _hit: &WatchHit) {}
+}
+
+/// Writes one JSON object per line for every step and trap.
+///
+/// `{"pc":0,"raw":"0x9005","op":"Imm A 5","regs":{"A":[0,5],"PC":[0,2]},"flags":[0,0]}`
+/// `{"pc":8,"trap":"interrupt"}`
+/// `{"pc":10,"watch":"write","addr":256,"value":3}`
+pub struct JsonTracer<W> {
+    out: W,
+}
+
+impl<W: Write> JsonTracer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl<W: Write> Tracer for JsonTracer<W> {
+    // Trace output is best effort, a failing writer must not stop the machine
+    fn step(&mut self, step: &TraceStep) {
+        let raw = match step.raw {
+            Some(raw) => format!("\"{:#06x}\"", raw),
+            None => "null".to_string(),
+        };
+        let op = match &step.instruction {
+            Some(ins) => json_string(&ins.to_string()),
+            None => "null".to_string(),
+        };
+        let regs: Vec<_> = step
+            .deltas
+            .iter()
+            .map(|d| format!("\"{}\":[{},{}]", d.register, d.before, d.after))
+            .collect();
+        let _ = writeln!(
+            self.out,
+            "{{\"pc\":{},\"raw\":{},\"op\":{},\"regs\":{{{}}},\"flags\":[{},{}]}}",
+            step.pc,
+            raw,
+            op,
+            regs.join(","),
+            step.flags.0,
+            step.flags.1
+        );
+    }
+
+    fn trap(&mut self, pc: u16, cause: TrapCause) {
+        let _ = writeln!(
+            self.out,
+            "{{\"pc\":{},\"trap\":{}}}",
+            pc,
+            json_string(&cause.to_string())
+        );
+    }
+
+    fn watch(&mut self, hit: &WatchHit) {
+        let _ = writeln!(
+            self.out,
+            "{{\"pc\":{},\"watch\":\"{}\",\"addr\":{},\"value\":{}}}",
+            hit.pc, hit.kind, hit.addr, hit.value
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// Index into the trap vector table, pushed as the cause word of a trap frame.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapCause {
+    IllegalInstruction,
+    MemoryFault,
+    UnknownSignal,
+    Interrupt,
+}
+
+impl TrapCause {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            x if x == TrapCause::IllegalInstruction as u16 => Some(TrapCause::IllegalInstruction),
+            x if x == TrapCause::MemoryFault as u16 => Some(TrapCause::MemoryFault),
+            x if x == TrapCause::UnknownSignal as u16 => Some(TrapCause::UnknownSignal),
+            x if x == TrapCause::Interrupt as u16 => Some(TrapCause::Interrupt),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TrapCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IllegalInstruction => write!(f, "illegal instruction"),
+            Self::MemoryFault => write!(f, "memory fault"),
+            Self::UnknownSignal => write!(f, "unknown signal"),
+            Self::Interrupt => write!(f, "interrupt"),
+        }
+    }
+}
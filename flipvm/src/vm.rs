@@ -1,9 +1,15 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{stdin, stdout, Read, Write};
 
-use crate::memory::{Addressable, MemoryMapper};
+use crate::coverage::Coverage;
+use crate::journal::{Journal, JournalEntry};
+use crate::memory::{Addressable, MemoryError, MemoryMapper, WatchHit, WatchKind};
 use crate::op::{Instruction, StackOp, TestOp};
+use crate::profile::Profiler;
 use crate::register::Flag;
-use crate::Register;
+use crate::trace::{RegisterDelta, TraceStep, Tracer};
+use crate::{syscall, Register, TrapCause};
 
 pub trait SignalHandler {
     fn handle(&self, m: &mut VM, arg: u16) -> Result<(), String>;
@@ -18,22 +24,78 @@ where
     }
 }
 
+/// Reason an instruction failed to execute. Traps can be handled by guest code through the trap
+/// vector table, host errors always stop the machine.
+#[derive(Debug)]
+pub enum Fault {
+    Trap(TrapCause, String),
+    Host(String),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Trap(cause, message) => write!(f, "{}: {}", cause, message),
+            Fault::Host(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<Fault> for String {
+    fn from(value: Fault) -> Self {
+        value.to_string()
+    }
+}
+
+impl From<MemoryError> for Fault {
+    fn from(value: MemoryError) -> Self {
+        Fault::Trap(TrapCause::MemoryFault, value.to_string())
+    }
+}
+
+/// Watched accesses kept for `take_watch_hits`, older ones are dropped past this.
+pub const MAX_WATCH_HITS: usize = 4096;
+
 #[derive(Default)]
 pub struct VM {
     registers: [u16; 8],
     flags: u16,
     pub halt: bool,
+    exit_code: Option<u16>,
     // TODO: Change This
     pub memory: MemoryMapper,
+    trap_vector: Option<u16>,
+    interrupt_pending: bool,
+    // the trap entered by the last step, if any
+    entered_trap: Option<TrapCause>,
+    journal: Option<Journal>,
+    watch_hits: Vec<WatchHit>,
+    // hits added by the last step
+    last_watch_hits: usize,
 }
 
 #[derive(Default)]
 pub struct Machine {
     signal_handlers: HashMap<u8, Box<dyn SignalHandler>>,
+    tracer: Option<Box<dyn Tracer>>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
     pub vm: VM,
 }
 
 impl Machine {
+    /// Creates a machine with the standard syscalls reading stdin and writing stdout.
+    pub fn with_std_syscalls() -> Self {
+        Self::with_syscalls(stdin(), stdout())
+    }
+
+    /// Creates a machine with the standard syscalls using the given host input and output.
+    pub fn with_syscalls(input: impl Read + 'static, output: impl Write + 'static) -> Self {
+        let mut m = Self::default();
+        syscall::install(&mut m, input, output);
+        m
+    }
+
     pub fn is_halted(&self) -> bool {
         self.vm.halt
     }
@@ -42,8 +104,110 @@ impl Machine {
         self.signal_handlers.insert(index, Box::new(f));
     }
 
-    pub fn step(&mut self) -> Result<(), String> {
-        self.vm.step(&self.signal_handlers)
+    /// Installs a tracer that observes every following step.
+    pub fn set_tracer(&mut self, tracer: Box<dyn Tracer>) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer>> {
+        self.tracer.take()
+    }
+
+    /// Starts counting executed instructions, replacing any previous profile.
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    /// Starts recording executed addresses and branch outcomes, replacing any previous record.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    pub fn step(&mut self) -> Result<(), Fault> {
+        if self.tracer.is_none() && self.profiler.is_none() && self.coverage.is_none() {
+            return self.vm.step(&self.signal_handlers);
+        }
+
+        let registers = Register::ALL.map(|r| self.vm.get_register(r));
+        let flags = self.vm.flags;
+        let pc = registers[Register::PC as usize];
+        let raw = self.vm.memory.fetch(pc as u32).ok();
+        let res = self.vm.step(&self.signal_handlers);
+        let instruction = raw.and_then(|r| Instruction::try_from(r).ok());
+        // an interrupt is dispatched instead of executing the instruction at PC
+        let executed = self.vm.entered_trap != Some(TrapCause::Interrupt);
+
+        if let Some(coverage) = self.coverage.as_mut().filter(|_| executed) {
+            coverage.record(pc, instruction.as_ref(), self.vm.get_register(Register::PC));
+        }
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            if executed {
+                profiler.record(pc, instruction.as_ref());
+            }
+            let next_pc = self.vm.get_register(Register::PC);
+            match (self.vm.entered_trap, &instruction) {
+                (Some(_), _) => profiler.push_frame(next_pc),
+                (None, Some(Instruction::Call(_))) if res.is_ok() => profiler.push_frame(next_pc),
+                (None, Some(Instruction::Ret | Instruction::TrapReturn)) if res.is_ok() => {
+                    profiler.pop_frame()
+                }
+                _ => (),
+            }
+        }
+
+        let tracer = match self.tracer.as_mut() {
+            Some(tracer) => tracer,
+            None => return res,
+        };
+        if executed {
+            let deltas: Vec<_> = Register::ALL
+                .iter()
+                .filter_map(|&r| {
+                    let before = registers[r as usize];
+                    let after = self.vm.get_register(r);
+                    (before != after).then_some(RegisterDelta {
+                        register: r,
+                        before,
+                        after,
+                    })
+                })
+                .collect();
+            tracer.step(&TraceStep {
+                pc,
+                raw,
+                instruction,
+                deltas,
+                flags: (flags, self.vm.flags),
+            });
+        }
+        if let Some(cause) = self.vm.entered_trap {
+            tracer.trap(pc, cause);
+        }
+        for hit in self.vm.last_watch_hits() {
+            tracer.watch(hit);
+        }
+        res
+    }
+
+    pub fn exit_code(&self) -> Option<u16> {
+        self.vm.exit_code
     }
 
     pub fn map(
@@ -59,6 +223,10 @@ impl Machine {
         self.vm.reset()
     }
 
+    pub fn set_trap_vector(&mut self, addr: u16) {
+        self.vm.set_trap_vector(addr)
+    }
+
     pub fn state(&self) -> String {
         self.vm.state()
     }
@@ -78,6 +246,34 @@ impl Machine {
     pub fn test_flag(&self, flag: Flag) -> bool {
         self.vm.test_flag(flag)
     }
+
+    pub fn enable_journal(&mut self, capacity: usize) {
+        self.vm.enable_journal(capacity)
+    }
+
+    pub fn step_back(&mut self, n: usize) -> Result<usize, String> {
+        self.vm.step_back(n)
+    }
+
+    pub fn step_back_to_write(&mut self, addr: u32) -> Result<Option<u16>, String> {
+        self.vm.step_back_to_write(addr)
+    }
+
+    pub fn add_watchpoint(&mut self, start: u32, len: u32, kind: WatchKind) {
+        self.vm.add_watchpoint(start, len, kind)
+    }
+
+    pub fn remove_watchpoint(&mut self, start: u32) -> bool {
+        self.vm.remove_watchpoint(start)
+    }
+
+    pub fn hit_watchpoint(&self) -> bool {
+        self.vm.hit_watchpoint()
+    }
+
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        self.vm.take_watch_hits()
+    }
 }
 
 impl VM {
@@ -94,16 +290,116 @@ impl VM {
         self.memory.map(start, size, a)
     }
 
+    /// Halts the machine with an exit status.
+    pub fn exit(&mut self, code: u16) {
+        self.halt = true;
+        self.exit_code = Some(code);
+    }
+
+    pub fn exit_code(&self) -> Option<u16> {
+        self.exit_code
+    }
+
+    /// Records the state before each step so the last `capacity` steps can be undone. Effects
+    /// on devices, such as console output, are not undone and undoing doesn't hit watchpoints.
+    pub fn enable_journal(&mut self, capacity: usize) {
+        self.journal = Some(Journal::new(capacity));
+    }
+
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// The number of steps that can be undone.
+    pub fn journal_len(&self) -> usize {
+        self.journal.as_ref().map(|j| j.len()).unwrap_or(0)
+    }
+
+    /// Undoes up to `n` steps, returning the number undone.
+    pub fn step_back(&mut self, n: usize) -> Result<usize, String> {
+        for i in 0..n {
+            let entry = match self.journal.as_mut().and_then(|j| j.pop()) {
+                Some(entry) => entry,
+                None => return Ok(i),
+            };
+            // devices are left as they are, writing them back would repeat their side effects
+            for &(addr, value) in entry.memory.iter().rev() {
+                self.memory.poke(addr, value);
+            }
+            self.registers = entry.registers;
+            self.flags = entry.flags;
+            self.halt = entry.halt;
+            self.exit_code = entry.exit_code;
+            self.interrupt_pending = entry.interrupt_pending;
+        }
+        Ok(n)
+    }
+
+    /// Runs backwards to just before the most recent recorded write of `addr`, returning the PC
+    /// of the writing instruction. The state is unchanged when no such write is recorded.
+    pub fn step_back_to_write(&mut self, addr: u32) -> Result<Option<u16>, String> {
+        let steps = match self.journal.as_ref().and_then(|j| j.steps_to_write(addr)) {
+            Some(steps) => steps,
+            None => return Ok(None),
+        };
+        self.step_back(steps)?;
+        Ok(Some(self.get_register(Register::PC)))
+    }
+
+    /// Watches `len` bytes from `start` for accesses of `kind`. Instruction fetches are not
+    /// watched.
+    pub fn add_watchpoint(&mut self, start: u32, len: u32, kind: WatchKind) {
+        self.memory.add_watchpoint(start, len, kind)
+    }
+
+    pub fn remove_watchpoint(&mut self, start: u32) -> bool {
+        self.memory.remove_watchpoint(start)
+    }
+
+    /// True when the last step accessed a watched address.
+    pub fn hit_watchpoint(&self) -> bool {
+        self.last_watch_hits > 0
+    }
+
+    /// The watched accesses made by the last step.
+    pub fn last_watch_hits(&self) -> &[WatchHit] {
+        &self.watch_hits[self.watch_hits.len() - self.last_watch_hits..]
+    }
+
+    /// Drains the recorded watched accesses, at most the last `MAX_WATCH_HITS`.
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        self.last_watch_hits = 0;
+        std::mem::take(&mut self.watch_hits)
+    }
+
+    /// Sets the address of the trap vector table. Entry `n` holds the handler address for
+    /// `TrapCause` `n`, a zero entry leaves the trap unhandled.
+    pub fn set_trap_vector(&mut self, addr: u16) {
+        self.trap_vector = Some(addr);
+    }
+
     pub fn reset(&mut self) {
         let _ = self.memory.zero_all();
         self.registers = [0; 8];
         self.halt = false;
         self.flags = 0;
+        self.interrupt_pending = false;
+        self.exit_code = None;
+        self.watch_hits.clear();
+        self.last_watch_hits = 0;
+        if let Some(journal) = self.journal.as_mut() {
+            journal.clear();
+        }
     }
 
     pub fn state(&self) -> String {
+        let flags: Vec<_> = Flag::ALL
+            .iter()
+            .filter(|&&flag| self.test_flag(flag))
+            .map(|flag| flag.to_string())
+            .collect();
         format!(
-            "A: {} | B: {} | C: {} | M: {} | SP: {} | PC: {} | BP: {} | Flags: {:016b}",
+            "A: {} | B: {} | C: {} | M: {} | SP: {} | PC: {} | BP: {} | Flags: [{}]",
             self.get_register(Register::A),
             self.get_register(Register::B),
             self.get_register(Register::C),
@@ -111,7 +407,7 @@ impl VM {
             self.get_register(Register::SP),
             self.get_register(Register::PC),
             self.get_register(Register::BP),
-            self.flags
+            flags.join(" ")
         )
     }
 
@@ -133,22 +429,22 @@ impl VM {
         }
     }
 
-    fn pop(&mut self, stack_pointer_register: Register) -> Result<u16, String> {
-        let sp = self.get_register(stack_pointer_register) - 2;
-        let v = self.memory.read2(sp as u32).map_err(|x| x.to_string())?;
+    fn pop(&mut self, stack_pointer_register: Register) -> Result<u16, MemoryError> {
+        let sp = self.get_register(stack_pointer_register).wrapping_sub(2);
+        let v = self.memory.read2(sp as u32)?;
         self.set_register(stack_pointer_register, sp);
         Ok(v)
     }
 
-    fn peek(&mut self, stack_pointer_register: Register) -> Result<u16, String> {
-        let sp = self.get_register(stack_pointer_register) - 2;
-        self.memory.read2(sp as u32).map_err(|x| x.to_string())
+    fn peek(&mut self, stack_pointer_register: Register) -> Result<u16, MemoryError> {
+        let sp = self.get_register(stack_pointer_register).wrapping_sub(2);
+        self.memory.read2(sp as u32)
     }
 
-    fn push(&mut self, stack_pointer_register: Register, v: u16) -> Result<(), String> {
+    fn push(&mut self, stack_pointer_register: Register, v: u16) -> Result<(), MemoryError> {
         let sp = self.get_register(stack_pointer_register);
-        self.set_register(stack_pointer_register, sp + 2);
-        self.memory.write2(sp as u32, v).map_err(|x| x.to_string())
+        self.set_register(stack_pointer_register, sp.wrapping_add(2));
+        self.memory.write2(sp as u32, v)
     }
 
     fn set_flag(&mut self, flag: Flag, state: bool) {
@@ -163,19 +459,181 @@ impl VM {
         self.flags & (flag as u16) != 0
     }
 
+    /// Jumps `word_offset` instructions relative to `pc`.
+    fn branch(&mut self, pc: u16, word_offset: i16) {
+        self.set_register(Register::PC, pc.wrapping_add((word_offset * 2) as u16));
+    }
+
+    fn set_result_flags(&mut self, result: u16, carry: bool, overflow: bool) {
+        self.set_flag(Flag::Zero, result == 0);
+        self.set_flag(Flag::Carry, carry);
+        self.set_flag(Flag::Overflow, overflow);
+        self.set_flag(Flag::Negative, result & 0x8000 != 0);
+    }
+
+    /// Adds two words, setting Zero, Carry, Overflow and Negative from the result.
+    fn add_with_flags(&mut self, a: u16, b: u16) -> u16 {
+        let (result, carry) = a.overflowing_add(b);
+        let (_, overflow) = (a as i16).overflowing_add(b as i16);
+        self.set_result_flags(result, carry, overflow);
+        result
+    }
+
+    /// Subtracts `b` from `a`, Carry is set when the subtraction borrows.
+    fn sub_with_flags(&mut self, a: u16, b: u16) -> u16 {
+        let (result, borrow) = a.overflowing_sub(b);
+        let (_, overflow) = (a as i16).overflowing_sub(b as i16);
+        self.set_result_flags(result, borrow, overflow);
+        result
+    }
+
+    /// Enters the handler for `cause`, pushing the faulting PC, the flags and the cause onto the
+    /// stack. Fails with the trap when no handler is installed.
+    fn trap(&mut self, pc: u16, cause: TrapCause, message: String) -> Result<(), Fault> {
+        let handler = match self.trap_handler(cause) {
+            Ok(Some(handler)) => handler,
+            Ok(None) => return Err(Fault::Trap(cause, message)),
+            Err(e) => {
+                return Err(Fault::Trap(
+                    cause,
+                    format!("{}: bad trap vector: {}", message, e),
+                ))
+            }
+        };
+        self.enter_trap(pc, cause, handler)
+            .map_err(|e| Fault::Trap(cause, format!("{}: double fault: {}", message, e)))
+    }
+
+    /// Looks up the handler address installed for `cause`, if any.
+    fn trap_handler(&self, cause: TrapCause) -> Result<Option<u16>, MemoryError> {
+        let vector = match self.trap_vector {
+            Some(vector) => vector,
+            None => return Ok(None),
+        };
+        let entry = vector.wrapping_add((cause as u16) * 2);
+        let handler = self.memory.read2(entry as u32)?;
+        Ok(if handler == 0 { None } else { Some(handler) })
+    }
+
+    fn enter_trap(&mut self, pc: u16, cause: TrapCause, handler: u16) -> Result<(), MemoryError> {
+        let flags = self.flags;
+        self.push(Register::SP, pc)?;
+        self.push(Register::SP, flags)?;
+        self.push(Register::SP, cause as u16)?;
+        self.set_flag(Flag::InterruptEnable, false);
+        self.set_register(Register::PC, handler);
+        self.entered_trap = Some(cause);
+        Ok(())
+    }
+
+    /// Dispatches a pending interrupt when interrupts are enabled and a handler is installed.
+    /// Returns true when the handler was entered.
+    fn dispatch_interrupt(&mut self) -> Result<bool, Fault> {
+        if !self.interrupt_pending || !self.test_flag(Flag::InterruptEnable) {
+            return Ok(false);
+        }
+
+        let handler = match self.trap_handler(TrapCause::Interrupt) {
+            Ok(Some(handler)) => handler,
+            Ok(None) => return Ok(false),
+            Err(e) => return Err(Fault::Host(format!("interrupt: bad trap vector: {}", e))),
+        };
+        self.interrupt_pending = false;
+        let pc = self.get_register(Register::PC);
+        self.enter_trap(pc, TrapCause::Interrupt, handler)
+            .map_err(|e| Fault::Host(format!("interrupt: double fault: {}", e)))?;
+        Ok(true)
+    }
+
     pub fn step(
         &mut self,
         signal_handlers: &HashMap<u8, Box<dyn SignalHandler>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), Fault> {
+        // accesses made between steps, e.g. by a debugger, are not program hits
+        self.memory.take_watch_hits();
+        let pc = self.get_register(Register::PC);
+
+        let res = if self.journal.is_none() {
+            self.step_inner(signal_handlers)
+        } else {
+            self.step_journaled(signal_handlers)
+        };
+
+        let hits = self.memory.take_watch_hits();
+        self.last_watch_hits = hits.len();
+        self.watch_hits
+            .extend(hits.into_iter().map(|(addr, kind, value)| WatchHit {
+                pc,
+                addr,
+                kind,
+                value,
+            }));
+        // trimmed in batches, the last step's hits are always kept
+        if self.watch_hits.len() >= MAX_WATCH_HITS * 2 {
+            let excess = self.watch_hits.len() - MAX_WATCH_HITS.max(self.last_watch_hits);
+            self.watch_hits.drain(..excess);
+        }
+        res
+    }
+
+    fn step_journaled(
+        &mut self,
+        signal_handlers: &HashMap<u8, Box<dyn SignalHandler>>,
+    ) -> Result<(), Fault> {
+        let entry = JournalEntry {
+            registers: self.registers,
+            flags: self.flags,
+            halt: self.halt,
+            exit_code: self.exit_code,
+            interrupt_pending: self.interrupt_pending,
+            memory: Vec::new(),
+        };
+        self.memory.begin_recording();
+        let res = self.step_inner(signal_handlers);
+        let memory = self.memory.end_recording();
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push(JournalEntry { memory, ..entry });
+        }
+        res
+    }
+
+    fn step_inner(
+        &mut self,
+        signal_handlers: &HashMap<u8, Box<dyn SignalHandler>>,
+    ) -> Result<(), Fault> {
+        self.entered_trap = None;
+        if self.dispatch_interrupt()? {
+            return Ok(());
+        }
+
         let pc = self.get_register(Register::PC);
-        let instruction = self.memory.read2(pc as u32).map_err(|x| x.to_string())?;
-        println!("PC: {}, instruction: {:#06X}", pc, instruction);
+        let res = match self.execute(pc, signal_handlers) {
+            Ok(()) => Ok(()),
+            Err(Fault::Trap(cause, message)) => self.trap(pc, cause, message),
+            Err(fault) => Err(fault),
+        };
+
+        if self.memory.tick() {
+            self.interrupt_pending = true;
+        }
+        res
+    }
+
+    fn execute(
+        &mut self,
+        pc: u16,
+        signal_handlers: &HashMap<u8, Box<dyn SignalHandler>>,
+    ) -> Result<(), Fault> {
+        let instruction = self.memory.fetch(pc as u32)?;
 
         self.set_flag(Flag::HasJumped, false);
-        let op = Instruction::try_from(instruction)?;
-        println!("executing: {}", op);
+        let op = Instruction::try_from(instruction)
+            .map_err(|e| Fault::Trap(TrapCause::IllegalInstruction, e))?;
         match op {
-            Instruction::Invalid => Err("0 instruction".to_string()),
+            Instruction::Invalid => Err(Fault::Trap(
+                TrapCause::IllegalInstruction,
+                "0 instruction".to_string(),
+            )),
             Instruction::Imm(r, v) => {
                 self.set_register(r, v.value);
                 Ok(())
@@ -183,29 +641,26 @@ impl VM {
             Instruction::Add(r0, r1, dst) => {
                 let v0 = self.get_register(r0);
                 let v1 = self.get_register(r1);
-                self.set_register(dst, v0 + v1);
+                let res = self.add_with_flags(v0, v1);
+                self.set_register(dst, res);
                 Ok(())
             }
             Instruction::Sub(r0, r1, dst) => {
                 let v0 = self.get_register(r0);
                 let v1 = self.get_register(r1);
-                self.set_register(dst, v0.wrapping_sub(v1));
+                let res = self.sub_with_flags(v0, v1);
+                self.set_register(dst, res);
                 Ok(())
             }
             Instruction::AddImm(r, l) => {
-                self.set_register(r, self.get_register(r) + (l.value as u16));
+                let res = self.add_with_flags(self.get_register(r), l.value as u16);
+                self.set_register(r, res);
                 Ok(())
             }
             Instruction::AddImmSigned(r, l) => {
-                let register_raw = self.get_register(r);
-                let imm_signed = l.as_signed();
-                unsafe {
-                    let register_signed: i16 = std::mem::transmute(register_raw);
-                    self.set_register(
-                        r,
-                        std::mem::transmute(register_signed + (imm_signed as i16)),
-                    );
-                }
+                let imm = l.as_signed() as i16 as u16;
+                let res = self.add_with_flags(self.get_register(r), imm);
+                self.set_register(r, res);
                 Ok(())
             }
             Instruction::ShiftLeft(r0, r1, offset) => {
@@ -219,21 +674,15 @@ impl VM {
                 Ok(())
             }
             Instruction::ShiftRightArithmetic(r0, r1, offset) => {
-                let base = self.get_register(r0);
-
-                let res: u16 = unsafe {
-                    let signed: i16 = std::mem::transmute(base);
-                    std::mem::transmute(signed >> (offset.value as u16))
-                };
-
-                self.set_register(r1, res);
+                let base = self.get_register(r0) as i16;
+                self.set_register(r1, (base >> (offset.value as u16)) as u16);
                 Ok(())
             }
             Instruction::LoadWord(r0, r1, r2) => {
                 let base = self.get_register(r1);
                 let page = self.get_register(r2);
                 let addr = (base as u32) + ((page as u32) << 16);
-                let w = self.memory.read2(addr).map_err(|x| x.to_string())?;
+                let w = self.memory.read2(addr)?;
                 self.set_register(r0, w);
                 Ok(())
             }
@@ -241,7 +690,7 @@ impl VM {
                 let base = self.get_register(r1);
                 let page = self.get_register(r2);
                 let addr = (base as u32) + ((page as u32) << 16);
-                let w = self.memory.read(addr).map_err(|x| x.to_string())?;
+                let w = self.memory.read(addr)?;
                 self.set_register(r0, w as u16);
                 Ok(())
             }
@@ -249,17 +698,16 @@ impl VM {
                 let base = self.get_register(r0);
                 let page = self.get_register(r1);
                 let addr = (base as u32) + ((page as u32) << 16);
-                self.memory
-                    .write2(addr, self.get_register(r2))
-                    .map_err(|x| x.to_string())
+                self.memory.write2(addr, self.get_register(r2))?;
+                Ok(())
             }
             Instruction::StoreByte(r0, r1, r2) => {
                 let base = self.get_register(r0);
                 let page = self.get_register(r1);
                 let addr = (base as u32) + ((page as u32) << 16);
                 self.memory
-                    .write(addr, (self.get_register(r2) & 0xff) as u8)
-                    .map_err(|x| x.to_string())
+                    .write(addr, (self.get_register(r2) & 0xff) as u8)?;
+                Ok(())
             }
             Instruction::JumpOffset(b) => {
                 self.set_register(Register::PC, self.get_register(Register::PC) + b.value);
@@ -291,6 +739,13 @@ impl VM {
                     TestOp::BothZero => v0 == 0 && v1 == 0,
                     TestOp::EitherNonZero => v0 != 0 || v1 != 0,
                     TestOp::BothNonZero => v0 != 0 && v1 != 0,
+                    TestOp::ZeroSet => self.test_flag(Flag::Zero),
+                    TestOp::ZeroClear => !self.test_flag(Flag::Zero),
+                    TestOp::CarrySet => self.test_flag(Flag::Carry),
+                    TestOp::CarryClear => !self.test_flag(Flag::Carry),
+                    TestOp::OverflowSet => self.test_flag(Flag::Overflow),
+                    TestOp::OverflowClear => !self.test_flag(Flag::Overflow),
+                    TestOp::NegativeSet => self.test_flag(Flag::Negative),
                 };
                 self.set_flag(Flag::Compare, res);
                 Ok(())
@@ -337,12 +792,14 @@ impl VM {
                     StackOp::Add => {
                         let a = self.pop(sp)?;
                         let b = self.pop(sp)?;
-                        self.push(sp, a + b)?;
+                        let res = self.add_with_flags(a, b);
+                        self.push(sp, res)?;
                     }
                     StackOp::Sub => {
                         let a = self.pop(sp)?;
                         let b = self.pop(sp)?;
-                        self.push(sp, a.wrapping_sub(b))?;
+                        let res = self.sub_with_flags(a, b);
+                        self.push(sp, res)?;
                     }
                 };
                 Ok(())
@@ -350,28 +807,86 @@ impl VM {
             Instruction::LoadStackOffset(target, sp, word_offset) => {
                 let base = self.get_register(sp);
                 let addr = base - ((word_offset.value as u16) * 2);
-                self.set_register(
-                    target,
-                    self.memory.read2(addr as u32).map_err(|x| x.to_string())?,
-                );
+                self.set_register(target, self.memory.read2(addr as u32)?);
+                Ok(())
+            }
+            Instruction::Branch(offset) => {
+                self.branch(pc, offset.as_signed());
+                Ok(())
+            }
+            // both consume the compare flag, taken or not
+            Instruction::BranchIf(offset) => {
+                if self.test_flag(Flag::Compare) {
+                    self.branch(pc, offset.as_signed());
+                }
+                self.set_flag(Flag::Compare, false);
+                Ok(())
+            }
+            Instruction::BranchIfNot(offset) => {
+                if !self.test_flag(Flag::Compare) {
+                    self.branch(pc, offset.as_signed());
+                }
+                self.set_flag(Flag::Compare, false);
+                Ok(())
+            }
+            Instruction::Call(r) => {
+                let target = self.get_register(r);
+                self.push(Register::SP, self.get_register(Register::BP))?;
+                self.push(Register::SP, pc + 2)?;
+                self.set_register(Register::BP, self.get_register(Register::SP));
+                self.set_register(Register::PC, target);
+                Ok(())
+            }
+            Instruction::TrapReturn => {
+                self.pop(Register::SP)?;
+                let flags = self.pop(Register::SP)?;
+                let return_addr = self.pop(Register::SP)?;
+                self.flags = flags;
+                self.set_register(Register::PC, return_addr);
+                Ok(())
+            }
+            Instruction::EnableInterrupts => {
+                self.set_flag(Flag::InterruptEnable, true);
+                Ok(())
+            }
+            Instruction::DisableInterrupts => {
+                self.set_flag(Flag::InterruptEnable, false);
+                Ok(())
+            }
+            Instruction::Ret => {
+                let bp = self.get_register(Register::BP);
+                let return_addr = self.memory.read2(bp.wrapping_sub(2) as u32)?;
+                let prev_bp = self.memory.read2(bp.wrapping_sub(4) as u32)?;
+                self.set_register(Register::SP, bp.wrapping_sub(4));
+                self.set_register(Register::BP, prev_bp);
+                self.set_register(Register::PC, return_addr);
                 Ok(())
             }
             Instruction::System(Register::Zero, arg_register, signal) => {
-                let sig = signal_handlers
-                    .get(&signal.value)
-                    .ok_or_else(|| format!("unknown signal: 0x{:X}", signal.value))?;
+                let sig = signal_handlers.get(&signal.value).ok_or_else(|| {
+                    Fault::Trap(
+                        TrapCause::UnknownSignal,
+                        format!("unknown signal: 0x{:X}", signal.value),
+                    )
+                })?;
                 let arg = self.get_register(arg_register);
-                sig.handle(self, arg)
+                sig.handle(self, arg).map_err(Fault::Host)
             }
             Instruction::System(sig, _, arg) => {
                 let value = self.get_register(sig);
                 if value > 0xff {
-                    Err(format!("unknown signal: 0x{:X}, must be <= 0xff", value))
+                    Err(Fault::Trap(
+                        TrapCause::UnknownSignal,
+                        format!("unknown signal: 0x{:X}, must be <= 0xff", value),
+                    ))
                 } else {
-                    let sig = signal_handlers
-                        .get(&(value as u8))
-                        .ok_or_else(|| format!("unknown signal: 0x{:X}", value))?;
-                    sig.handle(self, arg.value as u16)
+                    let sig = signal_handlers.get(&(value as u8)).ok_or_else(|| {
+                        Fault::Trap(
+                            TrapCause::UnknownSignal,
+                            format!("unknown signal: 0x{:X}", value),
+                        )
+                    })?;
+                    sig.handle(self, arg.value as u16).map_err(Fault::Host)
                 }
             }
         }?;
@@ -1,5 +1,6 @@
 use flipvm::op::Instruction::*;
-use flipvm::op::{Literal12Bit, Literal7Bit, Nibble};
+use flipvm::op::{Literal12Bit, Literal7Bit, Nibble, StackOp, TestOp};
+use flipvm::Flag;
 use flipvm::Register::*;
 
 use self::common::{init_machine, run, SIGHALT};
@@ -122,3 +123,98 @@ fn shift_right_arithmetic() {
     run(&mut m, &program).unwrap();
     assert_reg_eq!(m, C, 0xffdc);
 }
+
+#[test]
+fn add_sets_zero_and_carry() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(0xfff).unwrap()),
+        ShiftLeft(A, A, Nibble::new_checked(4).unwrap()),
+        AddImm(A, Literal7Bit::new_checked(0xf).unwrap()),
+        // 0xffff
+        Imm(B, Literal12Bit::new_checked(1).unwrap()),
+        Add(A, B, C),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, C, 0);
+    assert_flag_set!(m, Flag::Zero);
+    assert_flag_set!(m, Flag::Carry);
+    assert_flag_unset!(m, Flag::Overflow);
+    assert_flag_unset!(m, Flag::Negative);
+}
+
+#[test]
+fn add_sets_overflow_and_negative() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(0x7ff).unwrap()),
+        ShiftLeft(A, A, Nibble::new_checked(4).unwrap()),
+        AddImm(A, Literal7Bit::new_checked(0xf).unwrap()),
+        // 0x7fff
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 0x8000);
+    assert_flag_set!(m, Flag::Overflow);
+    assert_flag_set!(m, Flag::Negative);
+    assert_flag_unset!(m, Flag::Carry);
+    assert_flag_unset!(m, Flag::Zero);
+}
+
+#[test]
+fn sub_sets_borrow() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        Imm(B, Literal12Bit::new_checked(2).unwrap()),
+        Sub(A, B, C),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, C, 0xffff);
+    assert_flag_set!(m, Flag::Carry);
+    assert_flag_set!(m, Flag::Negative);
+    assert_flag_unset!(m, Flag::Overflow);
+    assert_flag_unset!(m, Flag::Zero);
+}
+
+#[test]
+fn stack_add_sets_flags() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(5).unwrap()),
+        Stack(A, SP, StackOp::Push),
+        Stack(A, SP, StackOp::Push),
+        Stack(Zero, SP, StackOp::Sub),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_flag_set!(m, Flag::Zero);
+    assert_flag_unset!(m, Flag::Carry);
+}
+
+#[test]
+fn add_32bit() {
+    // (A:B) = (A:B) + (C:M), low words in B and M
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        // 0x0001_fff0
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        Imm(B, Literal12Bit::new_checked(0xfff).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        // 0x0002_0020
+        Imm(C, Literal12Bit::new_checked(2).unwrap()),
+        Imm(M, Literal12Bit::new_checked(0x20).unwrap()),
+        Add(B, M, B),
+        Test(Zero, Zero, TestOp::CarryClear),
+        AddIf(PC, PC, Nibble::new_checked(2).unwrap()),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        Add(A, C, A),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 0x4);
+    assert_reg_eq!(m, B, 0x10);
+}
@@ -0,0 +1,85 @@
+use std::str::FromStr;
+
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit};
+use flipvm::pp::{macros, PreProcessor};
+use flipvm::Register::*;
+
+fn assemble(input: &str) -> Vec<Instruction> {
+    let mut pp = PreProcessor::new();
+    macros::setup_std_macros(&mut pp);
+    let lines = pp.resolve(input).map_err(String::from).unwrap();
+    lines
+        .iter()
+        .map(|line| line.resolve(&pp).map_err(String::from).unwrap())
+        .filter(|line| !line.starts_with(';'))
+        .map(|line| match Instruction::from_str(&line) {
+            Ok(ins) => ins,
+            Err(_) => panic!("failed to parse: {}", line),
+        })
+        .collect()
+}
+
+#[test]
+fn relative_labels() {
+    let program = assemble(
+        "
+        Imm A 1
+        :loop
+        BranchIf @out
+        Branch @loop
+        :out
+        Imm PC !loop
+        ",
+    );
+    assert_eq!(
+        program,
+        vec![
+            Imm(A, Literal12Bit::new_checked(1).unwrap()),
+            BranchIf(Literal10Bit::from_signed(2).unwrap()),
+            Branch(Literal10Bit::from_signed(-1).unwrap()),
+            Imm(PC, Literal12Bit::new_checked(2).unwrap()),
+        ]
+    );
+}
+
+#[test]
+fn origin() {
+    let mut pp = PreProcessor::new();
+    macros::setup_std_macros(&mut pp);
+    pp.set_origin(0x100);
+    let lines = pp
+        .resolve(
+            "
+            Imm A 1
+            :loop
+            BranchIf @loop
+            Imm PC !loop
+            ",
+        )
+        .map_err(String::from)
+        .unwrap();
+    let resolved: Vec<_> = lines
+        .iter()
+        .map(|line| line.resolve(&pp).map_err(String::from).unwrap())
+        .collect();
+    assert_eq!(
+        Instruction::from_str(&resolved[1]).ok(),
+        Some(BranchIf(Literal10Bit::from_signed(0).unwrap()))
+    );
+    assert_eq!(
+        Instruction::from_str(&resolved[2]).ok(),
+        Some(Imm(PC, Literal12Bit::new_checked(0x102).unwrap()))
+    );
+    assert_eq!(pp.labels().lookup("loop"), Some(0x102));
+}
+
+#[test]
+fn relative_label_out_of_range() {
+    let mut pp = PreProcessor::new();
+    macros::setup_std_macros(&mut pp);
+    let far = format!("Branch @out\n{}:out\n", "Imm A 1\n".repeat(600));
+    let lines = pp.resolve(&far).map_err(String::from).unwrap();
+    let err = lines[0].resolve(&pp).map_err(String::from).unwrap_err();
+    assert!(err.contains("out of range"), "{}", err);
+}
@@ -1,3 +1,10 @@
+// Not every test binary uses every helper
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
 use flipvm::op::Instruction;
 use flipvm::{Addressable, LinearMemory, Machine, Register, VM};
 
@@ -22,6 +29,21 @@ pub fn run(m: &mut Machine, program: &[Instruction]) -> Result<(), String> {
     Ok(())
 }
 
+/// Output written by a device or syscall, still readable by the test after it's been moved.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(pub Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 pub fn init_machine(mem_size: usize) -> Machine {
     let mut m = Machine::default();
     m.map(0x0, mem_size, Box::new(LinearMemory::new(mem_size)))
@@ -0,0 +1,85 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Literal10Bit, Literal12Bit, Literal7Bit, Nibble, TestOp};
+use flipvm::Register::*;
+use flipvm::{Addressable, Console};
+
+use self::common::{init_machine, run, SharedBuffer, SIGHALT};
+
+mod common;
+
+const CONSOLE: u16 = 0x1000;
+
+type TestConsole = Console<&'static [u8], SharedBuffer>;
+
+#[test]
+fn write_output() {
+    let output = SharedBuffer::default();
+    let mut m = init_machine(1024 * 4);
+    m.map(
+        CONSOLE as usize,
+        TestConsole::SIZE as usize,
+        Box::new(TestConsole::new(b"", output.clone())),
+    )
+    .unwrap();
+    let program = vec![
+        Imm(B, Literal12Bit::new_checked(CONSOLE >> 4).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        Imm(C, Literal12Bit::new_checked('h' as u16).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(C, Literal12Bit::new_checked('i' as u16).unwrap()),
+        StoreByte(B, Zero, C),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_eq!(output.0.borrow().as_slice(), b"hi");
+}
+
+#[test]
+fn echo_input() {
+    let output = SharedBuffer::default();
+    let mut m = init_machine(1024 * 4);
+    m.map(
+        CONSOLE as usize,
+        TestConsole::SIZE as usize,
+        Box::new(TestConsole::new(b"abc", output.clone())),
+    )
+    .unwrap();
+    // Copy input to output until a zero byte signals the end of input, counting bytes in A
+    let program = vec![
+        Imm(B, Literal12Bit::new_checked(CONSOLE >> 4).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        LoadWord(C, B, Zero),
+        Test(C, Zero, TestOp::BothZero),
+        BranchIf(Literal10Bit::from_signed(4).unwrap()),
+        StoreWord(B, Zero, C),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        Branch(Literal10Bit::from_signed(-5).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 3);
+    assert_eq!(output.0.borrow().as_slice(), b"abc");
+    assert_mem_eq!(
+        m,
+        CONSOLE as u32 + TestConsole::STATUS,
+        TestConsole::INPUT_EOF | TestConsole::OUTPUT_READY
+    );
+}
+
+#[test]
+fn peek_leaves_input() {
+    let console = TestConsole::new(b"xy", SharedBuffer::default());
+    // nothing is read ahead yet, so a peek can't tell and doesn't wait to find out
+    assert_eq!(console.peek(TestConsole::DATA), None);
+    assert_eq!(
+        console.peek(TestConsole::STATUS),
+        Some(TestConsole::OUTPUT_READY as u8)
+    );
+
+    let status = console.read(TestConsole::STATUS).unwrap() as u16;
+    assert_eq!(status, TestConsole::INPUT_READY | TestConsole::OUTPUT_READY);
+    assert_eq!(console.peek(TestConsole::DATA), Some(b'x'));
+    assert_eq!(console.peek2(TestConsole::DATA), Some('x' as u16));
+    assert_eq!(console.read(TestConsole::DATA).unwrap(), b'x');
+    assert_eq!(console.read(TestConsole::DATA).unwrap(), b'y');
+}
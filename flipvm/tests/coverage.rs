@@ -0,0 +1,35 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Literal10Bit, Literal12Bit, Literal7Bit, Nibble, TestOp};
+use flipvm::Register::*;
+
+use self::common::{init_machine, run, SIGHALT};
+
+mod common;
+
+#[test]
+fn hits_and_branches() {
+    let mut m = init_machine(1024 * 4);
+    m.enable_coverage();
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(3).unwrap()),
+        // Loop = 2
+        AddImmSigned(A, Literal7Bit::from_signed(-1).unwrap()),
+        Test(A, Zero, TestOp::Neq),
+        BranchIf(Literal10Bit::from_signed(-2).unwrap()),
+        Test(A, Zero, TestOp::Neq),
+        BranchIfNot(Literal10Bit::from_signed(2).unwrap()),
+        Invalid,
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+
+    let coverage = m.take_coverage().unwrap();
+    assert_eq!(coverage.hits(0), 1);
+    assert_eq!(coverage.hits(2), 3);
+    assert_eq!(coverage.hits(12), 0);
+    assert_eq!(coverage.branch(6), (2, 1));
+    assert_eq!(coverage.branch(10), (1, 0));
+    // not a branch
+    assert_eq!(coverage.branch(2), (0, 0));
+    assert_eq!(coverage.iter().count(), 7);
+}
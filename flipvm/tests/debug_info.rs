@@ -0,0 +1,61 @@
+use std::str::FromStr;
+
+use flipvm::{DebugInfo, LocalSlot, SourceLocation};
+
+const INFO: &str = "file fib.fl
+func 0x0000 0x000c __init
+func 0x000c 0x0030 main
+local 0 n
+func 0x0030 0x0040 fib
+local -6 n
+line 0x000c 1:5
+line 0x0012 2:9
+line 0x0030 6:5
+line 0x0036 7:8
+";
+
+#[test]
+fn round_trip() {
+    let info = DebugInfo::from_str(INFO).unwrap();
+    assert_eq!(info.to_string(), INFO);
+    assert_eq!(info.file.as_deref(), Some("fib.fl"));
+    assert_eq!(info.functions().len(), 3);
+    assert_eq!(
+        info.function("fib").unwrap().locals,
+        vec![LocalSlot {
+            name: "n".to_string(),
+            offset: -6
+        }]
+    );
+    assert_eq!(info.symbols().lookup("main"), Some(0xc));
+}
+
+#[test]
+fn lookup() {
+    let info = DebugInfo::from_str(INFO).unwrap();
+    assert_eq!(info.function_at(0x2e).unwrap().name, "main");
+    assert!(info.function_at(0x40).is_none());
+    assert_eq!(
+        info.location(0x14),
+        Some(SourceLocation { line: 2, column: 9 })
+    );
+    assert_eq!(
+        info.location(0x36),
+        Some(SourceLocation { line: 7, column: 8 })
+    );
+    // startup code has no source
+    assert_eq!(info.location(0x4), None);
+    // nor does anything past the last function
+    assert_eq!(info.location(0x40), None);
+
+    assert_eq!(info.describe(0x38).unwrap(), "fib.fl:7:8 in fib");
+    assert_eq!(info.describe(0x4).unwrap(), "in __init");
+    assert_eq!(DebugInfo::new().describe(0x4), None);
+}
+
+#[test]
+fn invalid() {
+    assert!(DebugInfo::from_str("local 0 n\n").is_err());
+    assert!(DebugInfo::from_str("line 0x10 7\n").is_err());
+    assert!(DebugInfo::from_str("func 0x10 main\n").is_err());
+}
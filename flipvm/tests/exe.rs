@@ -0,0 +1,177 @@
+use flipvm::exe::{self, Executable, FormatError, SectionKind};
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal12Bit, Nibble};
+use flipvm::Register::*;
+use flipvm::{Addressable, LinearMemory, Machine, SymbolMap, VM};
+
+mod common;
+
+fn encode(program: &[Instruction]) -> Vec<u8> {
+    program
+        .iter()
+        .flat_map(|i| i.encode_u16().to_le_bytes())
+        .collect()
+}
+
+fn signal_halt(vm: &mut VM, _: u16) -> Result<(), String> {
+    vm.halt = true;
+    Ok(())
+}
+
+fn executable() -> Executable {
+    let mut exe = Executable::new(0x102, 0x100);
+    exe.add_section(
+        SectionKind::Code,
+        0,
+        encode(&[
+            Invalid,
+            // entry
+            Imm(B, Literal12Bit::new_checked(0x200).unwrap()),
+            LoadWord(A, B, Zero),
+            Imm(C, Literal12Bit::new_checked(0x300).unwrap()),
+            LoadWord(C, C, Zero),
+            System(Zero, Zero, Nibble::new_checked(common::SIGHALT).unwrap()),
+        ]),
+    );
+    exe.add_section(SectionKind::Data, 0x100, vec![0x34, 0x12]);
+    exe.add_bss(0x200, 0x10);
+    let mut symbols = SymbolMap::new();
+    symbols.insert("start", 0x102);
+    exe.symbols = Some(symbols);
+    exe
+}
+
+fn machine() -> Machine {
+    let mut m = Machine::default();
+    // stack below the program
+    m.map(0x0, 0x100, Box::new(LinearMemory::new(0x100)))
+        .unwrap();
+    m.set_register(SP, 0x80);
+    m.define_handler(common::SIGHALT, signal_halt);
+    m
+}
+
+#[test]
+fn round_trip() {
+    let exe = executable();
+    let bytes = exe.to_bytes().unwrap();
+    assert!(bytes.starts_with(b"FLIP"));
+    assert_eq!(Executable::from_bytes(&bytes), Ok(exe));
+}
+
+#[test]
+fn invalid() {
+    let bytes = executable().to_bytes().unwrap();
+    assert_eq!(
+        Executable::from_bytes(&bytes[..bytes.len() - 3]),
+        Err(FormatError::Truncated)
+    );
+    assert_eq!(
+        Executable::from_bytes(&[0x05, 0x90]),
+        Err(FormatError::BadMagic)
+    );
+    let mut future = bytes.clone();
+    future[4] = 9;
+    assert_eq!(
+        Executable::from_bytes(&future),
+        Err(FormatError::UnsupportedVersion(9))
+    );
+    let mut bad_kind = bytes;
+    bad_kind[16] = 7;
+    assert_eq!(
+        Executable::from_bytes(&bad_kind),
+        Err(FormatError::InvalidSectionKind(7))
+    );
+}
+
+#[test]
+fn unencodable() {
+    let mut long_name = executable();
+    let name = "x".repeat(256);
+    long_name.symbols.as_mut().unwrap().insert(&name, 0x104);
+    assert_eq!(long_name.to_bytes(), Err(FormatError::NameTooLong(name)));
+
+    let mut overlapping = executable();
+    overlapping.add_bss(0x208, 0x10);
+    assert_eq!(
+        overlapping.to_bytes(),
+        Err(FormatError::OverlappingSections(0x200, 0x208))
+    );
+    assert!(overlapping.load(&mut machine()).is_err());
+
+    let mut outside = executable();
+    outside.entry = 0x200;
+    assert_eq!(
+        outside.to_bytes(),
+        Err(FormatError::EntryOutsideCode(0x200))
+    );
+}
+
+#[test]
+fn out_of_range() {
+    let mut high = executable();
+    high.load_address = 0xffffff00;
+    high.entry = 0xff02;
+    assert_eq!(high.to_bytes(), Err(FormatError::SectionOutOfRange(0)));
+    assert!(high.load(&mut machine()).is_err());
+
+    let mut bytes = executable().to_bytes().unwrap();
+    // load_address
+    bytes[8..12].copy_from_slice(&0xff00u32.to_le_bytes());
+    assert_eq!(
+        Executable::from_bytes(&bytes),
+        Err(FormatError::SectionOutOfRange(0x100))
+    );
+}
+
+#[test]
+fn load_over_memory() {
+    let mut m = Machine::default();
+    m.map(0x0, 0x1000, Box::new(LinearMemory::new(0x1000)))
+        .unwrap();
+    // the stack is above the program, in memory the sections are mapped over
+    m.set_register(SP, 0x800);
+    m.define_handler(common::SIGHALT, signal_halt);
+    executable().load(&mut m).unwrap();
+    m.vm.memory.write2(0x800, 0x55).unwrap();
+    assert_mem_eq!(m, 0x800, 0x55);
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_reg_eq!(m, A, 0x1234);
+}
+
+#[test]
+fn load() {
+    let mut m = machine();
+    let exe = exe::load(&mut m, executable().to_bytes().unwrap()).unwrap();
+    assert_eq!(exe.symbols.unwrap().lookup("start"), Some(0x102));
+    assert_reg_eq!(m, PC, 0x102);
+    assert_mem_eq!(m, 0x300, 0);
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_reg_eq!(m, A, 0x1234);
+    assert_reg_eq!(m, C, 0);
+}
+
+#[test]
+fn raw_fallback() {
+    let mut m = Machine::default();
+    m.map(0x1000, 0x100, Box::new(LinearMemory::new(0x100)))
+        .unwrap();
+    m.set_register(SP, 0x1000);
+    m.define_handler(common::SIGHALT, signal_halt);
+
+    let program = encode(&[
+        Imm(A, Literal12Bit::new_checked(7).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(common::SIGHALT).unwrap()),
+    ]);
+    let exe = exe::load(&mut m, program).unwrap();
+    assert_eq!(exe.entry, 0);
+    assert!(exe.symbols.is_none());
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_reg_eq!(m, A, 7);
+}
@@ -1,5 +1,7 @@
 use flipvm::op::Instruction::*;
-use flipvm::op::{Literal10Bit, Literal12Bit, Nibble, TestOp};
+use flipvm::op::{Literal10Bit, Literal12Bit, Literal7Bit, Nibble, StackOp, TestOp};
+use flipvm::Addressable;
+use flipvm::Flag;
 use flipvm::Register::*;
 
 use self::common::{init_machine, run, SIGHALT};
@@ -97,3 +99,80 @@ fn jump_and_link_add() {
     run(&mut m, &program).unwrap();
     assert_reg_eq!(m, B, 2);
 }
+
+#[test]
+fn call_ret() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(5).unwrap()),
+        Imm(BP, Literal12Bit::new_checked(0x100).unwrap()),
+        Imm(C, Literal12Bit::new_checked(14).unwrap()),
+        Call(C),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+        Invalid,
+        // func = 14
+        AddImm(A, Literal7Bit::new_checked(10).unwrap()),
+        Stack(A, SP, StackOp::Push),
+        Ret,
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 16);
+    assert_reg_eq!(m, SP, 1024 * 3);
+    assert_reg_eq!(m, BP, 0x100);
+}
+
+#[test]
+fn call_frame() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(BP, Literal12Bit::new_checked(0x100).unwrap()),
+        Imm(C, Literal12Bit::new_checked(8).unwrap()),
+        Call(C),
+        Invalid,
+        // func = 8
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, BP, 1024 * 3 + 4);
+    assert_mem_eq!(m, BP - 2, 6);
+    assert_mem_eq!(m, BP - 4, 0x100);
+}
+
+#[test]
+fn branch_relative() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(5).unwrap()),
+        // Loop = 2
+        AddImm(B, Literal7Bit::new_checked(2).unwrap()),
+        AddImmSigned(A, Literal7Bit::from_signed(-1).unwrap()),
+        Test(A, Zero, TestOp::Neq),
+        BranchIf(Literal10Bit::from_signed(-3).unwrap()),
+        Branch(Literal10Bit::from_signed(2).unwrap()),
+        Invalid,
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, B, 10);
+}
+
+#[test]
+fn branch_if_not() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(3).unwrap()),
+        Test(A, Zero, TestOp::Eq),
+        BranchIfNot(Literal10Bit::from_signed(3).unwrap()),
+        Invalid,
+        Invalid,
+        Test(A, Zero, TestOp::Neq),
+        BranchIfNot(Literal10Bit::from_signed(2).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+        Invalid,
+    ];
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, PC, 16);
+    // consumed even when not taken, like `BranchIf`
+    assert_flag_unset!(m, Flag::Compare);
+}
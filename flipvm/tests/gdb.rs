@@ -0,0 +1,186 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use flipvm::gdb::GdbStub;
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit, Literal7Bit, Nibble};
+use flipvm::syscall::EXIT;
+use flipvm::Register::*;
+use flipvm::{Addressable, LinearMemory, Machine};
+
+mod common;
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut buf = [0u8; 1];
+        self.stream.read_exact(&mut buf).unwrap();
+        buf[0]
+    }
+
+    fn send(&mut self, data: &str) {
+        let sum = data.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+        write!(self.stream, "${}#{:02x}", data, sum).unwrap();
+        assert_eq!(self.read_byte(), b'+', "packet {} not acked", data);
+    }
+
+    // Sends a packet and returns the reply data, checking acks and checksums
+    fn request(&mut self, data: &str) -> String {
+        self.send(data);
+        self.reply()
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                b => reply.push(b),
+            }
+        }
+        let mut sum = [0u8; 2];
+        self.stream.read_exact(&mut sum).unwrap();
+        let reply = String::from_utf8(reply).unwrap();
+        let expected = reply.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+        assert_eq!(
+            u8::from_str_radix(std::str::from_utf8(&sum).unwrap(), 16).unwrap(),
+            expected
+        );
+        self.stream.write_all(b"+").unwrap();
+        reply
+    }
+}
+
+// Runs `script` against a stub serving a machine loaded with `program`
+fn session(program: &[Instruction], script: impl FnOnce(&mut Client) + Send + 'static) -> Machine {
+    let mut m = Machine::with_syscalls(&b""[..], Vec::new());
+    m.map(0x0, 1024 * 4, Box::new(LinearMemory::new(1024 * 4)))
+        .unwrap();
+    for (i, ins) in program.iter().enumerate() {
+        m.vm.memory
+            .write2((i * 2) as u32, ins.encode_u16())
+            .unwrap();
+    }
+    m.set_register(SP, 1024 * 3);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut client = Client { stream };
+        script(&mut client);
+    });
+    let (stream, _) = listener.accept().unwrap();
+    stream.set_nodelay(true).unwrap();
+    GdbStub::new(&mut m, stream).serve().unwrap();
+    client.join().unwrap();
+    m
+}
+
+fn program() -> Vec<Instruction> {
+    vec![
+        Imm(A, Literal12Bit::new_checked(0x12).unwrap()),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        Imm(C, Literal12Bit::new_checked(EXIT as u16).unwrap()),
+        System(C, Zero, Nibble::new_checked(0).unwrap()),
+    ]
+}
+
+#[test]
+fn registers() {
+    let m = session(&program(), |c| {
+        assert_eq!(c.request("?"), "S05");
+        assert_eq!(c.request("s"), "S05");
+        // Zero A B C M SP PC BP
+        assert_eq!(c.request("g"), "00001200000000000000000c02000000");
+        assert_eq!(c.request("P2=3412"), "OK");
+        assert_eq!(c.request("p2"), "3412");
+        assert_eq!(c.request("p1"), "1200");
+        assert_eq!(c.request("D"), "OK");
+    });
+    assert_reg_eq!(m, B, 0x1234);
+    assert_reg_eq!(m, PC, 2);
+}
+
+#[test]
+fn memory() {
+    let m = session(&program(), |c| {
+        assert_eq!(c.request("m0,2"), "1290");
+        assert_eq!(c.request("M100,4:deadbeef"), "OK");
+        assert_eq!(c.request("m100,4"), "deadbeef");
+        assert_eq!(c.request("m0,801"), "E01");
+        c.send("k");
+    });
+    assert_mem_eq!(m, 0x100, 0xadde);
+}
+
+#[test]
+fn breakpoints_and_exit() {
+    let m = session(&program(), |c| {
+        assert_eq!(c.request("Z0,4,2"), "OK");
+        assert_eq!(c.request("c"), "S05");
+        assert_eq!(c.request("p6"), "0400");
+        assert_eq!(c.request("p1"), "1300");
+        assert_eq!(c.request("z0,4,2"), "OK");
+        assert_eq!(c.request("c"), "W14");
+        c.request("D");
+    });
+    assert_eq!(m.exit_code(), Some(0x14));
+}
+
+#[test]
+fn watchpoints() {
+    let program = vec![
+        Imm(B, Literal12Bit::new_checked(0x100).unwrap()),
+        Imm(A, Literal12Bit::new_checked(7).unwrap()),
+        StoreWord(B, Zero, A),
+        Imm(C, Literal12Bit::new_checked(EXIT as u16).unwrap()),
+        System(C, Zero, Nibble::new_checked(0).unwrap()),
+    ];
+    session(&program, |c| {
+        assert_eq!(c.request("Z2,100,2"), "OK");
+        assert_eq!(c.request("c"), "T05watch:100;");
+        assert_eq!(c.request("p6"), "0600");
+        assert_eq!(c.request("z2,100,2"), "OK");
+        assert_eq!(c.request("c"), "W07");
+        c.request("D");
+    });
+}
+
+#[test]
+fn interrupt() {
+    // spins until interrupted
+    let program = vec![Branch(Literal10Bit::from_signed(0).unwrap())];
+    session(&program, |c| {
+        c.send("c");
+        thread::sleep(Duration::from_millis(50));
+        c.stream.write_all(&[0x03]).unwrap();
+        assert_eq!(c.reply(), "S02");
+        assert_eq!(c.request("p6"), "0000");
+        c.request("D");
+    });
+}
+
+#[test]
+fn packet_while_running() {
+    let program = vec![Branch(Literal10Bit::from_signed(0).unwrap())];
+    session(&program, |c| {
+        c.send("c");
+        // a packet arriving while continuing is read by the interrupt polling
+        c.stream.write_all(b"$p6#a6").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        c.stream.write_all(&[0x03]).unwrap();
+        assert_eq!(c.reply(), "S02");
+        assert_eq!(c.read_byte(), b'+');
+        assert_eq!(c.reply(), "0000");
+        c.request("D");
+    });
+}
@@ -0,0 +1,129 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit, Literal7Bit, Nibble, TestOp};
+use flipvm::Register::*;
+use flipvm::{layout, Addressable, Flag, Timer, TrapCause};
+
+use self::common::{init_machine, run, SIGHALT};
+
+mod common;
+
+const TRAP_VECTOR: u16 = 0x800;
+const TIMER: u16 = 0x1000;
+
+// Installs the interrupt handler at `handler` and programs the timer with `reload` and `control`
+fn setup(handler: u16, reload: u16, control: u16) -> Vec<Instruction> {
+    vec![
+        Imm(
+            B,
+            Literal12Bit::new_checked(TRAP_VECTOR + (TrapCause::Interrupt as u16) * 2).unwrap(),
+        ),
+        Imm(C, Literal12Bit::new_checked(handler).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(B, Literal12Bit::new_checked(TIMER >> 4).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        Imm(C, Literal12Bit::new_checked(reload).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(C, Literal12Bit::new_checked(control).unwrap()),
+        AddImm(B, Literal7Bit::new_checked(Timer::CONTROL as u8).unwrap()),
+        StoreWord(B, Zero, C),
+    ]
+}
+
+// Counts interrupts in M
+fn count_handler() -> Vec<Instruction> {
+    vec![AddImm(M, Literal7Bit::new_checked(1).unwrap()), TrapReturn]
+}
+
+#[test]
+fn periodic_timer() {
+    let mut m = init_machine(1024 * 4);
+    m.map(TIMER as usize, Timer::SIZE as usize, Box::new(Timer::new()))
+        .unwrap();
+    m.set_trap_vector(TRAP_VECTOR);
+    let mut program = setup(30, 5, Timer::ENABLE | Timer::PERIODIC);
+    program.extend([
+        EnableInterrupts,
+        Imm(C, Literal12Bit::new_checked(3).unwrap()),
+        Test(M, C, TestOp::Neq),
+        BranchIf(Literal10Bit::from_signed(-1).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ]);
+    program.extend(count_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, M, 3);
+    assert_reg_eq!(m, SP, 1024 * 3);
+    assert_flag_set!(m, Flag::InterruptEnable);
+    assert_mem_eq!(m, TIMER as u32 + Timer::STATUS, Timer::FIRED);
+}
+
+#[test]
+fn one_shot_timer() {
+    let mut m = init_machine(1024 * 4);
+    m.map(TIMER as usize, Timer::SIZE as usize, Box::new(Timer::new()))
+        .unwrap();
+    m.set_trap_vector(TRAP_VECTOR);
+    let mut program = setup(36, 2, Timer::ENABLE);
+    program.push(EnableInterrupts);
+    program.extend((0..6).map(|_| AddImm(A, Literal7Bit::new_checked(1).unwrap())));
+    program.push(System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()));
+    program.extend(count_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, M, 1);
+    assert_reg_eq!(m, A, 6);
+    assert_mem_eq!(m, TIMER as u32 + Timer::CONTROL, 0);
+}
+
+#[test]
+fn interrupts_disabled() {
+    let mut m = init_machine(1024 * 4);
+    m.map(TIMER as usize, Timer::SIZE as usize, Box::new(Timer::new()))
+        .unwrap();
+    m.set_trap_vector(TRAP_VECTOR);
+    let mut program = setup(36, 2, Timer::ENABLE | Timer::PERIODIC);
+    program.extend((0..6).map(|_| AddImm(A, Literal7Bit::new_checked(1).unwrap())));
+    program.push(DisableInterrupts);
+    program.push(System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()));
+    program.extend(count_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, M, 0);
+    assert_reg_eq!(m, A, 6);
+    assert_flag_unset!(m, Flag::InterruptEnable);
+    assert_mem_eq!(m, TIMER as u32 + Timer::STATUS, Timer::FIRED);
+}
+
+#[test]
+fn std_layout_timer() {
+    let mut m = init_machine(1024 * 4);
+    layout::map_std(&mut m).unwrap();
+    let mut program = vec![
+        // install the handler at the end of the program
+        Imm(
+            B,
+            Literal12Bit::new_checked(layout::TRAP_VECTOR >> 4).unwrap(),
+        ),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        AddImm(
+            B,
+            Literal7Bit::new_checked(TrapCause::Interrupt as u8 * 2).unwrap(),
+        ),
+        Imm(C, Literal12Bit::new_checked(30).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(
+            B,
+            Literal12Bit::new_checked(layout::TIMER_ADDR as u16 >> 4).unwrap(),
+        ),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        Imm(C, Literal12Bit::new_checked(2).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(C, Literal12Bit::new_checked(Timer::ENABLE).unwrap()),
+        AddImm(B, Literal7Bit::new_checked(Timer::CONTROL as u8).unwrap()),
+        StoreWord(B, Zero, C),
+        EnableInterrupts,
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    program.extend(count_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, M, 1);
+    assert_reg_eq!(m, A, 1);
+}
@@ -0,0 +1,111 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal12Bit, Literal7Bit, Nibble, StackOp};
+use flipvm::Register::*;
+use flipvm::{Addressable, Console, Timer, WatchKind};
+
+use self::common::{init_machine, run, SharedBuffer, SIGHALT};
+
+mod common;
+
+fn program() -> Vec<Instruction> {
+    vec![
+        Imm(B, Literal12Bit::new_checked(0x100).unwrap()),
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        StoreWord(B, Zero, A),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        StoreWord(B, Zero, A),
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        Stack(A, SP, StackOp::Push),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ]
+}
+
+#[test]
+fn step_back() {
+    let mut m = init_machine(1024 * 4);
+    m.enable_journal(100);
+    run(&mut m, &program()).unwrap();
+    assert_eq!(m.step_back(1), Ok(1));
+    assert!(!m.is_halted());
+    assert_reg_eq!(m, PC, 14);
+
+    assert_eq!(m.step_back(3), Ok(3));
+    assert_reg_eq!(m, PC, 8);
+    assert_reg_eq!(m, A, 2);
+    assert_reg_eq!(m, SP, 1024 * 3);
+    assert_mem_eq!(m, 0x100, 1);
+
+    // replay forwards
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_reg_eq!(m, A, 3);
+    assert_mem_eq!(m, 0x100, 2);
+    assert_mem_eq!(m, 1024 * 3, 3);
+}
+
+#[test]
+fn step_back_to_write() {
+    let mut m = init_machine(1024 * 4);
+    m.enable_journal(100);
+    run(&mut m, &program()).unwrap();
+    assert_eq!(m.step_back_to_write(0x101), Ok(Some(8)));
+    assert_mem_eq!(m, 0x100, 1);
+    assert_eq!(m.step_back_to_write(0x100), Ok(Some(4)));
+    assert_mem_eq!(m, 0x100, 0);
+    assert_eq!(m.step_back_to_write(0x100), Ok(None));
+    assert_reg_eq!(m, PC, 4);
+}
+
+#[test]
+fn bounded() {
+    let mut m = init_machine(1024 * 4);
+    m.enable_journal(2);
+    run(&mut m, &program()).unwrap();
+    assert_eq!(m.step_back(5), Ok(2));
+    assert_reg_eq!(m, PC, 12);
+    assert_eq!(m.step_back_to_write(0x100), Ok(None));
+}
+
+#[test]
+fn step_back_leaves_devices() {
+    const TIMER: u16 = 0x1000;
+    const CONSOLE: u16 = 0x1010;
+    let output = SharedBuffer::default();
+    let mut m = init_machine(1024 * 4);
+    m.map(TIMER as usize, Timer::SIZE as usize, Box::new(Timer::new()))
+        .unwrap();
+    m.map(
+        CONSOLE as usize,
+        4,
+        Box::new(Console::new(&b""[..], output.clone())),
+    )
+    .unwrap();
+    m.enable_journal(100);
+    m.add_watchpoint(TIMER as u32, Timer::SIZE, WatchKind::Write);
+    let program = vec![
+        Imm(B, Literal12Bit::new_checked(CONSOLE >> 4).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        Imm(C, Literal12Bit::new_checked('h' as u16).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(B, Literal12Bit::new_checked(TIMER >> 4).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        Imm(C, Literal12Bit::new_checked(3).unwrap()),
+        StoreWord(B, Zero, C),
+        Imm(C, Literal12Bit::new_checked(Timer::ENABLE).unwrap()),
+        AddImm(B, Literal7Bit::new_checked(Timer::CONTROL as u8).unwrap()),
+        StoreWord(B, Zero, C),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    m.vm.memory.take_watch_hits();
+
+    assert_eq!(m.step_back(12), Ok(12));
+    assert_reg_eq!(m, PC, 0);
+    assert_eq!(output.0.borrow().as_slice(), b"h");
+    assert_mem_eq!(m, TIMER as u32 + Timer::CONTROL, Timer::ENABLE);
+    assert!(m.vm.memory.take_watch_hits().is_empty());
+
+    m.reset();
+    assert_eq!(m.vm.journal_len(), 0);
+}
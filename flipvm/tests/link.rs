@@ -0,0 +1,240 @@
+use flipvm::exe::{self, FormatError, SectionKind};
+use flipvm::obj::{self, LinkError, Object, RelocationKind};
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit, Nibble};
+use flipvm::pp::{macros, PreProcessor};
+use flipvm::Register::*;
+use flipvm::{Addressable, LinearMemory, Machine, VM};
+
+mod common;
+
+fn signal_halt(vm: &mut VM, _: u16) -> Result<(), String> {
+    vm.halt = true;
+    Ok(())
+}
+
+fn imm(value: u16) -> Literal12Bit {
+    Literal12Bit::new_checked(value).unwrap()
+}
+
+// start: loads `value` through an absolute reference and calls `double`
+fn caller() -> Object {
+    let mut obj = Object::from_instructions(&[
+        Imm(B, imm(0)),
+        LoadWord(A, B, Zero),
+        Imm(C, imm(0)),
+        Call(C),
+        System(Zero, Zero, Nibble::new_checked(common::SIGHALT).unwrap()),
+    ]);
+    obj.data = vec![0x15, 0x00];
+    obj.define("start", SectionKind::Code, 0, true);
+    obj.define("value", SectionKind::Data, 0, false);
+    obj.relocate(SectionKind::Code, 0, RelocationKind::Imm12, "value");
+    obj.relocate(SectionKind::Code, 4, RelocationKind::Imm12, "double");
+    obj
+}
+
+// double: A = A + A, branching to a local label that has to stay relative
+fn callee() -> Object {
+    let mut obj = Object::from_instructions(&[
+        Branch(Literal10Bit::from_signed(0).unwrap()),
+        Invalid,
+        Add(A, A, A),
+        Ret,
+    ]);
+    obj.bss = 4;
+    obj.define("double", SectionKind::Code, 0, true);
+    obj.define("add", SectionKind::Code, 4, false);
+    obj.define("scratch", SectionKind::Bss, 0, true);
+    obj.relocate(SectionKind::Code, 0, RelocationKind::Branch10, "add");
+    obj
+}
+
+fn run(image: Vec<u8>) -> Machine {
+    let mut m = Machine::default();
+    m.map(0x0, 0x100, Box::new(LinearMemory::new(0x100)))
+        .unwrap();
+    m.set_register(SP, 0x80);
+    m.define_handler(common::SIGHALT, signal_halt);
+    exe::load(&mut m, image).unwrap();
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    m
+}
+
+#[test]
+fn round_trip() {
+    let obj = caller();
+    let bytes = obj.to_bytes().unwrap();
+    assert!(Object::is_object(&bytes));
+    assert_eq!(Object::from_bytes(&bytes), Ok(obj));
+
+    assert_eq!(
+        Object::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(FormatError::Truncated)
+    );
+    let mut bad_kind = bytes;
+    let at = bad_kind.len() - "double".len() - 2;
+    bad_kind[at] = 9;
+    assert_eq!(
+        Object::from_bytes(&bad_kind),
+        Err(FormatError::InvalidRelocationKind(9))
+    );
+}
+
+#[test]
+fn undefined() {
+    assert_eq!(caller().undefined(), vec!["double"]);
+    assert!(callee().undefined().is_empty());
+}
+
+#[test]
+fn link() {
+    let exe = obj::link(&[caller(), callee()], 0x100, Some("start")).unwrap();
+    assert_eq!(exe.entry, 0x100);
+
+    let symbols = exe.symbols.as_ref().unwrap();
+    // code of both objects, then data, then bss
+    assert_eq!(symbols.lookup("double"), Some(0x10a));
+    assert_eq!(symbols.lookup("add"), Some(0x10e));
+    assert_eq!(symbols.lookup("value"), Some(0x112));
+    assert_eq!(symbols.lookup("scratch"), Some(0x114));
+
+    let code = &exe.sections[0];
+    let word = |at: usize| u16::from_le_bytes([code.data[at], code.data[at + 1]]);
+    assert_eq!(Instruction::try_from(word(0)), Ok(Imm(B, imm(0x112))));
+    assert_eq!(Instruction::try_from(word(4)), Ok(Imm(C, imm(0x10a))));
+    assert_eq!(
+        Instruction::try_from(word(10)),
+        Ok(Branch(Literal10Bit::from_signed(2).unwrap()))
+    );
+    assert_eq!(exe.sections[2].kind, SectionKind::Bss);
+    assert_eq!(exe.sections[2].size, 4);
+
+    let m = run(exe.to_bytes().unwrap());
+    assert_eq!(m.get_register(A), 0x2a);
+    assert_eq!(m.vm.memory.read2(0x114).unwrap(), 0);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        obj::link(&[caller()], 0x0, None),
+        Err(LinkError::UndefinedSymbol("double".to_string()))
+    );
+    assert_eq!(
+        obj::link(&[caller(), callee(), callee()], 0x0, None),
+        Err(LinkError::DuplicateSymbol("double".to_string()))
+    );
+    assert_eq!(
+        obj::link(&[caller(), callee()], 0x0, Some("add")),
+        Err(LinkError::UndefinedSymbol("add".to_string()))
+    );
+
+    // 0x1000 doesn't fit an Imm literal
+    let err = obj::link(&[caller(), callee()], 0x1000, None).unwrap_err();
+    assert!(matches!(err, LinkError::Relocation(ref s, 0x1000, _) if s == "value"));
+
+    // past the end of the address space, without overflowing
+    assert!(matches!(
+        obj::link(&[callee()], u32::MAX, None),
+        Err(LinkError::TooLarge(_))
+    ));
+    let mut huge = callee();
+    huge.bss = u32::MAX;
+    assert!(matches!(
+        obj::link(&[huge], 0x0, None),
+        Err(LinkError::TooLarge(_))
+    ));
+}
+
+#[test]
+fn local_symbols() {
+    let mut other = callee();
+    other.symbols[0].global = false;
+    other.symbols[2].global = false;
+    // another object's local `add` doesn't clash
+    let mut first = caller();
+    first.define("add", SectionKind::Code, 2, false);
+    assert_eq!(
+        obj::link(&[first, other], 0x0, None),
+        Err(LinkError::UndefinedSymbol("double".to_string()))
+    );
+}
+
+fn assemble(input: &str) -> Object {
+    let mut pp = PreProcessor::new();
+    macros::setup_std_macros(&mut pp);
+    pp.set_relocatable(true);
+    let lines = pp.resolve(input).map_err(String::from).unwrap();
+    let mut obj = Object::new();
+    let mut program = Vec::new();
+    for line in lines {
+        let (resolved, label_ref) = line.resolve_relocatable(&pp).map_err(String::from).unwrap();
+        let ins: Instruction = match resolved.parse() {
+            Ok(ins) => ins,
+            Err(_) => panic!("failed to parse: {}", resolved),
+        };
+        if let Some((label, relative)) = label_ref {
+            let kind = RelocationKind::for_instruction(&ins, relative).unwrap();
+            obj.relocate(SectionKind::Code, program.len() as u32 * 2, kind, &label);
+        }
+        program.push(ins);
+    }
+    obj.code = Object::from_instructions(&program).code;
+    for (addr, label) in pp.labels().iter() {
+        let global = pp.globals().iter().any(|g| g == label);
+        obj.define(label, SectionKind::Code, addr as u32, global);
+    }
+    obj
+}
+
+#[test]
+fn relocatable_assembly() {
+    let obj = assemble(
+        "
+        .global start
+        .extern double
+        :start
+        Imm C !double
+        Call C
+        BranchIf @start
+        Branch @exit
+        Imm PC !start
+        :exit
+        ",
+    );
+    assert_eq!(obj.code.len(), 10);
+    let relocs: Vec<_> = obj
+        .relocations
+        .iter()
+        .map(|r| (r.offset, r.kind, r.symbol.as_str()))
+        .collect();
+    assert_eq!(
+        relocs,
+        vec![
+            (0, RelocationKind::Imm12, "double"),
+            (8, RelocationKind::Imm12, "start"),
+        ]
+    );
+    assert_eq!(obj.undefined(), vec!["double"]);
+    assert!(obj.symbol("start").unwrap().global);
+    assert!(!obj.symbol("exit").unwrap().global);
+
+    let external = assemble(".extern elsewhere\nBranch @elsewhere");
+    assert_eq!(external.relocations[0].kind, RelocationKind::Branch10);
+    assert_eq!(external.undefined(), vec!["elsewhere"]);
+
+    // undeclared labels are typos, not references to other objects
+    let mut pp = PreProcessor::new();
+    macros::setup_std_macros(&mut pp);
+    pp.set_relocatable(true);
+    let lines = pp
+        .resolve(".extern double\nImm C !doubel\nBranch @elsewhere")
+        .map_err(String::from)
+        .unwrap();
+    for line in lines {
+        assert!(line.resolve_relocatable(&pp).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use std::str::FromStr;
+
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal12Bit, Literal7Bit, Nibble};
+use flipvm::Register::*;
+use flipvm::SymbolMap;
+
+use self::common::{init_machine, run, SIGHALT};
+
+mod common;
+
+fn program() -> Vec<Instruction> {
+    vec![
+        // main
+        Imm(C, Literal12Bit::new_checked(0x6).unwrap()),
+        Call(C),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+        // f
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        Ret,
+    ]
+}
+
+#[test]
+fn counts() {
+    let mut m = init_machine(1024 * 4);
+    m.enable_profiler();
+    run(&mut m, &program()).unwrap();
+    let profiler = m.profiler().unwrap();
+    assert_eq!(profiler.total(), 5);
+    assert_eq!(profiler.pc_count(0x6), 1);
+    assert_eq!(profiler.pc_count(0xa), 0);
+    assert_eq!(profiler.opcode_count("Call"), 1);
+    assert_eq!(profiler.opcode_count("Ret"), 1);
+    assert_eq!(profiler.opcode_count("Add"), 0);
+}
+
+#[test]
+fn functions() {
+    let mut m = init_machine(1024 * 4);
+    m.enable_profiler();
+    run(&mut m, &program()).unwrap();
+    let profiler = m.take_profiler().unwrap();
+    let symbols = SymbolMap::from_str("0x0 main\n0x6 f\n").unwrap();
+
+    assert_eq!(
+        profiler.functions(&symbols),
+        vec![("main".to_string(), 3, 5), ("f".to_string(), 2, 2)]
+    );
+    assert_eq!(profiler.folded(&symbols), "main 3\nmain;f 2\n");
+    assert!(profiler
+        .report(&symbols, 3)
+        .contains("instructions executed: 5"));
+
+    // unnamed frames fall back to their address
+    assert_eq!(
+        profiler.folded(&SymbolMap::new()),
+        "0x0000 3\n0x0000;0x0006 2\n"
+    );
+}
@@ -0,0 +1,89 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal12Bit, Nibble};
+use flipvm::syscall::{EOF, EXIT, GETCHAR, PRINT_INT, PUTCHAR, READ, WRITE};
+use flipvm::Register::*;
+use flipvm::{Addressable, LinearMemory, Machine, Register};
+
+use self::common::SharedBuffer;
+
+mod common;
+
+fn imm(r: Register, value: u16) -> Instruction {
+    Imm(r, Literal12Bit::new_checked(value).unwrap())
+}
+
+fn sys(signal: u8) -> Vec<Instruction> {
+    vec![
+        imm(C, signal as u16),
+        System(C, Zero, Nibble::new_checked(0).unwrap()),
+    ]
+}
+
+fn run_with_io(input: &'static [u8], program: &[Instruction]) -> (Machine, Vec<u8>) {
+    let output = SharedBuffer::default();
+    let mut m = Machine::with_syscalls(input, output.clone());
+    m.map(0x0, 1024 * 4, Box::new(LinearMemory::new(1024 * 4)))
+        .unwrap();
+    let program_bytes: Vec<u8> = program
+        .iter()
+        .flat_map(|x| x.encode_u16().to_le_bytes())
+        .collect();
+    m.vm.memory.load_from_vec(&program_bytes, 0).unwrap();
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    let out = output.0.borrow().clone();
+    (m, out)
+}
+
+#[test]
+fn print() {
+    let mut program = vec![imm(A, 'o' as u16)];
+    program.extend(sys(PUTCHAR));
+    program.extend([
+        imm(A, 0xfff),
+        ShiftLeft(A, A, Nibble::new_checked(4).unwrap()),
+    ]);
+    program.extend(sys(PRINT_INT));
+    program.extend(sys(EXIT));
+    let (_, output) = run_with_io(b"", &program);
+    assert_eq!(output, b"o-16");
+}
+
+#[test]
+fn input() {
+    let mut program = sys(GETCHAR);
+    program.push(Add(A, Zero, M));
+    program.extend(sys(GETCHAR));
+    program.push(Add(A, Zero, B));
+    program.extend(sys(GETCHAR));
+    program.extend(sys(EXIT));
+    let (m, _) = run_with_io(b"x", &program);
+    assert_reg_eq!(m, M, 'x' as u16);
+    assert_reg_eq!(m, B, EOF);
+    assert_reg_eq!(m, A, EOF);
+}
+
+#[test]
+fn buffers() {
+    let mut program = vec![imm(A, 0x200), imm(B, 4)];
+    program.extend(sys(READ));
+    program.extend([Add(A, Zero, M), Add(A, Zero, B), imm(A, 0x200)]);
+    program.extend(sys(WRITE));
+    program.extend(sys(EXIT));
+    let (m, output) = run_with_io(b"abc", &program);
+    assert_reg_eq!(m, M, 3);
+    assert_reg_eq!(m, A, 3);
+    assert_mem_eq!(m, 0x200, u16::from_le_bytes(*b"ab"));
+    assert_eq!(output, b"abc");
+    assert!(m.is_halted());
+}
+
+#[test]
+fn exit_code() {
+    let mut program = vec![imm(A, 42)];
+    program.extend(sys(EXIT));
+    program.push(Invalid);
+    let (m, _) = run_with_io(b"", &program);
+    assert_eq!(m.exit_code(), Some(42));
+}
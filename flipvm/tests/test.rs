@@ -1,5 +1,5 @@
 use flipvm::op::Instruction::*;
-use flipvm::op::{Literal12Bit, Nibble, TestOp};
+use flipvm::op::{Literal12Bit, Literal7Bit, Nibble, TestOp};
 use flipvm::Flag;
 use flipvm::Register::*;
 
@@ -170,3 +170,43 @@ fn both_nonzero() {
         Test(A, B, TestOp::BothNonZero)
     );
 }
+
+#[test]
+fn flags() {
+    let mut m = init_machine(1024 * 4);
+    test_set!(
+        m,
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        AddImmSigned(A, Literal7Bit::from_signed(-1).unwrap()),
+        Test(Zero, Zero, TestOp::ZeroSet)
+    );
+    m.reset();
+    test_set!(
+        m,
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        AddImmSigned(A, Literal7Bit::from_signed(-2).unwrap()),
+        Test(Zero, Zero, TestOp::NegativeSet)
+    );
+    m.reset();
+    test_unset!(
+        m,
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        Imm(B, Literal12Bit::new_checked(2).unwrap()),
+        Sub(B, A, C),
+        Test(Zero, Zero, TestOp::CarrySet)
+    );
+    m.reset();
+    test_set!(
+        m,
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        Imm(B, Literal12Bit::new_checked(2).unwrap()),
+        Sub(A, B, C),
+        Test(Zero, Zero, TestOp::CarrySet)
+    );
+    m.reset();
+    test_set!(
+        m,
+        Imm(A, Literal12Bit::new_checked(1).unwrap()),
+        Test(Zero, Zero, TestOp::OverflowClear)
+    );
+}
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use flipvm::op::Instruction::*;
+use flipvm::op::{Literal12Bit, Literal7Bit, Nibble};
+use flipvm::Register::*;
+use flipvm::{JsonTracer, RegisterDelta, TraceStep, Tracer, TrapCause};
+
+use self::common::{init_machine, run, SharedBuffer, SIGHALT};
+
+mod common;
+
+type Steps = Rc<RefCell<Vec<(u16, Vec<RegisterDelta>)>>>;
+
+#[derive(Default)]
+struct Recorder {
+    steps: Steps,
+    traps: Rc<RefCell<Vec<(u16, TrapCause)>>>,
+}
+
+impl Tracer for Recorder {
+    fn step(&mut self, step: &TraceStep) {
+        self.steps.borrow_mut().push((step.pc, step.deltas.clone()));
+    }
+
+    fn trap(&mut self, pc: u16, cause: TrapCause) {
+        self.traps.borrow_mut().push((pc, cause));
+    }
+}
+
+#[test]
+fn register_deltas() {
+    let recorder = Recorder::default();
+    let steps = recorder.steps.clone();
+    let mut m = init_machine(1024 * 4);
+    m.set_tracer(Box::new(recorder));
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(5).unwrap()),
+        AddImm(A, Literal7Bit::new_checked(2).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+
+    let steps = steps.borrow();
+    assert_eq!(steps.len(), 3);
+    assert_eq!(
+        steps[1],
+        (
+            2,
+            vec![
+                RegisterDelta {
+                    register: A,
+                    before: 5,
+                    after: 7
+                },
+                RegisterDelta {
+                    register: PC,
+                    before: 2,
+                    after: 4
+                },
+            ]
+        )
+    );
+}
+
+#[test]
+fn traps() {
+    let recorder = Recorder::default();
+    let traps = recorder.traps.clone();
+    let mut m = init_machine(1024 * 4);
+    m.set_trap_vector(0x800);
+    m.set_tracer(Box::new(recorder));
+    let program = vec![
+        Imm(B, Literal12Bit::new_checked(0x800).unwrap()),
+        Imm(C, Literal12Bit::new_checked(8).unwrap()),
+        StoreWord(B, Zero, C),
+        Invalid,
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+    assert_eq!(
+        traps.borrow().as_slice(),
+        &[(6, TrapCause::IllegalInstruction)]
+    );
+}
+
+#[test]
+fn json_lines() {
+    let output = SharedBuffer::default();
+    let mut m = init_machine(1024 * 4);
+    m.set_tracer(Box::new(JsonTracer::new(output.clone())));
+    let program = vec![
+        Imm(A, Literal12Bit::new_checked(5).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    run(&mut m, &program).unwrap();
+
+    let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{"pc":0,"raw":"0x9005","op":"Imm A 5","regs":{"A":[0,5],"PC":[0,2]},"flags":[0,0]}"#,
+            r#"{"pc":2,"raw":"0x00f1","op":"System Zero Zero 1","regs":{"PC":[2,4]},"flags":[0,0]}"#,
+        ]
+    );
+}
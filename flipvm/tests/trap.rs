@@ -0,0 +1,126 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal12Bit, Literal7Bit, Nibble, StackOp};
+use flipvm::Register::*;
+use flipvm::{layout, Fault, TrapCause};
+
+use self::common::{init_machine, run, SIGHALT};
+
+mod common;
+
+const TRAP_VECTOR: u16 = 0x800;
+
+// Skips the faulting instruction, counts the trap in A and leaves the cause in M
+fn skip_handler() -> Vec<Instruction> {
+    vec![
+        Stack(M, SP, StackOp::Pop),
+        LoadStackOffset(C, SP, Nibble::new_checked(2).unwrap()),
+        AddImm(C, Literal7Bit::new_checked(2).unwrap()),
+        Add(SP, Zero, B),
+        AddImmSigned(B, Literal7Bit::from_signed(-4).unwrap()),
+        StoreWord(B, Zero, C),
+        Stack(M, SP, StackOp::Push),
+        AddImm(A, Literal7Bit::new_checked(10).unwrap()),
+        TrapReturn,
+    ]
+}
+
+fn install_handler(cause: TrapCause, handler: u16) -> Vec<Instruction> {
+    vec![
+        Imm(
+            B,
+            Literal12Bit::new_checked(TRAP_VECTOR + (cause as u16) * 2).unwrap(),
+        ),
+        Imm(C, Literal12Bit::new_checked(handler).unwrap()),
+        StoreWord(B, Zero, C),
+    ]
+}
+
+#[test]
+fn illegal_instruction() {
+    let mut m = init_machine(1024 * 4);
+    m.set_trap_vector(TRAP_VECTOR);
+    let mut program = install_handler(TrapCause::IllegalInstruction, 14);
+    program.extend([
+        Invalid,
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+        Invalid,
+    ]);
+    program.extend(skip_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 11);
+    assert_reg_eq!(m, M, TrapCause::IllegalInstruction as u16);
+    assert_reg_eq!(m, SP, 1024 * 3);
+}
+
+#[test]
+fn memory_fault() {
+    let mut m = init_machine(1024 * 4);
+    m.set_trap_vector(TRAP_VECTOR);
+    let mut program = install_handler(TrapCause::MemoryFault, 14);
+    program.extend([
+        Imm(B, Literal12Bit::new_checked(0xfff).unwrap()),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        LoadWord(A, B, Zero),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ]);
+    program.extend(skip_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 10);
+    assert_reg_eq!(m, M, TrapCause::MemoryFault as u16);
+}
+
+#[test]
+fn unknown_signal() {
+    let mut m = init_machine(1024 * 4);
+    m.set_trap_vector(TRAP_VECTOR);
+    let mut program = install_handler(TrapCause::UnknownSignal, 14);
+    program.extend([
+        System(Zero, Zero, Nibble::new_checked(0x7).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+        Invalid,
+        Invalid,
+    ]);
+    program.extend(skip_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 10);
+    assert_reg_eq!(m, M, TrapCause::UnknownSignal as u16);
+}
+
+#[test]
+fn unhandled() {
+    let mut m = init_machine(1024 * 4);
+    let program = vec![Invalid];
+    assert!(run(&mut m, &program).is_err());
+    assert!(matches!(
+        m.step(),
+        Err(Fault::Trap(TrapCause::IllegalInstruction, _))
+    ));
+
+    // Vector table installed without an entry for the cause
+    let mut m = init_machine(1024 * 4);
+    m.set_trap_vector(TRAP_VECTOR);
+    assert!(run(&mut m, &program).is_err());
+}
+
+#[test]
+fn std_layout_trap_vector() {
+    let mut m = init_machine(1024 * 4);
+    layout::map_std(&mut m).unwrap();
+    let mut program = vec![
+        Imm(
+            B,
+            Literal12Bit::new_checked(layout::TRAP_VECTOR >> 4).unwrap(),
+        ),
+        ShiftLeft(B, B, Nibble::new_checked(4).unwrap()),
+        Imm(C, Literal12Bit::new_checked(14).unwrap()),
+        StoreWord(B, Zero, C),
+        Invalid,
+        AddImm(A, Literal7Bit::new_checked(1).unwrap()),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ];
+    program.extend(skip_handler());
+    run(&mut m, &program).unwrap();
+    assert_reg_eq!(m, A, 11);
+    assert_reg_eq!(m, M, TrapCause::IllegalInstruction as u16);
+}
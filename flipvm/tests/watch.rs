@@ -0,0 +1,137 @@
+use flipvm::op::Instruction::*;
+use flipvm::op::{Instruction, Literal10Bit, Literal12Bit, Literal7Bit, Nibble};
+use flipvm::Register::*;
+use flipvm::{
+    Addressable, LinearMemory, Machine, MappedMemoryBuffer, WatchHit, WatchKind, MAX_WATCH_HITS, VM,
+};
+
+use self::common::{init_machine, run, SIGHALT};
+
+mod common;
+
+fn stores() -> Vec<Instruction> {
+    vec![
+        Imm(B, Literal12Bit::new_checked(0x100).unwrap()),
+        Imm(A, Literal12Bit::new_checked(0x201).unwrap()),
+        StoreWord(B, Zero, A),
+        LoadWord(C, B, Zero),
+        AddImm(B, Literal7Bit::new_checked(2).unwrap()),
+        StoreByte(B, Zero, A),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ]
+}
+
+#[test]
+fn write() {
+    let mut m = init_machine(1024 * 4);
+    m.add_watchpoint(0x101, 2, WatchKind::Write);
+    run(&mut m, &stores()).unwrap();
+    assert_eq!(
+        m.take_watch_hits(),
+        vec![
+            WatchHit {
+                pc: 4,
+                addr: 0x101,
+                kind: WatchKind::Write,
+                value: 0x2
+            },
+            WatchHit {
+                pc: 10,
+                addr: 0x102,
+                kind: WatchKind::Write,
+                value: 0x1
+            },
+        ]
+    );
+}
+
+#[test]
+fn access_pauses() {
+    let mut m = init_machine(1024 * 4);
+    m.add_watchpoint(0x100, 1, WatchKind::Access);
+    let program: Vec<_> = stores().iter().map(|x| x.encode_u16()).collect();
+    for (i, w) in program.iter().enumerate() {
+        m.vm.memory.write2((i * 2) as u32, *w).unwrap();
+    }
+
+    m.define_handler(SIGHALT, |vm: &mut VM, _| {
+        vm.halt = true;
+        Ok(())
+    });
+
+    let mut pauses = Vec::new();
+    while !m.is_halted() {
+        m.step().unwrap();
+        if m.hit_watchpoint() {
+            pauses.push(m.get_register(PC));
+        }
+    }
+    assert_eq!(pauses, vec![6, 8]);
+    let kinds: Vec<_> = m.take_watch_hits().iter().map(|h| h.kind).collect();
+    assert_eq!(kinds, vec![WatchKind::Write, WatchKind::Read]);
+    assert!(m.remove_watchpoint(0x100));
+}
+
+#[test]
+fn program_image() {
+    // data word stored after the code in a read only image
+    let mut program: Vec<u8> = [
+        Imm(B, Literal12Bit::new_checked(6).unwrap()),
+        LoadWord(A, B, Zero),
+        System(Zero, Zero, Nibble::new_checked(SIGHALT).unwrap()),
+    ]
+    .iter()
+    .flat_map(|x| x.encode_u16().to_le_bytes())
+    .collect();
+    program.extend([0x34, 0x12]);
+
+    let mut m = Machine::default();
+    m.map(
+        0x0,
+        program.len(),
+        Box::new(MappedMemoryBuffer::new(program)),
+    )
+    .unwrap();
+    m.map(0x1000, 0x1000, Box::new(LinearMemory::new(0x1000)))
+        .unwrap();
+    m.define_handler(SIGHALT, |vm: &mut VM, _| {
+        vm.halt = true;
+        Ok(())
+    });
+    // instruction fetches are not watched
+    m.add_watchpoint(0x0, 8, WatchKind::Read);
+    while !m.is_halted() {
+        m.step().unwrap();
+    }
+    assert_reg_eq!(m, A, 0x1234);
+    let hits: Vec<_> = m.take_watch_hits().iter().map(|h| (h.pc, h.addr)).collect();
+    assert_eq!(hits, vec![(2, 6), (2, 7)]);
+}
+
+#[test]
+fn hits_are_capped_and_reset() {
+    let mut m = init_machine(1024 * 4);
+    m.add_watchpoint(0x100, 1, WatchKind::Write);
+    let program = [
+        Imm(B, Literal12Bit::new_checked(0x100).unwrap()),
+        StoreByte(B, Zero, A),
+        Branch(Literal10Bit::from_signed(-1).unwrap()),
+    ];
+    for (i, ins) in program.iter().enumerate() {
+        m.vm.memory
+            .write2((i * 2) as u32, ins.encode_u16())
+            .unwrap();
+    }
+    for _ in 0..MAX_WATCH_HITS * 6 {
+        m.step().unwrap();
+    }
+    let hits = m.take_watch_hits();
+    assert!(hits.len() >= MAX_WATCH_HITS && hits.len() < MAX_WATCH_HITS * 2);
+
+    m.step().unwrap();
+    m.step().unwrap();
+    assert!(m.hit_watchpoint());
+    m.reset();
+    assert!(!m.hit_watchpoint());
+    assert!(m.take_watch_hits().is_empty());
+}